@@ -0,0 +1,79 @@
+//
+//  osc_bench.rs
+//	Musical Sound Generator Framework
+//      Oscillator Benchmark
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  cargo bench --bench osc_bench
+//  Plays VOICES notes of a va tone with each osc.algorithm and osc.wv_type,
+//  and prints the CPU load of one voice to real time.
+//  "PWM" modulates the pulse width by LFO, "Sync" adds the master oscillator.
+//  Tones of several oscillators are measured as they are.
+//
+use std::time::Instant;
+use msgf::msgf_if;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BLOCK_SIZE: usize = 256;
+const SECONDS: usize = 4;
+const VOICES: usize = 16;
+const ALGORITHMS: [&str; 4] = ["Additive", "PolyBlep", "Blit", "Wavetable"];
+//  name, wv_type, other parameters
+type Params = &'static [(&'static str, f32)];
+const WAVES: [(&str, f32, Params); 5] = [
+    ("Saw", 1.0, &[]),
+    ("Square", 2.0, &[]),
+    ("Pulse", 3.0, &[]),
+    ("PWM", 3.0, &[("osc.pulse_width", 0.5), ("osc.pwm_lfo", 0.4), ("lfo.freq", 3.0)]),
+    ("Sync", 1.0, &[("osc.sync", 1.0), ("osc.sync_pitch", 700.0)]),
+];
+const TONES: [(&str, u8); 2] = [("Super Saw", 6), ("Thick Bass", 7)];
+
+fn measure(program: u8, prms: &[(&str, f32)]) -> (f64, f32) {
+    let mut msgf = msgf_if::Msgf::new(SAMPLE_RATE, BLOCK_SIZE);
+    msgf.init();
+    let mut abuf_l = vec![0.0_f32; BLOCK_SIZE];
+    let mut abuf_r = vec![0.0_f32; BLOCK_SIZE];
+    msgf.recieve_midi_message(0xc0, program, 0);
+    msgf.recieve_midi_message(0xb0, 91, 0);     //  no reverb send
+    msgf.process(&mut abuf_l, &mut abuf_r, BLOCK_SIZE as u32);
+    for (path, value) in prms.iter() {
+        let id = msgf.find_param(0, path).expect(path);
+        msgf.set_param(0, id, *value);
+    }
+    //  low notes are the most expensive for the additive oscillator
+    for i in 0..VOICES {
+        msgf.recieve_midi_message(0x90, (28 + i*4) as u8, 100);
+    }
+    let blocks = SECONDS*(SAMPLE_RATE as usize)/BLOCK_SIZE;
+    let mut power = 0.0_f32;
+    let start = Instant::now();
+    for _ in 0..blocks {
+        msgf.process(&mut abuf_l, &mut abuf_r, BLOCK_SIZE as u32);
+        power += abuf_l.iter().map(|v| v*v).sum::<f32>();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    let rms = (power/((blocks*BLOCK_SIZE) as f32)).sqrt();
+    (elapsed/(SECONDS as f64)/(VOICES as f64), rms)
+}
+fn main() {
+    println!("{} voices, {}Hz, {} samples/block", VOICES, SAMPLE_RATE, BLOCK_SIZE);
+    println!("{:<10} {:<8} {:>12} {:>8}", "algorithm", "wave", "CPU/voice", "rms");
+    for (alg, alg_name) in ALGORITHMS.iter().enumerate() {
+        for (wv_name, wv_type, prms) in WAVES.iter() {
+            //  va: sustaining tone
+            let alg = [("osc.algorithm", alg as f32), ("osc.wv_type", *wv_type)];
+            let prms: Vec<(&str, f32)> = alg.iter().chain(prms.iter()).cloned().collect();
+            let (load, rms) = measure(1, &prms);
+            println!("{:<10} {:<8} {:>11.3}% {:>8.4}", alg_name, wv_name, load*100.0, rms);
+        }
+    }
+    for (name, program) in TONES.iter() {
+        let (load, rms) = measure(*program, &[]);
+        println!("{:<19} {:>11.3}% {:>8.4}", name, load*100.0, rms);
+    }
+}
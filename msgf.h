@@ -10,11 +10,73 @@
 #include <stdint.h>
 #include <stdlib.h>
 
-#define MAX_PART_NUM 1
+#define MAX_PART_NUM 16
+
+#define RCV_CH_OMNI 16
+
+#define RCV_CH_OFF 127
+
+#define VOICE_STEAL_OLDEST 0
+
+#define VOICE_STEAL_QUIETEST 1
+
+#define VOICE_STEAL_SAME_NOTE 2
+
+#define VOICE_STEAL_RELEASE_FIRST 3
+
+#define NOTE_PRIORITY_LAST 0
+
+#define NOTE_PRIORITY_LOW 1
+
+#define NOTE_PRIORITY_HIGH 2
+
+#define LOG_LEVEL_OFF 0
+
+#define LOG_LEVEL_ERROR 1
+
+#define LOG_LEVEL_WARN 2
+
+#define LOG_LEVEL_INFO 3
+
+#define LOG_LEVEL_DEBUG 4
+
+#define LOG_CATEGORY_SYSTEM 1
+
+#define LOG_CATEGORY_MIDI 2
+
+#define LOG_CATEGORY_VOICE 4
+
+#define LOG_CATEGORY_PARAM 8
+
+#define PARAM_UNIT_NONE 0
+
+#define PARAM_UNIT_SEMITONE 1
+
+#define PARAM_UNIT_CENT 2
+
+#define PARAM_UNIT_HZ 3
+
+#define PARAM_UNIT_SEC 4
+
+#define PARAM_UNIT_RATE 5
+
+#define PARAM_UNIT_LEVEL 6
+
+#define PARAM_UNIT_OCTAVE 7
+
+#define PARAM_UNIT_CONTROL_FRAME 8
+
+#define PARAM_UNIT_INDEX 9
+
+#define PARAM_SMOOTHING_NONE 0
+
+#define PARAM_SMOOTHING_CONTROL 1
+
+#define PARAM_SMOOTHING_AUDIO 2
 
 #define MAX_BUFFER_SIZE 1024
 
-#define SAMPLING_FREQ 44100.0
+#define DEFAULT_SAMPLING_FREQ 44100.0
 
 #define AUDIO_FRAME_PER_CONTROL 128
 
@@ -22,15 +84,191 @@
 
 typedef struct Msgf Msgf;
 
-struct Msgf *rust_msgf_new(void);
+typedef void (*MsgfLogCallback)(uint8_t, uint8_t, const char*, void*);
+
+struct Msgf *rust_msgf_new(float sample_rate, uint32_t max_block_size);
+
+void rust_set_sample_rate(struct Msgf *rust_msgf, float sample_rate);
+
+void rust_msgf_set_part_channel(struct Msgf *rust_msgf, uint32_t part_num, uint8_t rcv_ch);
+
+void rust_msgf_set_part_key_range(struct Msgf *rust_msgf, uint32_t part_num, uint8_t low, uint8_t high);
+
+void rust_msgf_set_part_vel_range(struct Msgf *rust_msgf, uint32_t part_num, uint8_t low, uint8_t high);
+
+void rust_msgf_set_part_zone_fade(struct Msgf *rust_msgf,
+                                  uint32_t part_num,
+                                  uint8_t key_width,
+                                  uint8_t vel_width);
+
+void rust_msgf_set_part_transpose(struct Msgf *rust_msgf, uint32_t part_num, int8_t transpose);
+
+void rust_msgf_set_part_polyphony(struct Msgf *rust_msgf,
+                                  uint32_t part_num,
+                                  uint32_t max_voice,
+                                  uint8_t steal);
+
+void rust_msgf_set_part_mono_config(struct Msgf *rust_msgf,
+                                    uint32_t part_num,
+                                    uint8_t priority,
+                                    bool legato);
+
+/**
+ * # Safety
+ * `text` must point to `size` bytes of a UTF-8 patch bank text.
+ */
+bool rust_msgf_load_patch_bank(struct Msgf *rust_msgf, const uint8_t *text, uintptr_t size);
+
+/**
+ * # Safety
+ * `buf` must point to `size` bytes, or be null when `size` is 0.
+ * Returns the length of the whole text; the text is written only if it fits in `buf`.
+ */
+uintptr_t rust_msgf_save_patch_bank(const struct Msgf *rust_msgf, uint8_t *buf, uintptr_t size);
+
+uint32_t rust_msgf_program_count(const struct Msgf *rust_msgf);
+
+/**
+ * # Safety
+ * `bank_msb`, `bank_lsb` and `program` must point to writable bytes.
+ */
+bool rust_msgf_program_info(const struct Msgf *rust_msgf,
+                            uint32_t index,
+                            uint8_t *bank_msb,
+                            uint8_t *bank_lsb,
+                            uint8_t *program);
+
+/**
+ * # Safety
+ * `buf` must point to `size` bytes, or be null when `size` is 0.
+ * The name is truncated to `size`-1 bytes and terminated by NUL.
+ * Returns the length of the whole name (0: no such program).
+ */
+uintptr_t rust_msgf_program_name(const struct Msgf *rust_msgf,
+                                 uint32_t index,
+                                 uint8_t *buf,
+                                 uintptr_t size);
+
+uint32_t rust_msgf_param_count(const struct Msgf *rust_msgf, uint32_t part_num);
+
+/**
+ * # Safety
+ * `min`, `max`, `default_value`, `unit` and `smoothing` must point to writable values.
+ */
+bool rust_msgf_param_info(const struct Msgf *rust_msgf,
+                          uint32_t part_num,
+                          uint32_t id,
+                          float *min,
+                          float *max,
+                          float *default_value,
+                          uint8_t *unit,
+                          uint8_t *smoothing);
+
+/**
+ * # Safety
+ * `buf` must point to `size` bytes, or be null when `size` is 0.
+ * The path ("inst.engine.name") is truncated to `size`-1 bytes and terminated by NUL.
+ * Returns the length of the whole path (0: no such parameter).
+ */
+uintptr_t rust_msgf_param_path(const struct Msgf *rust_msgf,
+                               uint32_t part_num,
+                               uint32_t id,
+                               uint8_t *buf,
+                               uintptr_t size);
+
+/**
+ * # Safety
+ * `path` must be a NUL terminated string.
+ * Returns the parameter ID, or -1 when not found.
+ */
+int32_t rust_msgf_find_param(const struct Msgf *rust_msgf, uint32_t part_num, const char *path);
+
+bool rust_msgf_set_param(struct Msgf *rust_msgf, uint32_t part_num, uint32_t id, float value);
+
+/**
+ * # Safety
+ * `value` must point to a writable f32.
+ */
+bool rust_msgf_get_param(const struct Msgf *rust_msgf, uint32_t part_num, uint32_t id, float *value);
 
 void rust_recieve_midi_message(struct Msgf *rust_msgf, uint8_t dt1, uint8_t dt2, uint8_t dt3);
 
+void rust_recieve_midi_message_at(struct Msgf *rust_msgf,
+                                  uint8_t dt1,
+                                  uint8_t dt2,
+                                  uint8_t dt3,
+                                  uint32_t frame);
+
+/**
+ * # Safety
+ * `bytes` must point to `size` bytes.
+ */
+void rust_recieve_midi_bytes(struct Msgf *rust_msgf, const uint8_t *bytes, uintptr_t size);
+
+/**
+ * # Safety
+ * `bytes` must point to `size` bytes.
+ */
+void rust_recieve_midi_bytes_at(struct Msgf *rust_msgf,
+                                const uint8_t *bytes,
+                                uintptr_t size,
+                                uint32_t frame);
+
+/**
+ * # Safety
+ * `words` must point to `count` 32bit words.
+ */
+void rust_recieve_ump(struct Msgf *rust_msgf, const uint32_t *words, uintptr_t count);
+
+/**
+ * # Safety
+ * `words` must point to `count` 32bit words.
+ */
+void rust_recieve_ump_at(struct Msgf *rust_msgf,
+                         const uint32_t *words,
+                         uintptr_t count,
+                         uint32_t frame);
+
 void rust_process(struct Msgf *rust_msgf,
-                  float (*abuf_l)[MAX_BUFFER_SIZE],
-                  float (*abuf_r)[MAX_BUFFER_SIZE],
+                  float *abuf_l,
+                  float *abuf_r,
                   uint32_t in_number_frames);
 
+/**
+ * # Safety
+ * `data` must point to `size` readable bytes.
+ */
+bool rust_msgf_load_smf(struct Msgf *rust_msgf, const uint8_t *data, uintptr_t size);
+
+void rust_msgf_play(struct Msgf *rust_msgf);
+
+void rust_msgf_stop(struct Msgf *rust_msgf);
+
+void rust_msgf_locate(struct Msgf *rust_msgf, uint32_t tick);
+
+bool rust_msgf_is_playing(const struct Msgf *rust_msgf);
+
+/**
+ * # Safety
+ * `buf` must point to `size` bytes, or be null when `size` is 0.
+ * The message is truncated to `size`-1 bytes and terminated by NUL.
+ * Returns the length of the whole message (0: no message).
+ */
+uintptr_t rust_msgf_pop_log(uint8_t *buf, uintptr_t size);
+
+void rust_msgf_set_log_level(uint8_t level);
+
+void rust_msgf_set_log_categories(uint8_t mask);
+
+/**
+ * # Safety
+ * `user` is passed to `callback` as is, and must be valid until the callback is replaced.
+ * `callback` is called from rust_msgf_flush_log(), not from the audio thread.
+ */
+void rust_msgf_set_log_callback(MsgfLogCallback callback, void *user);
+
+uintptr_t rust_msgf_flush_log(void);
+
 void say_hello(void);
 
 void rust_msgf_destroy(struct Msgf *rust_msgf);
@@ -7,7 +7,10 @@
 //  Released under the MIT license
 //  https://opensource.org/licenses/mit-license.php
 //
-use crate::core::msgf_inst;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use crate::core::{msgf_log, msgf_program};
+use crate::core::msgf_patch::{self, PatchPrm};
 
 //  You can select a specific app.
 pub mod va;
@@ -16,38 +19,223 @@ pub mod sgf;
 use crate::app::va::*;
 use crate::app::sg::*;
 use crate::app::sgf::*;
-pub fn get_inst(inst_number:usize, vol:u8, pan:u8, exp:u8) -> Box<dyn msgf_inst::Inst> {
-    if inst_number < 8 {
-        println!("Changed Va: {}",inst_number);
-        Box::new(va_inst::InstVa::new(inst_number,vol,pan,exp))
+
+//  tone numbers are selected by bank select LSB and program change
+const MAX_PATCH_TONE: usize = msgf_program::TONES_PER_BANK*msgf_program::TONES_PER_BANK;
+
+//  Patch Bank
+//      The tone tables of all apps and the programs selecting them.
+//      It is never changed once published: load_patch_bank() builds a new one,
+//      and swaps the pointer. Msgf takes it without lock and allocation.
+pub struct PatchBank {
+    registry: msgf_program::ProgramRegistry,
+    va: Vec<va_prm::SynthParameter>,
+    sg: Vec<sg_prm::SynthParameter>,
+    sgf: Vec<sgf_prm::SynthParameter>,
+}
+impl PatchBank {
+    fn new(va: Vec<va_prm::SynthParameter>, sg: Vec<sg_prm::SynthParameter>,
+      sgf: Vec<sgf_prm::SynthParameter>) -> Self {
+        let mut registry = msgf_program::ProgramRegistry::new();
+        va::register(&mut registry, va.len());
+        sg::register(&mut registry, sg.len());
+        sgf::register(&mut registry, sgf.len());
+        Self {registry, va, sg, sgf}
+    }
+    pub fn registry(&self) -> &msgf_program::ProgramRegistry {&self.registry}
+    pub fn va(&self) -> &[va_prm::SynthParameter] {&self.va}
+    pub fn sg(&self) -> &[sg_prm::SynthParameter] {&self.sg}
+    pub fn sgf(&self) -> &[sgf_prm::SynthParameter] {&self.sgf}
+    pub fn select_program(&self, bank: u16, program: u8) -> msgf_program::Program {
+        let prg = self.registry.select(bank, program);
+        msgf_log::post(msgf_log::LogLevel::Info, msgf_log::LogCategory::Midi, format_args!("Changed {}: {}-{}-{}", prg.name, prg.bank_msb(), prg.bank_lsb(), prg.number));
+        prg
+    }
+}
+impl Default for PatchBank {
+    fn default() -> Self {
+        Self::new(va_prm::TONE_PRM.to_vec(), sg_prm::SG_TONE_PRM.to_vec(), sgf_prm::SGF_TONE_PRM.to_vec())
+    }
+}
+//  The current one is held by CURRENT as a raw pointer of Arc.
+//  READERS counts the threads between loading the pointer and counting up the Arc,
+//  the old ones are kept in RETIRED until nobody holds them,
+//  so that an Arc is never freed in the audio thread.
+static READERS: AtomicUsize = AtomicUsize::new(0);
+static GENERATION: AtomicUsize = AtomicUsize::new(0);  //  counted up by every publish
+fn current() -> &'static AtomicPtr<PatchBank> {
+    static CURRENT: OnceLock<AtomicPtr<PatchBank>> = OnceLock::new();
+    CURRENT.get_or_init(|| AtomicPtr::new(Arc::into_raw(Arc::new(PatchBank::default())) as *mut PatchBank))
+}
+fn retired() -> MutexGuard<'static, Vec<Arc<PatchBank>>> {
+    //  host only: load_patch_bank() is serialized by this
+    static RETIRED: Mutex<Vec<Arc<PatchBank>>> = Mutex::new(Vec::new());
+    RETIRED.lock().unwrap_or_else(PoisonError::into_inner)
+}
+pub fn patch_bank() -> Arc<PatchBank> {
+    //  no lock and no allocation, called in process() too
+    READERS.fetch_add(1, Ordering::SeqCst);
+    let ptr = current().load(Ordering::SeqCst);
+    //  Safety: ptr is made by Arc::into_raw(), and isn't freed while READERS > 0
+    let bank = unsafe {
+        Arc::increment_strong_count(ptr);
+        Arc::from_raw(ptr)
+    };
+    READERS.fetch_sub(1, Ordering::SeqCst);
+    bank
+}
+pub fn patch_bank_generation() -> usize {GENERATION.load(Ordering::SeqCst)}
+fn publish(retired: &mut Vec<Arc<PatchBank>>, bank: PatchBank) {
+    let new = Arc::into_raw(Arc::new(bank)) as *mut PatchBank;
+    let old = current().swap(new, Ordering::SeqCst);
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    //  Safety: the reference CURRENT had moves to RETIRED
+    retired.push(unsafe {Arc::from_raw(old)});
+    if READERS.load(Ordering::SeqCst) == 0 {
+        //  nobody can take the old ones any more, free those only RETIRED holds
+        retired.retain(|bank| Arc::strong_count(bank) > 1);
     }
-    else if inst_number == 8 {
-        println!("Changed Sg: {}",inst_number);
-        Box::new(sg_inst::InstSg::new(0,vol,pan,exp))
+}
+
+//  Patch Bank Text
+//      [va.N] / [sg.N] / [sgf.N] sections replace the tone tables of each app.
+//      The apps which don't appear in the text keep their tones.
+//      Omitted parameters keep the values of the current tone of the same number.
+pub fn load_patch_bank(text: &str) -> Result<(), String> {
+    let mut retired = retired();
+    let bank = read_patch_bank(text, &patch_bank())?;
+    publish(&mut retired, bank);
+    Ok(())
+}
+pub fn save_patch_bank() -> String {write_patch_bank(&patch_bank())}
+fn read_patch_bank(text: &str, cur: &PatchBank) -> Result<PatchBank, String> {
+    let mut va_bank: Vec<Option<va_prm::SynthParameter>> = Vec::new();
+    let mut sg_bank: Vec<Option<sg_prm::SynthParameter>> = Vec::new();
+    let mut sgf_bank: Vec<Option<sgf_prm::SynthParameter>> = Vec::new();
+    for sec in msgf_patch::parse(text)?.iter() {
+        let (app, num) = sec.name.split_once('.')
+            .and_then(|(app, num)| Some((app.trim(), num.trim().parse::<usize>().ok()?)))
+            .ok_or(format!("line {}: invalid section name: {}", sec.line, sec.name))?;
+        match app {
+            "va" => put_tone(&mut va_bank, num, cur.va(), sec)?,
+            "sg" => put_tone(&mut sg_bank, num, cur.sg(), sec)?,
+            "sgf" => put_tone(&mut sgf_bank, num, cur.sgf(), sec)?,
+            _ => return Err(format!("line {}: unknown app: {}", sec.line, app)),
+        }
     }
-    else if inst_number == 9 {
-        println!("Changed Sgf {}",inst_number);
-        Box::new(sgf_inst::InstSgf::new(0,vol,pan,exp))
+    //  nothing is changed if any error
+    let va_bank = complete_bank(va_bank, cur.va(), "va")?;
+    let sg_bank = complete_bank(sg_bank, cur.sg(), "sg")?;
+    let sgf_bank = complete_bank(sgf_bank, cur.sgf(), "sgf")?;
+    Ok(PatchBank::new(va_bank, sg_bank, sgf_bank))
+}
+fn write_patch_bank(bank: &PatchBank) -> String {
+    let mut out = String::from("# msgf patch bank\n");
+    for (num, tone) in bank.va().iter().enumerate() {
+        out.push_str(&format!("\n[va.{}]\n", num));
+        tone.write_patch("", &mut out);
     }
-    else if inst_number == 10 { //  for Touch Keyboard
-        println!("Changed Sgf {}",inst_number);
-        Box::new(sgf_inst::InstSgf::new(0,vol,pan,exp))
+    for (num, tone) in bank.sg().iter().enumerate() {
+        out.push_str(&format!("\n[sg.{}]\n", num));
+        tone.write_patch("", &mut out);
     }
-    //  for Touch Keyboard MFT2022 version
-    else if inst_number == 16 {
-        println!("Changed Va: {}",inst_number);
-        Box::new(va_inst::InstVa::new(4,vol,pan,exp))
+    for (num, tone) in bank.sgf().iter().enumerate() {
+        out.push_str(&format!("\n[sgf.{}]\n", num));
+        tone.write_patch("", &mut out);
     }
-    else if inst_number == 17 {
-        println!("Changed Va: {}",inst_number);
-        Box::new(va_inst::InstVa::new(5,vol,pan,exp))
+    out
+}
+fn put_tone<T: PatchPrm + Copy>(bank: &mut Vec<Option<T>>, num: usize, cur: &[T],
+  sec: &msgf_patch::PatchSection) -> Result<(), String> {
+    if num >= MAX_PATCH_TONE {
+        return Err(format!("line {}: tone number is too large: {}", sec.line, num));
     }
-    else if inst_number == 18 {
-        println!("Changed Sgf: {}",inst_number);
-        Box::new(sgf_inst::InstSgf::new(0,vol,pan,exp))
+    if bank.len() <= num {bank.resize(num+1, None);}
+    //  the base is the current tone, clamped to the last one
+    let tone = bank[num].get_or_insert(cur[num.min(cur.len()-1)]);
+    msgf_patch::set_items(tone, sec)
+}
+fn complete_bank<T: Copy>(bank: Vec<Option<T>>, cur: &[T], app: &str) -> Result<Vec<T>, String> {
+    //  tone numbers must be continuous from 0, no section keeps the current tones
+    if bank.is_empty() {
+        return Ok(cur.to_vec());
     }
+    bank.into_iter().enumerate()
+        .map(|(num, tone)| tone.ok_or(format!("[{}.{}] is missing", app, num)))
+        .collect()
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    else {
-        Box::new(va_inst::InstVa::new(0,vol,pan,exp))
+    fn tone_text<T: PatchPrm>(tone: &T) -> String {
+        let mut out = String::new();
+        tone.write_patch("", &mut out);
+        out
+    }
+    #[test]
+    fn patch_bank_round_trip() {
+        let text = write_patch_bank(&PatchBank::default());
+        for app in ["[va.0]", "[sg.0]", "[sgf.0]"] {
+            assert!(text.contains(app), "{} is not saved", app);
+        }
+        let bank = read_patch_bank(&text, &PatchBank::default()).expect("saved text");
+        assert_eq!(write_patch_bank(&bank), text);
+        //  read on another bank, every parameter is written
+        let other = read_patch_bank("[va.0]\n[sg.0]\n[sgf.0]\n", &PatchBank::default()).expect("other");
+        let bank = read_patch_bank(&text, &other).expect("saved text");
+        assert_eq!(write_patch_bank(&bank), text);
+    }
+    #[test]
+    fn omitted_parameters_keep_current_values() {
+        let cur = PatchBank::default();
+        let bank = read_patch_bank("[va.0]\nosc.coarse_tune = 12\n[va.1]\n", &cur).expect("patch");
+        assert_eq!(bank.va().len(), 2);
+        let before = tone_text(&cur.va()[0]);
+        let after = tone_text(&bank.va()[0]);
+        let changed: Vec<&str> = before.lines().zip(after.lines())
+            .filter(|(b, a)| b != a).map(|(_, a)| a).collect();
+        assert_eq!(changed, ["osc.coarse_tune = 12"]);
+        assert_eq!(tone_text(&bank.va()[1]), tone_text(&cur.va()[1]));
+        //  apps which don't appear keep their tones
+        assert_eq!(bank.sg().len(), cur.sg().len());
+        assert_eq!(tone_text(&bank.sgf()[0]), tone_text(&cur.sgf()[0]));
+    }
+    #[test]
+    fn unknown_bank_falls_back() {
+        let bank = PatchBank::default();
+        //  no bank 5: the same program of bank 0
+        let prg = bank.select_program(msgf_program::bank_number(5, 3), 1);
+        assert_eq!((prg.bank, prg.number, prg.name), (0, 1, "VA Saw Lead"));
+        //  no LSB 5 of bank 1: the capital bank 1/0
+        let prg = bank.select_program(msgf_program::bank_number(1, 5), 2);
+        assert_eq!((prg.bank_msb(), prg.bank_lsb(), prg.name), (1, 0, "Sing by Filter (Touch Keyboard)"));
+        //  no program 100 in any bank: the first program
+        let prg = bank.select_program(msgf_program::bank_number(5, 0), 100);
+        assert_eq!((prg.bank, prg.number), (0, 0));
+    }
+    #[test]
+    fn patch_bank_adds_a_bank() {
+        //  [va.128] is bank 2/1, program 0
+        let cur = PatchBank::default();
+        let user = msgf_program::bank_number(2, 1);
+        assert_eq!(cur.select_program(user, 0).bank, msgf_program::bank_number(2, 0));
+        let text: String = (0..=128).map(|num| format!("[va.{}]\n", num)).collect();
+        let bank = read_patch_bank(&text, &cur).expect("patch");
+        let prg = bank.select_program(user, 0);
+        assert_eq!((prg.bank, prg.number, prg.tone), (user, 0, 128));
+        assert!(bank.registry().find(user, 1).is_none());
+        assert_eq!(bank.registry().programs().len(), cur.registry().programs().len() + 121);
+        //  published to every instance (the only test using the shared one)
+        let saved = save_patch_bank();
+        let generation = patch_bank_generation();
+        load_patch_bank(&text).expect("patch");
+        assert!(patch_bank_generation() > generation);
+        assert_eq!(patch_bank().select_program(user, 0).tone, 128);
+        load_patch_bank(&saved).expect("saved text");
+        assert_eq!(save_patch_bank(), saved);
     }
-} 
+}
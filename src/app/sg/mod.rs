@@ -10,4 +10,18 @@
 //
 pub mod sg_inst;
 pub mod sg_voice;
-pub mod sg_prm;
\ No newline at end of file
+pub mod sg_prm;
+
+use crate::msgf_if;
+use crate::app;
+use crate::core::{msgf_inst, msgf_program};
+
+fn create(bank: &app::PatchBank, tone: usize, vol: u8, pan: u8, exp: u8, cfg: &msgf_if::AudioConfig) -> Box<dyn msgf_inst::Inst> {
+    Box::new(sg_inst::InstSg::new(bank.sg(), tone, vol, pan, exp, cfg))
+}
+pub fn register(reg: &mut msgf_program::ProgramRegistry, tone_count: usize) {
+    //  tone_count: tones of the patch bank
+    reg.add(0, 8, "Sing", 0, create);
+    //  Bank 3/0-127: tones of a patch bank ([sg.N] -> LSB N/128, program N%128)
+    reg.add_tone_bank(3, "Sing User", create, tone_count);
+}
@@ -11,9 +11,12 @@
 use std::rc::Rc;
 use std::cell::Cell;
 use crate::msgf_if;
+use crate::app;
 use crate::core::*;
 use crate::core::msgf_voice::*;
 use crate::core::msgf_disp::MsgfDisplay;
+use crate::core::msgf_log::{LogLevel, LogCategory};
+use crate::core::msgf_param::{self, ParamPrm};
 //use crate::engine::*;
 use crate::app::sg::*;
 use crate::app::sg::sg_voice;
@@ -21,48 +24,48 @@ use crate::app::sg::sg_voice;
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
-struct NoteSg {
-    note: u8,
-    _vel: u8,
-    off: bool,
-}
 pub struct InstSg {
     vce_audio: msgf_afrm::AudioFrame,
     inst_audio: msgf_afrm::AudioFrame,
-    vcevec: Vec<NoteSg>,                    // 押鍵中の Note
+    cfg: msgf_if::AudioConfig,
+    note_stack: msgf_note_stack::NoteStack, // 押鍵中の Note
     vce: Option<Box<sg_voice::VoiceSg>>,    // 発音中の Voice
-    active_vce_index: i8,                  // 発音中の vcevec のIndex
+    spare_vce: Option<Box<sg_voice::VoiceSg>>,  // 次の Note On で再利用する Voice
     inst_number: usize,
     mdlt: f32,  //  0.0..0.5
     pit: f32,   //  [cent]
     vol: u8,    //  0..127
     pan: f32,   //  -1..0..+1
     exp: u8,    //  0..127
-    spmsg: [u8;4],    //  special message for SG
+    pedal: msgf_pedal::Pedal,
+    vowel: (f32, f32),  //  x, y: -1..0..1
+    params: msgf_param::ParamSet,
+    tone: sg_prm::SynthParameter,    //  values of the tone, for param_default()
     inst_prm: Rc<Cell<sg_prm::SynthParameter>>,
 }
-const NO_NOTE:i8 = -1;
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl MsgfDisplay for InstSg {}
-impl NoteSg {
-    fn new(note: u8, _vel: u8) -> Self {
-        Self {note, _vel, off: false,}
-    }
-}
-impl Drop for InstSg {
-    fn drop(&mut self) {self.vcevec.clear();}
-}
 //---------------------------------------------------------
 impl msgf_inst::Inst for InstSg {
 
-    fn change_inst(&mut self, mut inst_number: usize, vol: u8, pan: u8, exp: u8) {
-        let max_tone = sg_prm::SG_MAX_TONE_COUNT;
+    fn change_inst(&mut self, bank: &app::PatchBank, mut inst_number: usize, vol: u8, pan: u8, exp: u8) {
+        let tones = bank.sg();
+        let max_tone = tones.len();
         if inst_number >= max_tone {
             inst_number = max_tone-1;
         }
-        let _ = &self.inst_prm.replace(sg_prm::SG_TONE_PRM[inst_number]);
+        if self.vce.is_some() {
+            self.spare_vce = self.vce.take();
+        }
+        self.pedal.reset();
+        self.note_stack.clear();
+        let tprm = tones[inst_number];
+        self.tone = tprm;
+        let _ = &self.inst_prm.replace(tprm);
+        self.params.reset(|id| tprm.param(id));
+        self.vowel = (0.0, 0.0);
         self.inst_number = inst_number;
         //self.mdlt = self.inst_prm.get().osc.lfo_depth;
         self.pit = 0.0;
@@ -71,45 +74,24 @@ impl msgf_inst::Inst for InstSg {
         self.exp = exp;
     }
     fn note_off(&mut self, dt2: u8, _dt3: u8) {
-        let nt_idx = self.search_note(dt2);
-        if nt_idx == NO_NOTE {return}
-        if nt_idx == self.active_vce_index {
-            // sounding voice
-            if let Some(cur_vce) = &mut self.vce {
-                cur_vce.note_off();
-            }
-            self.vcevec[nt_idx as usize].off = true;
+        if self.pedal.note_off(dt2) {
+            self.release_note(dt2);
         }
-        else {
-            // key pressd, but already no sound
-            self.remove_note(nt_idx);
+    }
+    fn note_on_f(&mut self, dt2: u8, vel: f32) {
+        self.pedal.note_on(dt2);
+        self.note_stack.push(dt2, vel);
+        if self.note_stack.is_active(dt2) {
+            self.sound_note(dt2, vel);
         }
     }
-    fn note_on(&mut self, dt2: u8, dt3: u8) {
+    fn per_note_pitch(&mut self, note: u8, pit: f32) {
         if let Some(cur_vce) = &mut self.vce {
-            cur_vce.slide(dt2, dt3);
-        }
-        else {// 1st Note On
-            let mut new_vce = Box::new(
-                sg_voice::VoiceSg::new(dt2, dt3, 
-                    self.mdlt, self.pit, self.vol, self.exp, Rc::clone(&self.inst_prm)));
-            // Send Special Message to new voice
-            for i in 0..self.spmsg.len() {
-                new_vce.set_prm(i as u8, self.spmsg[i]);
-            }
-            new_vce.start_sound();
-            self.vce = Some(new_vce);
-        }
-        let cur_note = self.active_vce_index;
-        if cur_note > NO_NOTE && self.vcevec[cur_note as usize].off == true {
-            // Same note is releasing now
-            self.remove_note(cur_note);
+            if cur_vce.note_num() == note {cur_vce.per_note_pitch(pit);}
         }
-        self.vcevec.push(NoteSg::new(dt2, dt3));
-        self.active_vce_index = (self.vcevec.len() as i8)-1; // the last order
     }
-    fn modulation(&mut self, value: u8) {
-        let mdlt = 0.5f32*(value as f32)/127.0;
+    fn modulation_f(&mut self, value: f32) {
+        let mdlt = 0.5f32*value;
         self.mdlt = mdlt;
         if let Some(cur_vce) = &mut self.vce {
             cur_vce.change_pmd(mdlt);
@@ -132,27 +114,60 @@ impl msgf_inst::Inst for InstSg {
             cur_vce.amplitude(vol, value);
         }
     }
-    fn pitch(&mut self, bend:i16, tune_coarse:u8, tune_fine:u8) {
-        let pit:f32 = ((bend as f32)*200.0)/8192.0
-                     + ((tune_coarse as f32)-64.0)*100.0
-                     + ((tune_fine as f32)-64.0)*100.0/64.0;
+    fn pitch(&mut self, pit: f32) {
         self.pit = pit;
         if let Some(cur_vce) = &mut self.vce {
             cur_vce.pitch(pit);
         }
     }
-    fn sustain(&mut self, _value: u8) {}
+    fn sustain(&mut self, value: u8) {
+        let released = self.pedal.sustain(value);
+        msgf_pedal::Pedal::for_each_note(&released, |nt| self.release_note(nt));
+    }
+    fn sostenuto(&mut self, value: u8) {
+        let released = self.pedal.sostenuto(value);
+        msgf_pedal::Pedal::for_each_note(&released, |nt| self.release_note(nt));
+    }
+    fn set_mono_config(&mut self, priority: msgf_note_stack::NotePriority, _legato: bool) {
+        //  always monophonic with legato
+        self.note_stack.set_priority(priority);
+    }
     fn all_sound_off(&mut self) {
+        self.pedal.reset();
+        self.note_stack.clear();
         if let Some(cur_vce) = &mut self.vce {
             cur_vce.damp();
         }
     }
-    fn set_prm(&mut self, prm_type: u8, value: u8) {
-        let idx: usize = prm_type as usize;
-        if idx <= self.spmsg.len() {
-            self.spmsg[idx] = value;
-            if let Some(cur_vce) = &mut self.vce {
-                cur_vce.set_prm(prm_type, value)
+    fn release_all(&mut self) {
+        self.pedal.reset();
+        self.note_stack.clear();
+        if let Some(cur_vce) = &mut self.vce {
+            if cur_vce.status() == NoteStatus::DuringNoteOn {
+                cur_vce.note_off();
+            }
+        }
+    }
+    fn set_prm_f(&mut self, prm_type: u8, value: f32) {
+        //  CC#16-31
+        let (path, value) = match prm_type {
+            0 => ("lfo.freq", value*12.7),                      //  0.1[Hz] step by 7bit
+            1 => ("lfo.wave", (value*4.0).floor().min(3.0)),    //  bit 6-5 of 7bit
+            2 => ("formant.vowel_x", (value*127.0-64.0)/64.0),
+            3 => ("formant.vowel_y", (value*127.0-64.0)/64.0),
+            _ => return,
+        };
+        if let Some(id) = self.params.find(path) {
+            self.set_param(id, value);
+        }
+    }
+    fn params(&self) -> Option<&msgf_param::ParamSet> {Some(&self.params)}
+    fn param_default(&self, id: usize) -> f32 {self.tone.param(id)}
+    fn set_param(&mut self, id: usize, value: f32) {
+        if let Some(value) = self.params.set(id, value) {
+            //  Control: applied step by step in process()
+            if self.params.smoothing(id) != msgf_param::Smoothing::Control {
+                self.apply_param(id, value);
             }
         }
     }
@@ -160,75 +175,116 @@ impl msgf_inst::Inst for InstSg {
       abuf_l: &mut msgf_afrm::AudioFrame,
       abuf_r: &mut msgf_afrm::AudioFrame,
       in_number_frames: usize) {
-        self.vce_audio.set_sample_number(in_number_frames as usize);
-        self.inst_audio.set_sample_number(in_number_frames as usize);
+        self.vce_audio.set_sample_number(in_number_frames);
+        self.inst_audio.set_sample_number(in_number_frames);
         self.inst_audio.clr_abuf();
         let mut vce_ended = false;
- 
+
+        //  Parameters in smoothing
+        let mut params = std::mem::take(&mut self.params);
+        params.advance(in_number_frames, |id, value| self.apply_param(id, value));
+        self.params = params;
+
         if let Some(cur_vce) = &mut self.vce {
             vce_ended = cur_vce.process(&mut self.vce_audio, in_number_frames);
-            self.inst_audio.mul_and_mix(&mut self.vce_audio, 1.0);
+            self.inst_audio.mul_and_mix(&self.vce_audio, 1.0);
         }
 
         //  make audio stereo
-        abuf_l.mul_and_mix(&mut self.inst_audio, 1.0-self.pan);
-        abuf_r.mul_and_mix(&mut self.inst_audio, self.pan);
+        abuf_l.mul_and_mix(&self.inst_audio, 1.0-self.pan);
+        abuf_r.mul_and_mix(&self.inst_audio, self.pan);
 
         if vce_ended {
             // when voice is released
-            self.vce = None;
-            assert!(self.vcevec.len() > 0);
-            self.remove_note(self.active_vce_index);
-            self.print_str("Released!");
+            self.spare_vce = self.vce.take();
+            self.log(LogLevel::Debug, LogCategory::Voice, format_args!("Released!"));
         }
     }
 }
 
 impl InstSg {
 
-    pub fn new(mut inst_number: usize, vol: u8, pan: u8, exp: u8) -> Self {
-        let max_tone = sg_prm::SG_MAX_TONE_COUNT;
+    pub fn new(tones: &[sg_prm::SynthParameter], mut inst_number: usize, vol: u8, pan: u8, exp: u8, cfg: &msgf_if::AudioConfig) -> Self {
+        let max_tone = tones.len();
         if inst_number >= max_tone {
             inst_number = max_tone-1;
         }
-        let prm = Rc::new(Cell::new(sg_prm::SG_TONE_PRM[inst_number]));
+        let tprm = tones[inst_number];
+        let prm = Rc::new(Cell::new(tprm));
         Self {
-            vce_audio: msgf_afrm::AudioFrame::new(0,msgf_if::MAX_BUFFER_SIZE),
-            inst_audio: msgf_afrm::AudioFrame::new(0,msgf_if::MAX_BUFFER_SIZE),
-            vcevec: Vec::new(),
+            vce_audio: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
+            inst_audio: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
+            cfg: *cfg,
+            note_stack: msgf_note_stack::NoteStack::new(msgf_note_stack::NotePriority::Last),
             vce: None,
-            active_vce_index: NO_NOTE,
+            spare_vce: Some(Box::new(sg_voice::VoiceSg::new(60, 0.0, 0.0, 0.0, vol, exp, Rc::clone(&prm), cfg))),
             inst_number,
             mdlt: 0.0,//prm.get().osc.lfo_depth,
             pit: 0.0,
             vol,
             pan: Self::calc_pan(pan),
             exp,
-            spmsg: [0,0,0,0],
+            pedal: msgf_pedal::Pedal::new(),
+            vowel: (0.0, 0.0),
+            params: msgf_param::ParamSet::new("sg", &sg_prm::PARAMS, cfg.sampling_freq, |id| tprm.param(id)),
+            tone: tprm,
             inst_prm: prm,
         }
     }
+    fn apply_param(&mut self, id: usize, value: f32) {
+        //  for the next notes, and the sounding voice
+        let mut tprm = self.inst_prm.get();
+        tprm.set_param(id, value);
+        self.inst_prm.set(tprm);
+        match msgf_param::locate(&sg_prm::PARAMS, id) {
+            Some((sg_prm::PRM_GRP_FORMANT, idx)) => {
+                if idx == 0 {self.vowel.0 = value;} else {self.vowel.1 = value;}
+                if let Some(cur_vce) = &mut self.vce {
+                    cur_vce.set_vowel(self.vowel.0, self.vowel.1);
+                }
+            }
+            Some((grp, _)) => if let Some(cur_vce) = &mut self.vce {
+                cur_vce.update_prm(grp, &tprm);
+            },
+            None => (),
+        }
+    }
     fn calc_pan(mut value:u8) -> f32 {
         if value == 127 {value = 128;}
         (value as f32)/128.0
     }
-    fn search_note(&mut self, note_num: u8) -> i8 {
-        let max_note = self.vcevec.len();
-        for i in 0..max_note {
-            if self.vcevec[i].note == note_num /*&& sts == self.vcevec[i].status()*/ {
-                return i as i8;
-            }            
+    fn sound_note(&mut self, dt2: u8, vel: f32) {
+        if let Some(cur_vce) = &mut self.vce {
+            cur_vce.slide(dt2, vel);
         }
-        return -1
-    }
-    fn remove_note(&mut self, nt_idx:i8) {
-        assert!(nt_idx != NO_NOTE);
-        self.vcevec.remove(nt_idx as usize);
-        if nt_idx == self.active_vce_index {
-            self.active_vce_index = NO_NOTE;
+        else {// 1st Note On
+            let mut new_vce = match self.spare_vce.take() {
+                Some(mut vce) => {
+                    vce.restart(dt2, vel, self.mdlt, self.pit, self.vol, self.exp, &self.inst_prm);
+                    vce
+                }
+                None => Box::new(
+                    sg_voice::VoiceSg::new(dt2, vel, 
+                        self.mdlt, self.pit, self.vol, self.exp, Rc::clone(&self.inst_prm), &self.cfg)),
+            };
+            new_vce.set_vowel(self.vowel.0, self.vowel.1);
+            new_vce.start_sound();
+            self.vce = Some(new_vce);
         }
-        else if nt_idx < self.active_vce_index {
-            self.active_vce_index -= 1;
+    }
+    fn release_note(&mut self, dt2: u8) {
+        let was_active = self.note_stack.is_active(dt2);
+        self.note_stack.remove(dt2);
+        if !was_active {return}
+        match self.note_stack.active() {
+            // go back to the held note
+            Some(nt) => self.sound_note(nt.note, nt.vel),
+            None => {
+                if let Some(cur_vce) = &mut self.vce {
+                    cur_vce.set_release_time_ratio(self.pedal.release_time_ratio());
+                    cur_vce.note_off();
+                }
+            }
         }
     }
 }
\ No newline at end of file
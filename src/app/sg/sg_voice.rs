@@ -24,16 +24,21 @@ use crate::app::sg::*;
 pub struct VoiceSg {
     // Note
     note: u8,
-    vel: u8,
+    vel: f32,   //  0.0-1.0
     status: NoteStatus,
     damp_counter: u32,
+    cfg: msgf_if::AudioConfig,
     lvl_check_buf: msgf_afrm::AudioFrame,
+    lbuf: msgf_cfrm::CtrlFrame,     //  work buffers of process()
+    aegbuf: msgf_cfrm::CtrlFrame,
     // Synth
     osc: msgf_additive::Additive,
     aeg: msgf_aeg::Aeg,
     lfo: msgf_lfo::Lfo,
     max_note_vol: f32,
     ended: bool,
+    chan_pit: f32,      //  [cent] pitch bend and tuning of the channel
+    note_pit: f32,      //  [cent] per-note pitch
     vowel_x: f32,   // -1..0..1
     vowel_y: f32,   // -1..0..1
 }
@@ -52,7 +57,7 @@ impl msgf_voice::Voice for VoiceSg {
         self.aeg.move_to_attack();
         self.lfo.start();
     }
-    fn slide(&mut self, note:u8, vel:u8) {
+    fn slide(&mut self, note:u8, vel:f32) {
         self.note = note;
         self.vel = vel;
         self.status = NoteStatus::DuringNoteOn;
@@ -65,8 +70,11 @@ impl msgf_voice::Voice for VoiceSg {
         self.status = NoteStatus::AfterNoteOff;
         self.aeg.move_to_release()
     }
+    fn set_release_time_ratio(&mut self, ratio: f32) {
+        self.aeg.set_release_time_ratio(ratio);
+    }
     fn note_num(&self) -> u8 {self.note}
-    fn velocity(&self) -> u8 {self.vel}
+    fn velocity(&self) -> f32 {self.vel}
     fn change_pmd(&mut self, value: f32) {
         self.osc.change_pmd(value);
     }
@@ -74,7 +82,12 @@ impl msgf_voice::Voice for VoiceSg {
         self.max_note_vol = VoiceSg::calc_vol(volume, expression);
     }
     fn pitch(&mut self, pitch:f32) {
-        self.osc.change_pitch(pitch);
+        self.chan_pit = pitch;
+        self.osc.change_pitch(self.chan_pit + self.note_pit);
+    }
+    fn per_note_pitch(&mut self, pitch: f32) {
+        self.note_pit = pitch;
+        self.osc.change_pitch(self.chan_pit + self.note_pit);
     }
     fn status(&self) -> NoteStatus {self.status}
     fn damp(&mut self) {
@@ -85,8 +98,8 @@ impl msgf_voice::Voice for VoiceSg {
         if self.ended {return self.ended;}
 
         //  Pitch Control
-        let cbuf_size = msgf_cfrm::CtrlFrame::get_cbuf_size(in_number_frames);
-        let lbuf = &mut msgf_cfrm::CtrlFrame::new(cbuf_size);
+        self.lbuf.next_block(in_number_frames);
+        let lbuf = &mut self.lbuf;
 
         //  LFO
         self.lfo.process_c(lbuf);
@@ -95,7 +108,8 @@ impl msgf_voice::Voice for VoiceSg {
         self.osc.process_ac(abuf, lbuf);
 
         //  AEG
-        let aegbuf = &mut msgf_cfrm::CtrlFrame::new(cbuf_size);
+        self.aegbuf.next_block(in_number_frames);
+        let aegbuf = &mut self.aegbuf;
         self.aeg.process_c(aegbuf);
 
         //  Volume
@@ -103,16 +117,9 @@ impl msgf_voice::Voice for VoiceSg {
             let aeg = aegbuf.ctrl_for_audio(i);
             abuf.mul_rate(i, self.max_note_vol*aeg);
         }
-        msgf_voice::manage_note_level(self, abuf, aegbuf)
-    }
-    fn set_prm(&mut self, prm_type: u8, value: u8) {
-        match prm_type {
-            0 => self.lfo.set_freq(value),  // 16 : LFO freq.
-            1 => self.lfo.set_wave(value),  // 17 : LFO Wave
-            2 => {self.vowel_x = (value as f32-64.0)/64.0; self.calc_formant();}
-            3 => {self.vowel_y = (value as f32-64.0)/64.0; self.calc_formant();}
-            _ => ()
-        }
+        let level = aegbuf.get_max_level();
+        let fs_ratio = self.cfg.sampling_ratio();
+        msgf_voice::manage_note_level(self, abuf, level, fs_ratio)
     }
     fn put_lvl_check_buf(&mut self, lvl: f32) {self.lvl_check_buf.put_into_abuf(lvl);}
     fn damp_counter(&self) -> u32 {self.damp_counter}
@@ -122,24 +129,67 @@ impl msgf_voice::Voice for VoiceSg {
 }
 
 impl VoiceSg {
-    pub fn new(note:u8, vel:u8, _pmd:f32, pit:f32, vol:u8, exp:u8,
-        inst_prm: Rc<Cell<sg_prm::SynthParameter>>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(note:u8, vel:f32, _pmd:f32, pit:f32, vol:u8, exp:u8,
+        inst_prm: Rc<Cell<sg_prm::SynthParameter>>, cfg: &msgf_if::AudioConfig) -> Self {
         let tprm: &sg_prm::SynthParameter = &inst_prm.get();
+        let lvl_size = lvl_check_buf_size(cfg.sampling_freq);
         Self {
             note,
             vel,
             status: NoteStatus::DuringNoteOn,
             damp_counter: 0,
-            lvl_check_buf: msgf_afrm::AudioFrame::new((msgf_if::SAMPLING_FREQ/100.0) as usize, msgf_if::MAX_BUFFER_SIZE),
-            osc: msgf_additive::Additive::new(&tprm.osc, note, pit),
-            aeg: msgf_aeg::Aeg::new(&tprm.aeg),
-            lfo: msgf_lfo::Lfo::new(&tprm.lfo),
+            cfg: *cfg,
+            lvl_check_buf: msgf_afrm::AudioFrame::new(lvl_size, lvl_size),
+            lbuf: msgf_cfrm::CtrlFrame::new(msgf_cfrm::CtrlFrame::get_cbuf_size(cfg.max_block_size)),
+            aegbuf: msgf_cfrm::CtrlFrame::new(msgf_cfrm::CtrlFrame::get_cbuf_size(cfg.max_block_size)),
+            osc: msgf_additive::Additive::new(&tprm.osc, note, pit, cfg.sampling_freq),
+            aeg: msgf_aeg::Aeg::new(&tprm.aeg, cfg.sampling_ratio()),
+            lfo: msgf_lfo::Lfo::new(&tprm.lfo, cfg.sampling_freq),
             max_note_vol: VoiceSg::calc_vol(vol, exp),
             ended: false,
+            chan_pit: pit,
+            note_pit: 0.0,
             vowel_x: 0.0,
             vowel_y: 0.0,
         }
     }
+    #[allow(clippy::too_many_arguments)]
+    pub fn restart(&mut self, note:u8, vel:f32, _pmd:f32, pit:f32, vol:u8, exp:u8,
+        inst_prm: &Rc<Cell<sg_prm::SynthParameter>>) {
+        //  Reuse this voice for a new note without heap allocation
+        let tprm: &sg_prm::SynthParameter = &inst_prm.get();
+        self.note = note;
+        self.vel = vel;
+        self.status = NoteStatus::DuringNoteOn;
+        self.damp_counter = 0;
+        self.lvl_check_buf.clr_abuf();
+        self.lbuf.reset();
+        self.aegbuf.reset();
+        self.osc = msgf_additive::Additive::new(&tprm.osc, note, pit, self.cfg.sampling_freq);
+        self.aeg = msgf_aeg::Aeg::new(&tprm.aeg, self.cfg.sampling_ratio());
+        self.lfo = msgf_lfo::Lfo::new(&tprm.lfo, self.cfg.sampling_freq);
+        self.max_note_vol = VoiceSg::calc_vol(vol, exp);
+        self.ended = false;
+        self.chan_pit = pit;
+        self.note_pit = 0.0;
+        self.vowel_x = 0.0;
+        self.vowel_y = 0.0;
+    }
+    pub fn update_prm(&mut self, grp: usize, tprm: &sg_prm::SynthParameter) {
+        //  Parameter change while sounding
+        match grp {
+            sg_prm::PRM_GRP_OSC => self.osc.update_prm(&tprm.osc, self.note),
+            sg_prm::PRM_GRP_AEG => self.aeg.update_prm(&tprm.aeg),
+            sg_prm::PRM_GRP_LFO => self.lfo.update_prm(&tprm.lfo),
+            _ => (),
+        }
+    }
+    pub fn set_vowel(&mut self, vowel_x: f32, vowel_y: f32) {
+        self.vowel_x = vowel_x;
+        self.vowel_y = vowel_y;
+        self.calc_formant();
+    }
     fn calc_vol(vol:u8, exp:u8) -> f32 {
         let exp_sq = exp as f32;
         let vol_sq = vol as f32;
@@ -10,4 +10,22 @@
 //
 pub mod sgf_inst;
 pub mod sgf_voice;
-pub mod sgf_prm;
\ No newline at end of file
+pub mod sgf_prm;
+
+use crate::msgf_if;
+use crate::app;
+use crate::core::{msgf_inst, msgf_program};
+
+fn create(bank: &app::PatchBank, tone: usize, vol: u8, pan: u8, exp: u8, cfg: &msgf_if::AudioConfig) -> Box<dyn msgf_inst::Inst> {
+    Box::new(sgf_inst::InstSgf::new(bank.sgf(), tone, vol, pan, exp, cfg))
+}
+pub fn register(reg: &mut msgf_program::ProgramRegistry, tone_count: usize) {
+    //  tone_count: tones of the patch bank
+    reg.add(0, 9, "Sing by Filter", 0, create);
+    //  for Touch Keyboard
+    reg.add(0, 10, "Sing by Filter (Touch Keyboard)", 0, create);
+    reg.add(0, 18, "Sing by Filter (Touch Keyboard 2022)", 0, create);
+    reg.add(msgf_program::bank_number(1, 0), 2, "Sing by Filter (Touch Keyboard)", 0, create);
+    //  Bank 4/0-127: tones of a patch bank ([sgf.N] -> LSB N/128, program N%128)
+    reg.add_tone_bank(4, "Sing by Filter User", create, tone_count);
+}
@@ -11,6 +11,8 @@
 use crate::engine::msgf_vocal::*;
 use crate::engine::msgf_aeg::*;
 use crate::engine::msgf_lfo::*;
+use crate::core::msgf_patch::{self, PatchPrm};
+use crate::core::msgf_param::{self, ParamDef, ParamGroup, ParamPrm, ParamUnit, Smoothing};
 
 #[derive(Copy, Clone)]
 pub struct SynthParameter {
@@ -18,6 +20,59 @@ pub struct SynthParameter {
     pub aeg: AegParameter,
     pub lfo: LfoParameter,
 }
+impl PatchPrm for SynthParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if let Some(k) = msgf_patch::split_prefix(key, "osc") {self.osc.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "aeg") {self.aeg.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "lfo") {self.lfo.set_patch(k, value)}
+        else {Err(msgf_patch::unknown_key())}
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        self.osc.write_patch(&format!("{}osc.", prefix), out);
+        self.aeg.write_patch(&format!("{}aeg.", prefix), out);
+        self.lfo.write_patch(&format!("{}lfo.", prefix), out);
+    }
+}
+//  Parameter Tree: parameter ID is the serial number in this order
+pub const PRM_GRP_OSC: usize = 0;
+pub const PRM_GRP_AEG: usize = 1;
+pub const PRM_GRP_LFO: usize = 2;
+pub const PRM_GRP_LPF: usize = 3;
+pub const PRM_GRP_FORMANT: usize = 4;
+//  not a part of the tone (CC#16)
+pub const LPF_PARAMS: [ParamDef; 1] = [
+    ParamDef {name: "cutoff", min: 0.0, max: 2540.0, unit: ParamUnit::Hz, smoothing: Smoothing::Control},  // 0: through
+];
+//  not a part of the tone (CC#18/19)
+pub const FORMANT_PARAMS: [ParamDef; 2] = [
+    ParamDef {name: "vowel_x", min: -1.0, max: 1.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+    ParamDef {name: "vowel_y", min: -1.0, max: 1.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+];
+pub const PARAMS: [ParamGroup; 5] = [
+    ParamGroup {engine: "osc", params: &VOCAL_PARAMS},
+    ParamGroup {engine: "aeg", params: &AEG_PARAMS},
+    ParamGroup {engine: "lfo", params: &LFO_PARAMS},
+    ParamGroup {engine: "lpf", params: &LPF_PARAMS},
+    ParamGroup {engine: "formant", params: &FORMANT_PARAMS},
+];
+impl ParamPrm for SynthParameter {
+    fn param(&self, id: usize) -> f32 {
+        match msgf_param::locate(&PARAMS, id) {
+            Some((PRM_GRP_OSC, idx)) => self.osc.param(idx),
+            Some((PRM_GRP_AEG, idx)) => self.aeg.param(idx),
+            Some((PRM_GRP_LFO, idx)) => self.lfo.param(idx),
+            _ => 0.0,
+        }
+    }
+    fn set_param(&mut self, id: usize, value: f32) {
+        match msgf_param::locate(&PARAMS, id) {
+            Some((PRM_GRP_OSC, idx)) => self.osc.set_param(idx, value),
+            Some((PRM_GRP_AEG, idx)) => self.aeg.set_param(idx, value),
+            Some((PRM_GRP_LFO, idx)) => self.lfo.set_param(idx, value),
+            _ => (),
+        }
+    }
+}
 
 pub const SGF_MAX_TONE_COUNT:usize = 1;
 pub const SGF_TONE_PRM: [SynthParameter; SGF_MAX_TONE_COUNT] = [
@@ -28,10 +28,13 @@ const NOTE_OFFSET:u8 = 24;
 pub struct VoiceSgf {
     // Note
     note: u8,
-    vel: u8,
+    vel: f32,   //  0.0-1.0
     status: NoteStatus,
     damp_counter: u32,
+    cfg: msgf_if::AudioConfig,
     lvl_check_buf: msgf_afrm::AudioFrame,
+    lbuf: msgf_cfrm::CtrlFrame,     //  work buffers of process()
+    aegbuf: msgf_cfrm::CtrlFrame,
     // Synth
     vcl: msgf_vocal::Vocal,
     lpf: msgf_biquad::Biquad,
@@ -41,6 +44,8 @@ pub struct VoiceSgf {
     lfo: msgf_lfo::Lfo,
     max_note_vol: f32,
     ended: bool,
+    chan_pit: f32,      //  [cent] pitch bend and tuning of the channel
+    note_pit: f32,      //  [cent] per-note pitch
     vowel_x: f32,   // -1..0..1
     vowel_y: f32,   // -1..0..1
     fmnt_adjust_vol: f32,   //  0..1
@@ -64,7 +69,7 @@ impl msgf_voice::Voice for VoiceSgf {
         self.frm2.set_bpf(DEFAULT_F2, BPF_RESO);
         self.lfo.start();
     }
-    fn slide(&mut self, note:u8, vel:u8) {
+    fn slide(&mut self, note:u8, vel:f32) {
         let real_note = note - NOTE_OFFSET;
         self.note = real_note;
         self.vel = vel;
@@ -79,8 +84,11 @@ impl msgf_voice::Voice for VoiceSgf {
         self.status = NoteStatus::AfterNoteOff;
         self.aeg.move_to_release()
     }
+    fn set_release_time_ratio(&mut self, ratio: f32) {
+        self.aeg.set_release_time_ratio(ratio);
+    }
     fn note_num(&self) -> u8 {self.note + NOTE_OFFSET}
-    fn velocity(&self) -> u8 {self.vel}
+    fn velocity(&self) -> f32 {self.vel}
     fn change_pmd(&mut self, value: f32) {
         self.vcl.change_pmd(value);
     }
@@ -88,7 +96,12 @@ impl msgf_voice::Voice for VoiceSgf {
         self.max_note_vol = VoiceSgf::calc_vol(volume, expression);
     }
     fn pitch(&mut self, pitch:f32) {
-        self.vcl.change_pitch(pitch);
+        self.chan_pit = pitch;
+        self.vcl.change_pitch(self.chan_pit + self.note_pit);
+    }
+    fn per_note_pitch(&mut self, pitch: f32) {
+        self.note_pit = pitch;
+        self.vcl.change_pitch(self.chan_pit + self.note_pit);
     }
     fn status(&self) -> NoteStatus {self.status}
     fn damp(&mut self) {
@@ -99,8 +112,8 @@ impl msgf_voice::Voice for VoiceSgf {
         if self.ended {return self.ended;}
 
         //  Pitch Control
-        let cbuf_size = msgf_cfrm::CtrlFrame::get_cbuf_size(in_number_frames);
-        let lbuf = &mut msgf_cfrm::CtrlFrame::new(cbuf_size);
+        self.lbuf.next_block(in_number_frames);
+        let lbuf = &mut self.lbuf;
 
         //  LFO
         self.lfo.process_c(lbuf);
@@ -114,7 +127,8 @@ impl msgf_voice::Voice for VoiceSgf {
         self.frm2.process_a(abuf);
 
         //  AEG
-        let aegbuf = &mut msgf_cfrm::CtrlFrame::new(cbuf_size);
+        self.aegbuf.next_block(in_number_frames);
+        let aegbuf = &mut self.aegbuf;
         self.aeg.process_c(aegbuf);
 
         //  Volume
@@ -123,16 +137,9 @@ impl msgf_voice::Voice for VoiceSgf {
             let aeg = aegbuf.ctrl_for_audio(i);
             abuf.mul_rate(i, tmpvol*aeg);
         }
-        msgf_voice::manage_note_level(self, abuf, aegbuf)
-    }
-    fn set_prm(&mut self, prm_type: u8, value: u8) {
-        match prm_type {
-            0 => self.lpf.set_lpf((value as f32)*20.0,1.0),  // 16 : LPF cutoff
-            //1 => self.lfo.set_wave(value),  // 17 : LFO Wave
-            2 => {self.vowel_x = (value as f32-64.0)/64.0; self.calc_formant();}
-            3 => {self.vowel_y = (value as f32-64.0)/64.0; self.calc_formant();}
-            _ => ()
-        }
+        let level = aegbuf.get_max_level();
+        let fs_ratio = self.cfg.sampling_ratio();
+        msgf_voice::manage_note_level(self, abuf, level, fs_ratio)
     }
     fn put_lvl_check_buf(&mut self, lvl: f32) {self.lvl_check_buf.put_into_abuf(lvl);}
     fn damp_counter(&self) -> u32 {self.damp_counter}
@@ -142,30 +149,83 @@ impl msgf_voice::Voice for VoiceSgf {
 }
 
 impl VoiceSgf {
-    pub fn new(org_note:u8, vel:u8, pmd:f32, pit:f32, vol:u8, exp:u8,
-        inst_prm: Rc<Cell<sgf_prm::SynthParameter>>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(org_note:u8, vel:f32, pmd:f32, pit:f32, vol:u8, exp:u8,
+        inst_prm: Rc<Cell<sgf_prm::SynthParameter>>, cfg: &msgf_if::AudioConfig) -> Self {
         let tprm: &sgf_prm::SynthParameter = &inst_prm.get();
+        let lvl_size = lvl_check_buf_size(cfg.sampling_freq);
         let real_note = org_note - NOTE_OFFSET;
         Self {
             note: real_note,
             vel,
             status: NoteStatus::DuringNoteOn,
             damp_counter: 0,
-            lvl_check_buf: msgf_afrm::AudioFrame::new((msgf_if::SAMPLING_FREQ/100.0) as usize, msgf_if::MAX_BUFFER_SIZE),
-            vcl: msgf_vocal::Vocal::new(&tprm.osc, real_note, pmd, pit),
-            lpf: msgf_biquad::Biquad::new(),
-            frm1: msgf_biquad::Biquad::new(),
-            frm2: msgf_biquad::Biquad::new(),
-            aeg: msgf_aeg::Aeg::new(&tprm.aeg),
-            lfo: msgf_lfo::Lfo::new(&tprm.lfo),
+            cfg: *cfg,
+            lvl_check_buf: msgf_afrm::AudioFrame::new(lvl_size, lvl_size),
+            lbuf: msgf_cfrm::CtrlFrame::new(msgf_cfrm::CtrlFrame::get_cbuf_size(cfg.max_block_size)),
+            aegbuf: msgf_cfrm::CtrlFrame::new(msgf_cfrm::CtrlFrame::get_cbuf_size(cfg.max_block_size)),
+            vcl: msgf_vocal::Vocal::new(&tprm.osc, real_note, pmd, pit, cfg.sampling_freq),
+            lpf: msgf_biquad::Biquad::new(cfg.sampling_freq),
+            frm1: msgf_biquad::Biquad::new(cfg.sampling_freq),
+            frm2: msgf_biquad::Biquad::new(cfg.sampling_freq),
+            aeg: msgf_aeg::Aeg::new(&tprm.aeg, cfg.sampling_ratio()),
+            lfo: msgf_lfo::Lfo::new(&tprm.lfo, cfg.sampling_freq),
             max_note_vol: VoiceSgf::calc_vol(vol, exp),
             ended: false,
+            chan_pit: pit,
+            note_pit: 0.0,
             vowel_x: 0.0,
             vowel_y: 0.0,
             fmnt_adjust_vol: 1.0,
             scl_adjust_vol: VoiceSgf::calc_scaling_vol(real_note),
         }
     }
+    #[allow(clippy::too_many_arguments)]
+    pub fn restart(&mut self, org_note:u8, vel:f32, pmd:f32, pit:f32, vol:u8, exp:u8,
+        inst_prm: &Rc<Cell<sgf_prm::SynthParameter>>) {
+        //  Reuse this voice for a new note without heap allocation
+        let tprm: &sgf_prm::SynthParameter = &inst_prm.get();
+        let real_note = org_note - NOTE_OFFSET;
+        self.note = real_note;
+        self.vel = vel;
+        self.status = NoteStatus::DuringNoteOn;
+        self.damp_counter = 0;
+        self.lvl_check_buf.clr_abuf();
+        self.lbuf.reset();
+        self.aegbuf.reset();
+        self.vcl = msgf_vocal::Vocal::new(&tprm.osc, real_note, pmd, pit, self.cfg.sampling_freq);
+        self.lpf = msgf_biquad::Biquad::new(self.cfg.sampling_freq);
+        self.frm1 = msgf_biquad::Biquad::new(self.cfg.sampling_freq);
+        self.frm2 = msgf_biquad::Biquad::new(self.cfg.sampling_freq);
+        self.aeg = msgf_aeg::Aeg::new(&tprm.aeg, self.cfg.sampling_ratio());
+        self.lfo = msgf_lfo::Lfo::new(&tprm.lfo, self.cfg.sampling_freq);
+        self.max_note_vol = VoiceSgf::calc_vol(vol, exp);
+        self.ended = false;
+        self.chan_pit = pit;
+        self.note_pit = 0.0;
+        self.vowel_x = 0.0;
+        self.vowel_y = 0.0;
+        self.fmnt_adjust_vol = 1.0;
+        self.scl_adjust_vol = VoiceSgf::calc_scaling_vol(real_note);
+    }
+    pub fn update_prm(&mut self, grp: usize, tprm: &sgf_prm::SynthParameter) {
+        //  Parameter change while sounding
+        match grp {
+            sgf_prm::PRM_GRP_OSC => self.vcl.update_prm(&tprm.osc, self.note),
+            sgf_prm::PRM_GRP_AEG => self.aeg.update_prm(&tprm.aeg),
+            sgf_prm::PRM_GRP_LFO => self.lfo.update_prm(&tprm.lfo),
+            _ => (),
+        }
+    }
+    pub fn set_lpf(&mut self, cutoff: f32) {
+        if cutoff == 0.0 {self.lpf.set_thru();}
+        else {self.lpf.set_lpf(cutoff, 1.0);}
+    }
+    pub fn set_vowel(&mut self, vowel_x: f32, vowel_y: f32) {
+        self.vowel_x = vowel_x;
+        self.vowel_y = vowel_y;
+        self.calc_formant();
+    }
     fn calc_scaling_vol(note:u8) -> f32 {
         1.0 - 0.01*((note as f32)-60.0)
     }
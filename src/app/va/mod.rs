@@ -10,4 +10,32 @@
 //
 pub mod va_inst;
 pub mod va_voice;
-pub mod va_prm;
\ No newline at end of file
+pub mod va_prm;
+
+use crate::msgf_if;
+use crate::app;
+use crate::core::{msgf_inst, msgf_program};
+
+fn create(bank: &app::PatchBank, tone: usize, vol: u8, pan: u8, exp: u8, cfg: &msgf_if::AudioConfig) -> Box<dyn msgf_inst::Inst> {
+    Box::new(va_inst::InstVa::new(bank.va(), tone, vol, pan, exp, cfg))
+}
+pub fn register(reg: &mut msgf_program::ProgramRegistry, tone_count: usize) {
+    //  tone_count: tones of the patch bank
+    //  Bank 0/0, tone numbers of va_prm
+    reg.add(0, 0, "VA Sine Bell", 0, create);
+    reg.add(0, 1, "VA Saw Lead", 1, create);
+    reg.add(0, 2, "VA Square", 2, create);
+    reg.add(0, 3, "VA Pulse Bass", 3, create);
+    reg.add(0, 4, "VA Sine", 4, create);
+    reg.add(0, 5, "VA Saw", 5, create);
+    reg.add(0, 6, "VA Super Saw", 6, create);
+    reg.add(0, 7, "VA Thick Bass", 7, create);
+    //  for Touch Keyboard MFT2022 version
+    reg.add(0, 16, "VA Sine (Touch Keyboard)", 4, create);
+    reg.add(0, 17, "VA Saw (Touch Keyboard)", 5, create);
+    //  Bank 1/0: Touch Keyboard set
+    reg.add(msgf_program::bank_number(1, 0), 0, "VA Sine (Touch Keyboard)", 4, create);
+    reg.add(msgf_program::bank_number(1, 0), 1, "VA Saw (Touch Keyboard)", 5, create);
+    //  Bank 2/0-127: tones of a patch bank ([va.N] -> LSB N/128, program N%128)
+    reg.add_tone_bank(2, "VA User", create, tone_count);
+}
@@ -11,24 +11,29 @@
 use std::rc::Rc;
 use std::cell::Cell;
 use crate::msgf_if;
+use crate::app;
 use crate::core::*;
 use crate::core::msgf_voice::*;
 use crate::core::msgf_disp::MsgfDisplay;
 use crate::engine::*;
 use crate::engine::msgf_gen::Engine;
+use crate::core::msgf_param::{self, ParamPrm};
 use crate::app::va::*;
 
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
-const MAX_PB_RANGE:f32 = 1200.0;
-const MIDI_MAX_PB_VAL:f32 = 8192.0;
-const MIDI_CENTER_VAL:f32 = 64.0;
+const PB_SENS: u16 = 1200;  //  [cent]
+const STEAL_MARGIN: usize = 8;  //  spare voices for stolen ones fading out
+const VOICE_POOL_SIZE: usize = MAX_POLYPHONY + STEAL_MARGIN;
 //---------------------------------------------------------
 pub struct InstVa {
     vce_audio: msgf_afrm::AudioFrame,
     inst_audio: msgf_afrm::AudioFrame,
-    vcevec: Vec<va_voice::VoiceVa>,
+    vcevec: Vec<va_voice::VoiceVa>,     //  sounding voices, the oldest first
+    spare_vce: Vec<va_voice::VoiceVa>,  //  voice pool, reused without allocation
+    polyphony: usize,
+    steal: StealPolicy,
     delay: msgf_delay::Delay,
     inst_number: usize,
     mdlt: f32,  //  0.0..0.5
@@ -36,6 +41,16 @@ pub struct InstVa {
     vol: u8,    //  0..127
     pan: f32,   //  -1..0..+1
     exp: u8,    //  0..127
+    pedal: msgf_pedal::Pedal,
+    prtm: bool,
+    prtm_time: f32,         //  [sec]
+    prtm_src: Option<u8>,   //  source note by CC84, for the next note on
+    last_note: Option<u8>,
+    mono: bool,
+    legato: bool,
+    note_stack: msgf_note_stack::NoteStack,
+    params: msgf_param::ParamSet,
+    tone: va_prm::SynthParameter,    //  values of the tone, for param_default()
     inst_prm: Rc<Cell<va_prm::SynthParameter>>,
 }
 //---------------------------------------------------------
@@ -49,15 +64,15 @@ impl Drop for InstVa {
 impl msgf_inst::Inst for InstVa {
 /*
     fn new(inst_number: usize, vol: u8, pan: u8, exp: u8) -> Self {
-        let max_tone = va_prm::MAX_TONE_COUNT;
+        let max_tone = va_prm::tone_count();
         let mut inst_num = inst_number;
         if inst_number >= max_tone {
             inst_num = max_tone-1;
         }
-        let prm = Rc::new(Cell::new(va_prm::TONE_PRM[inst_number]));
+        let prm = Rc::new(Cell::new(va_prm::tone(inst_number)));
         Self {
-            vce_audio: msgf_afrm::AudioFrame::new(0,msgf_if::MAX_BUFFER_SIZE),
-            inst_audio: msgf_afrm::AudioFrame::new(0,msgf_if::MAX_BUFFER_SIZE),
+            vce_audio: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
+            inst_audio: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
             vcevec: Vec::new(),
             delay: msgf_delay::Delay::new(&prm.get().delay),
             inst_number: inst_num,
@@ -70,13 +85,24 @@ impl msgf_inst::Inst for InstVa {
         }
     }
 */
-    fn change_inst(&mut self, mut inst_number: usize, vol: u8, pan: u8, exp: u8) {
-        let max_tone = va_prm::MAX_TONE_COUNT;
+    fn change_inst(&mut self, bank: &app::PatchBank, mut inst_number: usize, vol: u8, pan: u8, exp: u8) {
+        let tones = bank.va();
+        let max_tone = tones.len();
         if inst_number >= max_tone {
             inst_number = max_tone-1;
         }
-        let _ = &self.inst_prm.replace(va_prm::TONE_PRM[inst_number]);
-        self.delay = msgf_delay::Delay::new(&self.inst_prm.get().delay);
+        while let Some(vce) = self.vcevec.pop() {
+            self.spare_vce.push(vce);
+        }
+        self.pedal.reset();
+        self.note_stack.clear();
+        let tprm = tones[inst_number];
+        self.tone = tprm;
+        let _ = &self.inst_prm.replace(tprm);
+        self.params.reset(|id| tprm.param(id));
+        self.delay.reset(&self.inst_prm.get().delay);
+        self.prtm_src = None;
+        self.last_note = None;
         self.inst_number = inst_number;
         self.mdlt = self.inst_prm.get().osc.lfo_depth;
         self.pit = 0.0;
@@ -85,30 +111,44 @@ impl msgf_inst::Inst for InstVa {
         self.exp = exp;
     }
     fn note_off(&mut self, dt2: u8, _dt3: u8) {
-        let nt_opt = self.search_note(dt2, NoteStatus::DuringNoteOn);
-        if let Some(nt) = nt_opt {
-            nt.note_off();
+        if self.pedal.note_off(dt2) {
+            self.release_note(dt2);
         }
     }
-    fn note_on(&mut self, dt2: u8, dt3: u8) {
-        let mut new_voice = va_voice::VoiceVa::new(
-            dt2, dt3, self.mdlt, self.pit, self.vol, self.exp, Rc::clone(&self.inst_prm)
-        );
-        new_voice.start_sound();
-        self.vcevec.push(new_voice);
+    fn note_on_f(&mut self, dt2: u8, vel: f32) {
+        self.pedal.note_on(dt2);
+        if self.mono {
+            self.note_stack.push(dt2, vel);
+            if self.note_stack.is_active(dt2) {
+                self.mono_note(dt2, vel);
+            }
+        } else {
+            self.start_voice(dt2, vel);
+        }
     }
-    fn per_note_after(&mut self, dt2: u8, dt3: u8) {
-        let nt = self.search_note( dt2, NoteStatus::DuringNoteOn);
+    fn per_note_after_f(&mut self, note: u8, value: f32) {
+        let nt = self.search_note(note, NoteStatus::DuringNoteOn);
         if let Some(vce) = nt {
-            let pmd = (dt3 as f32)/(127.0*12.0);    // MAX+-100[cent]
+            let pmd = value/12.0;   // MAX+-100[cent]
             vce.change_pmd(pmd);    //  0 - 1/12
         }
     }
-    fn modulation(&mut self, value: u8) {
-        let mdlt = 0.5f32*(value as f32)/127.0; // 0.0 - 0.5
+    fn channel_after_f(&mut self, value: f32) {
+        let pmd = value/12.0;   // MAX+-100[cent]
+        self.vcevec.iter_mut()
+            .filter(|vce| vce.status() == NoteStatus::DuringNoteOn)
+            .for_each(|vce| vce.change_pmd(pmd));
+    }
+    fn modulation_f(&mut self, value: f32) {
+        let mdlt = 0.5f32*value; // 0.0 - 0.5
         self.mdlt = mdlt;
         self.vcevec.iter_mut().for_each(|vce| vce.change_pmd(mdlt));
     }
+    fn per_note_pitch(&mut self, note: u8, pit: f32) {
+        self.vcevec.iter_mut()
+            .filter(|vce| vce.note_num() == note && vce.status() != NoteStatus::DuringDamp)
+            .for_each(|vce| vce.per_note_pitch(pit));
+    }
     fn volume(&mut self, value: u8) {
         self.vol = value;
         let exp = self.exp;
@@ -122,19 +162,75 @@ impl msgf_inst::Inst for InstVa {
         let vol = self.vol;
         self.vcevec.iter_mut().for_each(|vce| vce.amplitude(vol, value));
     }
-    fn pitch(&mut self, bend:i16, tune_coarse:u8, tune_fine:u8) {
-        let pit:f32 = ((bend as f32)*MAX_PB_RANGE)/MIDI_MAX_PB_VAL
-            + ((tune_coarse as f32)-MIDI_CENTER_VAL)*100.0 
-            + ((tune_fine as f32)-MIDI_CENTER_VAL)*100.0/MIDI_CENTER_VAL;
+    fn pitch(&mut self, pit: f32) {
         self.pit = pit;
         self.vcevec.iter_mut().for_each(|vce| vce.pitch(pit));
     }
-    fn sustain(&mut self, _value: u8) {}
+    fn pb_sens(&self) -> u16 {PB_SENS}
+    fn sustain(&mut self, value: u8) {
+        let released = self.pedal.sustain(value);
+        msgf_pedal::Pedal::for_each_note(&released, |nt| self.release_note(nt));
+    }
+    fn sostenuto(&mut self, value: u8) {
+        let released = self.pedal.sostenuto(value);
+        msgf_pedal::Pedal::for_each_note(&released, |nt| self.release_note(nt));
+    }
+    fn set_polyphony(&mut self, max_voice: usize, steal: StealPolicy) {
+        self.polyphony = max_voice.clamp(1, MAX_POLYPHONY);
+        self.steal = steal;
+    }
+    fn mono(&mut self, on: bool) {
+        //  Mode change works as All Notes Off
+        if self.mono != on {
+            self.vcevec.iter_mut()
+                .filter(|vce| vce.status() == NoteStatus::DuringNoteOn)
+                .for_each(|vce| vce.note_off());
+            self.note_stack.clear();
+            self.mono = on;
+        }
+    }
+    fn set_mono_config(&mut self, priority: msgf_note_stack::NotePriority, legato: bool) {
+        self.note_stack.set_priority(priority);
+        self.legato = legato;
+    }
+    fn portamento(&mut self, on: bool) {self.prtm = on;}
+    fn portamento_time(&mut self, value: u8) {
+        self.prtm_time = Self::calc_prtm_time(value);
+    }
+    fn portamento_ctrl(&mut self, src_note: u8) {self.prtm_src = Some(src_note);}
     fn all_sound_off(&mut self) {
+        self.pedal.reset();
+        self.note_stack.clear();
         self.vcevec.iter_mut().for_each(|vce| vce.damp());
     }
-    fn set_prm(&mut self, prm_type: u8, value: u8) {
-        self.vcevec.iter_mut().for_each(|vce| vce.set_prm(prm_type, value));
+    fn release_all(&mut self) {
+        self.pedal.reset();
+        self.note_stack.clear();
+        self.vcevec.iter_mut().filter(|vce| vce.status() == NoteStatus::DuringNoteOn)
+            .for_each(|vce| vce.note_off());
+    }
+    fn set_prm_f(&mut self, prm_type: u8, value: f32) {
+        //  CC#16-31
+        let (path, value) = match prm_type {
+            0 => ("lfo.freq", value*12.7),                      //  0.1[Hz] step by 7bit
+            1 => ("lfo.wave", (value*4.0).floor().min(3.0)),    //  bit 6-5 of 7bit
+            2 => ("vcf.cutoff", 20.0*2.0_f32.powf(value*10.0)), //  20[Hz]-20[kHz] in 10 octaves
+            3 => ("vcf.resonance", 0.5 + value*19.5),
+            _ => return,
+        };
+        if let Some(id) = self.params.find(path) {
+            self.set_param(id, value);
+        }
+    }
+    fn params(&self) -> Option<&msgf_param::ParamSet> {Some(&self.params)}
+    fn param_default(&self, id: usize) -> f32 {self.tone.param(id)}
+    fn set_param(&mut self, id: usize, value: f32) {
+        if let Some(value) = self.params.set(id, value) {
+            //  Control: applied step by step in process()
+            if self.params.smoothing(id) != msgf_param::Smoothing::Control {
+                self.apply_param(id, value);
+            }
+        }
     }
 /*    fn release_note(&mut self, nt: &va_voice::VoiceVa){
         let ntcmp = self.vcevec.iter_mut();
@@ -150,32 +246,37 @@ impl msgf_inst::Inst for InstVa {
       abuf_l: &mut msgf_afrm::AudioFrame,
       abuf_r: &mut msgf_afrm::AudioFrame,
       in_number_frames: usize) {
-        let sz = self.vcevec.len();
-        let mut ch_ended = vec![false; sz];
-        self.vce_audio.set_sample_number(in_number_frames as usize);
-        self.inst_audio.set_sample_number(in_number_frames as usize);
+        self.vce_audio.set_sample_number(in_number_frames);
+        self.inst_audio.set_sample_number(in_number_frames);
         self.inst_audio.clr_abuf();
 
+        //  Parameters in smoothing
+        let mut params = std::mem::take(&mut self.params);
+        params.advance(in_number_frames, |id, value| self.apply_param(id, value));
+        self.params = params;
+
         //  All voices get together 
-        for i in 0..sz {
-            if let Some(nt) = self.vcevec.get_mut(i) {
-                ch_ended[i] = nt.process(&mut self.vce_audio, in_number_frames);
-                self.inst_audio.mul_and_mix(&mut self.vce_audio, 1.0);
+        for vce in self.vcevec.iter_mut() {
+            if !vce.process(&mut self.vce_audio, in_number_frames) {
+                self.inst_audio.mul_and_mix(&self.vce_audio, 1.0);
             }
         }
 
         //  make audio stereo
-        abuf_l.mul_and_mix(&mut self.inst_audio, 1.0-self.pan);
-        abuf_r.mul_and_mix(&mut self.inst_audio, self.pan);
+        abuf_l.mul_and_mix(&self.inst_audio, 1.0-self.pan);
+        abuf_r.mul_and_mix(&self.inst_audio, self.pan);
 
         //  with Effect
         self.delay.process_as([abuf_l, abuf_r]);
 
-        for i in 0..sz {
-            if ch_ended[i] {
-                //  一つ消去したら、ループから抜ける
-                self.vcevec.remove(i);
-                break;
+        //  終了した Voice を全て pool に戻す
+        let mut i = 0;
+        while i < self.vcevec.len() {
+            if self.vcevec[i].ended() {
+                let vce = self.vcevec.remove(i);
+                self.spare_vce.push(vce);
+            } else {
+                i += 1;
             }
         }
     }
@@ -183,30 +284,153 @@ impl msgf_inst::Inst for InstVa {
 
 impl InstVa {
 
-    pub fn new(mut inst_number: usize, vol: u8, pan: u8, exp: u8) -> Self {
-        let max_tone = va_prm::MAX_TONE_COUNT;
+    pub fn new(tones: &[va_prm::SynthParameter], mut inst_number: usize, vol: u8, pan: u8, exp: u8, cfg: &msgf_if::AudioConfig) -> Self {
+        let max_tone = tones.len();
         if inst_number >= max_tone {
             inst_number = max_tone-1;
         }
-        let prm = Rc::new(Cell::new(va_prm::TONE_PRM[inst_number]));
+        let tprm = tones[inst_number];
+        let prm = Rc::new(Cell::new(tprm));
+        let spare_vce = (0..VOICE_POOL_SIZE).map(|_|
+            va_voice::VoiceVa::new(0, 0.0, 0.0, 0.0, vol, exp, Rc::clone(&prm), cfg)).collect();
         Self {
-            vce_audio: msgf_afrm::AudioFrame::new(0,msgf_if::MAX_BUFFER_SIZE),
-            inst_audio: msgf_afrm::AudioFrame::new(0,msgf_if::MAX_BUFFER_SIZE),
-            vcevec: Vec::new(),
-            delay: msgf_delay::Delay::new(&prm.get().delay),
+            vce_audio: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
+            inst_audio: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
+            vcevec: Vec::with_capacity(VOICE_POOL_SIZE),
+            spare_vce,
+            polyphony: MAX_POLYPHONY,
+            steal: StealPolicy::ReleaseFirst,
+            delay: msgf_delay::Delay::new(&prm.get().delay, cfg.sampling_freq),
             inst_number,
             mdlt: prm.get().osc.lfo_depth,
             pit: 0.0,
             vol,
             pan: Self::calc_pan(pan),
             exp,
+            pedal: msgf_pedal::Pedal::new(),
+            prtm: false,
+            prtm_time: 0.0,
+            prtm_src: None,
+            last_note: None,
+            mono: false,
+            legato: true,
+            note_stack: msgf_note_stack::NoteStack::new(msgf_note_stack::NotePriority::Last),
+            params: msgf_param::ParamSet::new("va", &va_prm::PARAMS, cfg.sampling_freq, |id| tprm.param(id)),
+            tone: tprm,
             inst_prm: prm,
         }
     }
+    fn apply_param(&mut self, id: usize, value: f32) {
+        //  for the next notes, and the sounding voices
+        let mut tprm = self.inst_prm.get();
+        tprm.set_param(id, value);
+        self.inst_prm.set(tprm);
+        match msgf_param::locate(&va_prm::PARAMS, id) {
+            Some((va_prm::PRM_GRP_DELAY, _)) => self.delay.update_prm(&tprm.delay),
+            Some((va_prm::PRM_GRP_OSC, 2)) => {
+                //  lfo_depth works as Modulation Wheel
+                self.mdlt = value;
+                self.vcevec.iter_mut().for_each(|vce| vce.change_pmd(value));
+            }
+            Some((grp, _)) => self.vcevec.iter_mut().for_each(|vce| vce.update_prm(grp, &tprm)),
+            None => (),
+        }
+    }
+    fn calc_prtm_time(value: u8) -> f32 {
+        //  0:0[sec], 64:0.16[sec], 127:2.4[sec]
+        if value == 0 {return 0.0;}
+        0.01*2.0_f32.powf((value as f32)/16.0)
+    }
     fn calc_pan(mut value:u8) -> f32 {
         if value == 127 {value = 128;}
         (value as f32)/128.0
     }
+    fn mono_note(&mut self, dt2: u8, vel: f32) {
+        //  Mono: change the note of the sounding voice (legato),
+        //  or start a new voice after damping it (retrigger)
+        let crnt = self.mono_voice();
+        match crnt {
+            Some(idx) if self.legato => {
+                let vce = &mut self.vcevec[idx];
+                let src_note = vce.note_num();
+                vce.slide(dt2, vel);
+                if self.prtm {
+                    vce.glide_from(src_note, self.prtm_time);
+                }
+                self.last_note = Some(dt2);
+            }
+            _ => {
+                if let Some(idx) = crnt {
+                    self.vcevec[idx].damp();
+                }
+                self.start_voice(dt2, vel);
+            }
+        }
+    }
+    fn mono_voice(&self) -> Option<usize> {
+        self.vcevec.iter().rposition(|vce| vce.status() == NoteStatus::DuringNoteOn)
+    }
+    fn release_note(&mut self, dt2: u8) {
+        let ratio = self.pedal.release_time_ratio();
+        if self.mono {
+            let was_active = self.note_stack.is_active(dt2);
+            self.note_stack.remove(dt2);
+            if !was_active {return}
+            match self.note_stack.active() {
+                //  go back to the held note
+                Some(nt) => self.mono_note(nt.note, nt.vel),
+                None => {
+                    if let Some(idx) = self.mono_voice() {
+                        self.vcevec[idx].set_release_time_ratio(ratio);
+                        self.vcevec[idx].note_off();
+                    }
+                }
+            }
+            return;
+        }
+        if let Some(nt) = self.search_note(dt2, NoteStatus::DuringNoteOn) {
+            nt.set_release_time_ratio(ratio);
+            nt.note_off();
+        }
+    }
+    fn start_voice(&mut self, dt2: u8, vel: f32) {
+        let mut new_voice = self.alloc_voice(dt2);
+        new_voice.restart(dt2, vel, self.mdlt, self.pit, self.vol, self.exp, &self.inst_prm);
+        //  Portamento: from CC84 source note, or from the last note
+        let src = self.prtm_src.take().or(if self.prtm {self.last_note} else {None});
+        if let Some(src_note) = src {
+            new_voice.glide_from(src_note, self.prtm_time);
+        }
+        self.last_note = Some(dt2);
+        new_voice.start_sound();
+        self.vcevec.push(new_voice);
+    }
+    fn alloc_voice(&mut self, note: u8) -> va_voice::VoiceVa {
+        //  Voice Stealing: the chosen voice is damped quickly,
+        //  and keeps a pool slot until it ends
+        if self.steal == StealPolicy::SameNote {
+            if let Some(vce) = self.vcevec.iter_mut()
+                .find(|vce| vce.note_num() == note && vce.status() != NoteStatus::DuringDamp) {
+                vce.damp();
+            }
+        }
+        let active = self.vcevec.iter().filter(|vce| vce.status() != NoteStatus::DuringDamp).count();
+        if active >= self.polyphony {
+            if let Some(idx) = self.steal.select(&self.vcevec, |vce| vce.status(), |vce| vce.level()) {
+                self.vcevec[idx].damp();
+            }
+        }
+        match self.spare_vce.pop() {
+            Some(vce) => vce,
+            None => {
+                //  No spare voice: the oldest damping voice is cut off
+                let idx = self.vcevec.iter()
+                    .position(|vce| vce.status() == NoteStatus::DuringDamp)
+                    .unwrap_or(0);
+                self.vcevec.remove(idx)
+            }
+        }
+    }
     fn search_note(&mut self, note_num: u8, sts: NoteStatus) -> Option<&mut va_voice::VoiceVa> {
         let max_note = self.vcevec.len();
         let mut return_num = max_note;
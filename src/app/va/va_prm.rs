@@ -12,6 +12,10 @@ use crate::engine::msgf_osc::*;
 use crate::engine::msgf_aeg::*;
 use crate::engine::msgf_lfo::*;
 use crate::engine::msgf_delay::*;
+use crate::engine::msgf_noise::*;
+use crate::engine::msgf_vcf::*;
+use crate::core::msgf_patch::{self, PatchPrm};
+use crate::core::msgf_param::{self, ParamDef, ParamGroup, ParamPrm, ParamUnit, Smoothing};
 
 #[derive(Copy, Clone)]
 pub struct SynthParameter {
@@ -19,9 +23,232 @@ pub struct SynthParameter {
     pub aeg: AegParameter,
     pub lfo: LfoParameter,
     pub delay: DelayParameter,
+    pub osc2: OscParameter,     //  lfo_depth of osc is used for every oscillator
+    pub osc3: OscParameter,
+    pub mixer: MixerParameter,
+    pub vcf: VcfParameter,
+    pub feg: AegParameter,      //  filter envelope
 }
+//  Levels of the sources mixed in a voice
+#[derive(Copy, Clone)]
+pub struct MixerParameter {
+    pub osc1_level: f32,
+    pub osc2_level: f32,
+    pub osc3_level: f32,
+    pub sub_level: f32,         //  sub oscillator under osc
+    pub sub_octave: i32,        //  1 - 2 octaves under osc
+    pub sub_wave: WvType,
+    pub noise_level: f32,
+    pub noise_type: NoiseType,
+}
+impl PatchPrm for MixerParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "osc1_level" => self.osc1_level = msgf_patch::parse_value(value)?,
+            "osc2_level" => self.osc2_level = msgf_patch::parse_value(value)?,
+            "osc3_level" => self.osc3_level = msgf_patch::parse_value(value)?,
+            "sub_level" => self.sub_level = msgf_patch::parse_value(value)?,
+            "sub_octave" => self.sub_octave = msgf_patch::parse_value(value)?,
+            "sub_wave" => self.sub_wave = match value {
+                "Sine" => WvType::Sine,
+                "Saw" => WvType::Saw,
+                "Square" => WvType::Square,
+                "Pulse" => WvType::Pulse,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "noise_level" => self.noise_level = msgf_patch::parse_value(value)?,
+            "noise_type" => self.noise_type = match value {
+                "White" => NoiseType::White,
+                "Pink" => NoiseType::Pink,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "osc1_level", self.osc1_level);
+        msgf_patch::write_item(out, prefix, "osc2_level", self.osc2_level);
+        msgf_patch::write_item(out, prefix, "osc3_level", self.osc3_level);
+        msgf_patch::write_item(out, prefix, "sub_level", self.sub_level);
+        msgf_patch::write_item(out, prefix, "sub_octave", self.sub_octave);
+        msgf_patch::write_item(out, prefix, "sub_wave", self.sub_wave);
+        msgf_patch::write_item(out, prefix, "noise_level", self.noise_level);
+        msgf_patch::write_item(out, prefix, "noise_type", self.noise_type);
+    }
+}
+pub const MIXER_PARAMS: [ParamDef; 8] = [
+    ParamDef {name: "osc1_level", min: 0.0, max: 1.0, unit: ParamUnit::Level, smoothing: Smoothing::Control},
+    ParamDef {name: "osc2_level", min: 0.0, max: 1.0, unit: ParamUnit::Level, smoothing: Smoothing::Control},
+    ParamDef {name: "osc3_level", min: 0.0, max: 1.0, unit: ParamUnit::Level, smoothing: Smoothing::Control},
+    ParamDef {name: "sub_level", min: 0.0, max: 1.0, unit: ParamUnit::Level, smoothing: Smoothing::Control},
+    ParamDef {name: "sub_octave", min: 1.0, max: 2.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    ParamDef {name: "sub_wave", min: 0.0, max: 3.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    ParamDef {name: "noise_level", min: 0.0, max: 1.0, unit: ParamUnit::Level, smoothing: Smoothing::Control},
+    ParamDef {name: "noise_type", min: 0.0, max: 1.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+];
+impl ParamPrm for MixerParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.osc1_level,
+            1 => self.osc2_level,
+            2 => self.osc3_level,
+            3 => self.sub_level,
+            4 => self.sub_octave as f32,
+            5 => self.sub_wave as u8 as f32,
+            6 => self.noise_level,
+            _ => self.noise_type as u8 as f32,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.osc1_level = value,
+            1 => self.osc2_level = value,
+            2 => self.osc3_level = value,
+            3 => self.sub_level = value,
+            4 => self.sub_octave = value as i32,
+            5 => self.sub_wave = WvType::from_u8(value as u8),
+            6 => self.noise_level = value,
+            _ => self.noise_type = NoiseType::from_u8(value as u8),
+        }
+    }
+}
+impl PatchPrm for SynthParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if let Some(k) = msgf_patch::split_prefix(key, "osc") {self.osc.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "aeg") {self.aeg.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "lfo") {self.lfo.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "delay") {self.delay.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "osc2") {self.osc2.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "osc3") {self.osc3.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "mixer") {self.mixer.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "vcf") {self.vcf.set_patch(k, value)}
+        else if let Some(k) = msgf_patch::split_prefix(key, "feg") {self.feg.set_patch(k, value)}
+        else {Err(msgf_patch::unknown_key())}
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        self.osc.write_patch(&format!("{}osc.", prefix), out);
+        self.aeg.write_patch(&format!("{}aeg.", prefix), out);
+        self.lfo.write_patch(&format!("{}lfo.", prefix), out);
+        self.delay.write_patch(&format!("{}delay.", prefix), out);
+        self.osc2.write_patch(&format!("{}osc2.", prefix), out);
+        self.osc3.write_patch(&format!("{}osc3.", prefix), out);
+        self.mixer.write_patch(&format!("{}mixer.", prefix), out);
+        self.vcf.write_patch(&format!("{}vcf.", prefix), out);
+        self.feg.write_patch(&format!("{}feg.", prefix), out);
+    }
+}
+//  Parameter Tree: parameter ID is the serial number in this order
+pub const PRM_GRP_OSC: usize = 0;
+pub const PRM_GRP_AEG: usize = 1;
+pub const PRM_GRP_LFO: usize = 2;
+pub const PRM_GRP_DELAY: usize = 3;
+pub const PRM_GRP_OSC2: usize = 4;
+pub const PRM_GRP_OSC3: usize = 5;
+pub const PRM_GRP_MIXER: usize = 6;
+pub const PRM_GRP_VCF: usize = 7;
+pub const PRM_GRP_FEG: usize = 8;
+//  osc2/osc3 have no lfo_depth: the lfo_depth of osc is used for every oscillator
+const OSC_LFO_DEPTH: usize = 2;     //  index in OSC_PARAMS
+pub const SUB_OSC_PARAMS: [ParamDef; OSC_PARAMS.len()-1] = {
+    let mut prms = [OSC_PARAMS[0]; OSC_PARAMS.len()-1];
+    let mut idx = 0;
+    while idx < prms.len() {
+        prms[idx] = OSC_PARAMS[osc_idx(idx)];
+        idx += 1;
+    }
+    prms
+};
+const fn osc_idx(sub_idx: usize) -> usize {
+    //  index in SUB_OSC_PARAMS -> OSC_PARAMS
+    if sub_idx < OSC_LFO_DEPTH {sub_idx} else {sub_idx + 1}
+}
+pub const PARAMS: [ParamGroup; 9] = [
+    ParamGroup {engine: "osc", params: &OSC_PARAMS},
+    ParamGroup {engine: "aeg", params: &AEG_PARAMS},
+    ParamGroup {engine: "lfo", params: &LFO_PARAMS},
+    ParamGroup {engine: "delay", params: &DELAY_PARAMS},
+    ParamGroup {engine: "osc2", params: &SUB_OSC_PARAMS},
+    ParamGroup {engine: "osc3", params: &SUB_OSC_PARAMS},
+    ParamGroup {engine: "mixer", params: &MIXER_PARAMS},
+    ParamGroup {engine: "vcf", params: &VCF_PARAMS},
+    ParamGroup {engine: "feg", params: &AEG_PARAMS},
+];
+impl ParamPrm for SynthParameter {
+    fn param(&self, id: usize) -> f32 {
+        match msgf_param::locate(&PARAMS, id) {
+            Some((PRM_GRP_OSC, idx)) => self.osc.param(idx),
+            Some((PRM_GRP_AEG, idx)) => self.aeg.param(idx),
+            Some((PRM_GRP_LFO, idx)) => self.lfo.param(idx),
+            Some((PRM_GRP_DELAY, idx)) => self.delay.param(idx),
+            Some((PRM_GRP_OSC2, idx)) => self.osc2.param(osc_idx(idx)),
+            Some((PRM_GRP_OSC3, idx)) => self.osc3.param(osc_idx(idx)),
+            Some((PRM_GRP_MIXER, idx)) => self.mixer.param(idx),
+            Some((PRM_GRP_VCF, idx)) => self.vcf.param(idx),
+            Some((_, idx)) => self.feg.param(idx),
+            None => 0.0,
+        }
+    }
+    fn set_param(&mut self, id: usize, value: f32) {
+        match msgf_param::locate(&PARAMS, id) {
+            Some((PRM_GRP_OSC, idx)) => self.osc.set_param(idx, value),
+            Some((PRM_GRP_AEG, idx)) => self.aeg.set_param(idx, value),
+            Some((PRM_GRP_LFO, idx)) => self.lfo.set_param(idx, value),
+            Some((PRM_GRP_DELAY, idx)) => self.delay.set_param(idx, value),
+            Some((PRM_GRP_OSC2, idx)) => self.osc2.set_param(osc_idx(idx), value),
+            Some((PRM_GRP_OSC3, idx)) => self.osc3.set_param(osc_idx(idx), value),
+            Some((PRM_GRP_MIXER, idx)) => self.mixer.set_param(idx, value),
+            Some((PRM_GRP_VCF, idx)) => self.vcf.set_param(idx, value),
+            Some((_, idx)) => self.feg.set_param(idx, value),
+            None => (),
+        }
+    }
+}
+
+//  osc only, the same sound as a single oscillator
+const OSC_UNUSED: OscParameter = OscParameter {
+    coarse_tune: 0,
+    fine_tune: 0.0,
+    lfo_depth: 0.0,
+    wv_type: WvType::Saw,
+    prtm_curve: PrtmCurve::Linear,
+    algorithm: OscAlgorithm::PolyBlep,
+    pulse_width: 0.5,
+    pwm_lfo: 0.0,
+    pwm_eg: 0.0,
+    sync: SyncType::Off,
+    sync_pitch: 0.0,
+};
+//  no filter
+const VCF_THRU: VcfParameter = VcfParameter {
+    filter_type: VcfType::Thru,
+    slope: VcfSlope::Db12,
+    cutoff: 20000.0,
+    resonance: 0.707,
+    eg_depth: 0.0,
+    key_track: 0.0,
+    vel_sens: 0.0,
+    model: VcfModel::Biquad,
+    drive: 1.0,
+};
+const FEG_FLAT: AegParameter = AegParameter {
+    attack_rate: 1.0,
+    decay_rate: 1.0,
+    sustain_level: 1.0,
+    release_rate: 1.0,
+};
+const MIXER_OSC1: MixerParameter = MixerParameter {
+    osc1_level: 1.0,
+    osc2_level: 0.0,
+    osc3_level: 0.0,
+    sub_level: 0.0,
+    sub_octave: 1,
+    sub_wave: WvType::Square,
+    noise_level: 0.0,
+    noise_type: NoiseType::White,
+};
 
-pub const MAX_TONE_COUNT:usize = 6;
+pub const MAX_TONE_COUNT:usize = 8;
 pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
     // No.0
     SynthParameter {
@@ -30,6 +257,13 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             fine_tune: 0.0,     //  f32 : 1.0 means 1[cent]
             lfo_depth: 0.02,    //  f32 : 1.0 means +-1oct.
             wv_type: WvType::Sine,
+            prtm_curve: PrtmCurve::Linear,   //  portamento: linear in cents or exponential
+            algorithm: OscAlgorithm::Additive,
+            pulse_width: 0.1,   //  f32 : duty of Pulse 0.0-1.0
+            pwm_lfo: 0.0,       //  f32 : +-1.0 means +-100% by LFO
+            pwm_eg: 0.0,        //  f32 : 1.0 means +100% by AEG
+            sync: SyncType::Off,
+            sync_pitch: 0.0,    //  f32 : [cent] over the master
         },
         aeg: AegParameter {
             attack_rate: 0.9,   //  0.0-1.0
@@ -49,6 +283,11 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             r_time: 0.5,        //  0.0 - 1.0 [sec]
             att_ratio: 0.4,     //  attenuation
         },
+        osc2: OSC_UNUSED,       //  oscillators over osc
+        osc3: OSC_UNUSED,
+        mixer: MIXER_OSC1,
+        vcf: VCF_THRU,
+        feg: FEG_FLAT,
     },
     // No.1
     SynthParameter {
@@ -57,6 +296,13 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             fine_tune: 0.0,
             lfo_depth: 0.0,
             wv_type: WvType::Saw,
+            prtm_curve: PrtmCurve::Exponential,
+            algorithm: OscAlgorithm::Additive,
+            pulse_width: 0.1,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: SyncType::Off,
+            sync_pitch: 0.0,
         },
         aeg: AegParameter {
             attack_rate: 0.9,
@@ -76,6 +322,11 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             r_time: 0.4,
             att_ratio: 0.3,
         },
+        osc2: OSC_UNUSED,
+        osc3: OSC_UNUSED,
+        mixer: MIXER_OSC1,
+        vcf: VCF_THRU,
+        feg: FEG_FLAT,
     },
     // No.2
     SynthParameter {
@@ -84,6 +335,13 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             fine_tune: 0.0,
             lfo_depth: 0.02,
             wv_type: WvType::Square,
+            prtm_curve: PrtmCurve::Linear,
+            algorithm: OscAlgorithm::Additive,
+            pulse_width: 0.1,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: SyncType::Off,
+            sync_pitch: 0.0,
         },
         aeg: AegParameter {
             attack_rate: 0.5,
@@ -103,6 +361,11 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             r_time: 0.5,
             att_ratio: 0.2,
         },
+        osc2: OSC_UNUSED,
+        osc3: OSC_UNUSED,
+        mixer: MIXER_OSC1,
+        vcf: VCF_THRU,
+        feg: FEG_FLAT,
     },
     // No.3
     SynthParameter {
@@ -111,6 +374,13 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             fine_tune: 0.0,
             lfo_depth: 0.04,
             wv_type: WvType::Pulse,
+            prtm_curve: PrtmCurve::Linear,
+            algorithm: OscAlgorithm::Additive,
+            pulse_width: 0.1,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: SyncType::Off,
+            sync_pitch: 0.0,
         },
         aeg: AegParameter {
             attack_rate: 0.5,
@@ -130,6 +400,11 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             r_time: 0.7,
             att_ratio: 0.2,
         },
+        osc2: OSC_UNUSED,
+        osc3: OSC_UNUSED,
+        mixer: MIXER_OSC1,
+        vcf: VCF_THRU,
+        feg: FEG_FLAT,
     },
     // No.4
     SynthParameter {
@@ -138,6 +413,13 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             fine_tune: 0.0,     //  f32 : 1.0 means 1[cent]
             lfo_depth: 0.02,    //  f32 : 1.0 means +-1oct.
             wv_type: WvType::Sine,
+            prtm_curve: PrtmCurve::Linear,
+            algorithm: OscAlgorithm::Additive,
+            pulse_width: 0.1,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: SyncType::Off,
+            sync_pitch: 0.0,
         },
         aeg: AegParameter {
             attack_rate: 0.5,   //  0.0-1.0
@@ -157,6 +439,11 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             r_time: 0.5,        //  0.0 - 1.0 [sec]
             att_ratio: 0.4,     //  attenuation
         },
+        osc2: OSC_UNUSED,
+        osc3: OSC_UNUSED,
+        mixer: MIXER_OSC1,
+        vcf: VCF_THRU,
+        feg: FEG_FLAT,
     },
     // No.5
     SynthParameter {
@@ -165,6 +452,13 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             fine_tune: 0.0,     //  f32 : 1.0 means 1[cent]
             lfo_depth: 0.0,     //  f32 : 1.0 means +-1oct.
             wv_type: WvType::Saw,
+            prtm_curve: PrtmCurve::Linear,
+            algorithm: OscAlgorithm::Additive,
+            pulse_width: 0.1,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: SyncType::Off,
+            sync_pitch: 0.0,
         },
         aeg: AegParameter {
             attack_rate: 0.7,   //  0.0-1.0
@@ -184,5 +478,134 @@ pub const TONE_PRM: [SynthParameter; MAX_TONE_COUNT] = [
             r_time: 0.5,        //  0.0 - 1.0 [sec]
             att_ratio: 0.3,     //  attenuation
         },
+        osc2: OSC_UNUSED,
+        osc3: OSC_UNUSED,
+        mixer: MIXER_OSC1,
+        vcf: VCF_THRU,
+        feg: FEG_FLAT,
+    },
+    // No.6 : Super Saw
+    SynthParameter {
+        osc: OscParameter {
+            coarse_tune: 0,
+            fine_tune: 0.0,
+            lfo_depth: 0.0,
+            wv_type: WvType::Saw,
+            prtm_curve: PrtmCurve::Linear,
+            algorithm: OscAlgorithm::PolyBlep,
+            pulse_width: 0.5,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: SyncType::Off,
+            sync_pitch: 0.0,
+        },
+        aeg: AegParameter {
+            attack_rate: 0.7,
+            decay_rate: 0.05,
+            sustain_level: 0.7,
+            release_rate: 0.05,
+        },
+        lfo: LfoParameter {
+            freq: 5.0,
+            wave: LfoWave::Tri,
+            direction: LfoDirection::LfoBoth,
+            fadein_time: 100,
+            delay_time: 100,
+        },
+        delay: DelayParameter {
+            l_time: 0.4,
+            r_time: 0.5,
+            att_ratio: 0.3,
+        },
+        osc2: OscParameter {fine_tune: 12.0, ..OSC_UNUSED},     //  detuned Saw
+        osc3: OscParameter {fine_tune: -12.0, ..OSC_UNUSED},
+        mixer: MixerParameter {
+            osc1_level: 0.6,
+            osc2_level: 0.6,
+            osc3_level: 0.6,
+            ..MIXER_OSC1
+        },
+        vcf: VcfParameter {
+            filter_type: VcfType::Lpf,
+            slope: VcfSlope::Db12,
+            cutoff: 3000.0,
+            resonance: 1.0,
+            eg_depth: 1.0,
+            key_track: 0.5,
+            vel_sens: 1.0,
+            model: VcfModel::Biquad,
+            drive: 1.0,
+        },
+        feg: AegParameter {
+            attack_rate: 0.5,
+            decay_rate: 0.02,
+            sustain_level: 0.3,
+            release_rate: 0.05,
+        },
+    },
+    // No.7 : Thick Bass
+    SynthParameter {
+        osc: OscParameter {
+            coarse_tune: -12,
+            fine_tune: 0.0,
+            lfo_depth: 0.0,
+            wv_type: WvType::Saw,
+            prtm_curve: PrtmCurve::Exponential,
+            algorithm: OscAlgorithm::PolyBlep,
+            pulse_width: 0.5,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: SyncType::Off,
+            sync_pitch: 0.0,
+        },
+        aeg: AegParameter {
+            attack_rate: 0.9,
+            decay_rate: 0.05,
+            sustain_level: 0.6,
+            release_rate: 0.1,
+        },
+        lfo: LfoParameter {
+            freq: 4.0,
+            wave: LfoWave::Tri,
+            direction: LfoDirection::LfoBoth,
+            fadein_time: 100,
+            delay_time: 200,
+        },
+        delay: DelayParameter {
+            l_time: 0.5,
+            r_time: 0.5,
+            att_ratio: 0.1,
+        },
+        osc2: OscParameter {
+            coarse_tune: -12,
+            fine_tune: 7.0,
+            wv_type: WvType::Pulse,
+            pulse_width: 0.3,
+            ..OSC_UNUSED
+        },
+        osc3: OSC_UNUSED,
+        mixer: MixerParameter {
+            osc1_level: 0.7,
+            osc2_level: 0.5,
+            sub_level: 0.6,
+            ..MIXER_OSC1
+        },
+        vcf: VcfParameter {
+            filter_type: VcfType::Lpf,
+            slope: VcfSlope::Db24,
+            cutoff: 200.0,
+            resonance: 8.0,
+            eg_depth: 4.0,
+            key_track: 0.5,
+            vel_sens: 1.0,
+            model: VcfModel::Ladder,
+            drive: 2.0,
+        },
+        feg: AegParameter {
+            attack_rate: 0.9,
+            decay_rate: 0.05,
+            sustain_level: 0.2,
+            release_rate: 0.1,
+        },
     },
 ];
\ No newline at end of file
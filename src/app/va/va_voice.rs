@@ -24,18 +24,34 @@ use crate::app::va::*;
 pub struct VoiceVa {
     // Note
     note: u8,
-    vel: u8,
+    vel: f32,   //  0.0-1.0
     status: NoteStatus,
     damp_counter: u32,
+    cfg: msgf_if::AudioConfig,
     lvl_check_buf: msgf_afrm::AudioFrame,
+    lbuf: msgf_cfrm::CtrlFrame,     //  work buffers of process()
+    aegbuf: msgf_cfrm::CtrlFrame,
+    fegbuf: msgf_cfrm::CtrlFrame,
+    syncbuf: msgf_afrm::AudioFrame,
+    srcbuf: msgf_afrm::AudioFrame,  //  each source before the mixer
     // Synth
-    osc: msgf_osc::Osc,
+    osc: [msgf_osc::Osc; OSC_NUM],
+    sync_osc: msgf_osc::Osc,    //  master of sync at the pitch of osc1
+    sub_osc: msgf_osc::Osc,
+    noise: msgf_noise::Noise,
+    mixer: va_prm::MixerParameter,
+    vcf: msgf_vcf::Vcf,
+    feg: msgf_aeg::Aeg,     //  filter envelope
     aeg: msgf_aeg::Aeg,
     lfo: msgf_lfo::Lfo,
     max_note_vol: f32,
     emphasis_vol: f32,
+    crnt_level: f32,    //  AEG level of the last block, for voice stealing
     ended: bool,
+    chan_pit: f32,      //  [cent] pitch bend and tuning of the channel
+    note_pit: f32,      //  [cent] per-note pitch
 }
+const OSC_NUM: usize = 3;
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
@@ -49,23 +65,43 @@ impl PartialEq for VoiceVa {
 impl msgf_voice::Voice for VoiceVa {
     fn start_sound(&mut self) {
         self.aeg.move_to_attack();
+        self.feg.move_to_attack();
         self.lfo.start();
     }
+    fn slide(&mut self, note:u8, vel:f32) {
+        //  Legato: pitch only, without envelope retrigger
+        self.note = note;
+        self.vel = vel;
+        self.for_each_osc(|osc| osc.change_note(note));
+        self.vcf.change_note(note);
+    }
     fn note_off(&mut self) {
         self.status = NoteStatus::AfterNoteOff;
-        self.aeg.move_to_release()
+        self.aeg.move_to_release();
+        self.feg.move_to_release()
+    }
+    fn set_release_time_ratio(&mut self, ratio: f32) {
+        self.aeg.set_release_time_ratio(ratio);
+        self.feg.set_release_time_ratio(ratio);
     }
     fn note_num(&self) -> u8 {self.note}
-    fn velocity(&self) -> u8 {self.vel}
+    fn velocity(&self) -> f32 {self.vel}
     fn change_pmd(&mut self, value: f32) {
-        self.osc.change_pmd(value);
+        self.for_each_osc(|osc| osc.change_pmd(value));
         self.emphasis_vol = 1.0 + value*6.0; // 1.0 - 1.5
     }
     fn amplitude(&mut self, volume: u8, expression: u8) {
         self.max_note_vol = VoiceVa::calc_vol(volume, expression);
     }
     fn pitch(&mut self, pitch:f32) {
-        self.osc.change_pitch(pitch);
+        self.chan_pit = pitch;
+        let pitch = self.chan_pit + self.note_pit;
+        self.for_each_osc(|osc| osc.change_pitch(pitch));
+    }
+    fn per_note_pitch(&mut self, pitch: f32) {
+        self.note_pit = pitch;
+        let pitch = self.chan_pit + self.note_pit;
+        self.for_each_osc(|osc| osc.change_pitch(pitch));
     }
     fn status(&self) -> NoteStatus {self.status}
     fn damp(&mut self) {
@@ -76,32 +112,58 @@ impl msgf_voice::Voice for VoiceVa {
         if self.ended {return self.ended;}
 
         //  Pitch Control
-        let cbuf_size = msgf_cfrm::CtrlFrame::get_cbuf_size(in_number_frames);
-        let lbuf = &mut msgf_cfrm::CtrlFrame::new(cbuf_size);
+        self.lbuf.next_block(in_number_frames);
+        let lbuf = &mut self.lbuf;
 
         //  LFO
         self.lfo.process_c(lbuf);
 
-        //  Oscillator
-        self.osc.process_ac(abuf, lbuf);
-
         //  AEG
-        let aegbuf = &mut msgf_cfrm::CtrlFrame::new(cbuf_size);
+        self.aegbuf.next_block(in_number_frames);
+        let aegbuf = &mut self.aegbuf;
         self.aeg.process_c(aegbuf);
 
+        //  Oscillators, Sub Oscillator and Noise are mixed (AEG also modulates the pulse width)
+        abuf.clr_abuf();
+        self.srcbuf.set_sample_number(abuf.sample_number);
+        let mixer = &self.mixer;
+        let levels = [mixer.osc1_level, mixer.osc2_level, mixer.osc3_level];
+        let oscs = &mut self.osc;
+        if (0..OSC_NUM).any(|n| levels[n] > 0.0 && oscs[n].is_synced()) {
+            self.syncbuf.set_sample_number(abuf.sample_number);
+            self.sync_osc.process_sync_src(lbuf, &mut self.syncbuf);
+        }
+        for n in 0..OSC_NUM {
+            if levels[n] <= 0.0 {continue;}
+            let sbuf = if oscs[n].is_synced() {Some(&self.syncbuf)} else {None};
+            oscs[n].process_mod(&mut self.srcbuf, lbuf, Some(aegbuf), sbuf);
+            abuf.mul_and_mix(&self.srcbuf, levels[n]);
+        }
+        if mixer.sub_level > 0.0 {
+            self.sub_osc.process_mod(&mut self.srcbuf, lbuf, Some(aegbuf), None);
+            abuf.mul_and_mix(&self.srcbuf, mixer.sub_level);
+        }
+        if mixer.noise_level > 0.0 {
+            self.noise.process_a(&mut self.srcbuf);
+            abuf.mul_and_mix(&self.srcbuf, mixer.noise_level);
+        }
+
+        //  VCF with its own envelope
+        self.fegbuf.next_block(in_number_frames);
+        if !self.vcf.is_thru() {
+            self.feg.process_c(&mut self.fegbuf);
+            self.vcf.process_ac(abuf, &mut self.fegbuf);
+        }
+
         //  Volume
         for i in 0..abuf.sample_number {
             let aeg = aegbuf.ctrl_for_audio(i);
             abuf.mul_rate(i, self.max_note_vol*self.emphasis_vol*aeg);
         }
-        msgf_voice::manage_note_level(self, abuf, aegbuf)
-    }
-    fn set_prm(&mut self, prm_type: u8, value: u8) {
-        match prm_type {
-            0 => self.lfo.set_freq(value),
-            1 => self.lfo.set_wave(value),
-            _ => ()
-        }
+        self.crnt_level = aegbuf.get_max_level();
+        let level = aegbuf.get_max_level();
+        let fs_ratio = self.cfg.sampling_ratio();
+        msgf_voice::manage_note_level(self, abuf, level, fs_ratio)
     }
     fn put_lvl_check_buf(&mut self, lvl: f32) {self.lvl_check_buf.put_into_abuf(lvl);}
     fn damp_counter(&self) -> u32 {self.damp_counter}
@@ -111,23 +173,117 @@ impl msgf_voice::Voice for VoiceVa {
 }
 
 impl VoiceVa {
-    pub fn new(note:u8, vel:u8, pmd:f32, pit:f32, vol:u8, exp:u8,
-        inst_prm: Rc<Cell<va_prm::SynthParameter>>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(note:u8, vel:f32, pmd:f32, pit:f32, vol:u8, exp:u8,
+        inst_prm: Rc<Cell<va_prm::SynthParameter>>, cfg: &msgf_if::AudioConfig) -> Self {
         let tprm: &va_prm::SynthParameter = &inst_prm.get();
+        let lvl_size = lvl_check_buf_size(cfg.sampling_freq);
         Self {
             note,
             vel,
             status: NoteStatus::DuringNoteOn,
             damp_counter: 0,
-            lvl_check_buf: msgf_afrm::AudioFrame::new((msgf_if::SAMPLING_FREQ/100.0) as usize, msgf_if::MAX_BUFFER_SIZE),
-            osc: msgf_osc::Osc::new(&tprm.osc, note, pmd, pit),
-            aeg: msgf_aeg::Aeg::new(&tprm.aeg),
-            lfo: msgf_lfo::Lfo::new(&tprm.lfo),
+            cfg: *cfg,
+            lvl_check_buf: msgf_afrm::AudioFrame::new(lvl_size, lvl_size),
+            lbuf: msgf_cfrm::CtrlFrame::new(msgf_cfrm::CtrlFrame::get_cbuf_size(cfg.max_block_size)),
+            aegbuf: msgf_cfrm::CtrlFrame::new(msgf_cfrm::CtrlFrame::get_cbuf_size(cfg.max_block_size)),
+            fegbuf: msgf_cfrm::CtrlFrame::new(msgf_cfrm::CtrlFrame::get_cbuf_size(cfg.max_block_size)),
+            syncbuf: msgf_afrm::AudioFrame::new(0, cfg.max_block_size),
+            srcbuf: msgf_afrm::AudioFrame::new(0, cfg.max_block_size),
+            osc: VoiceVa::osc_prms(tprm).map(|prm| msgf_osc::Osc::new(&prm, note, pmd, pit, cfg.sampling_freq)),
+            sync_osc: msgf_osc::Osc::new(&tprm.osc, note, pmd, pit, cfg.sampling_freq),
+            sub_osc: msgf_osc::Osc::new(&VoiceVa::sub_prm(tprm), note, pmd, pit, cfg.sampling_freq),
+            noise: msgf_noise::Noise::new(tprm.mixer.noise_type),
+            mixer: tprm.mixer,
+            vcf: msgf_vcf::Vcf::new(&tprm.vcf, note, vel, cfg.sampling_freq),
+            feg: msgf_aeg::Aeg::new(&tprm.feg, cfg.sampling_ratio()),
+            aeg: msgf_aeg::Aeg::new(&tprm.aeg, cfg.sampling_ratio()),
+            lfo: msgf_lfo::Lfo::new(&tprm.lfo, cfg.sampling_freq),
             max_note_vol: VoiceVa::calc_vol(vol, exp),
             emphasis_vol: 1.0,
+            crnt_level: 0.0,
             ended: false,
+            chan_pit: pit,
+            note_pit: 0.0,
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn restart(&mut self, note:u8, vel:f32, pmd:f32, pit:f32, vol:u8, exp:u8,
+        inst_prm: &Rc<Cell<va_prm::SynthParameter>>) {
+        //  Reuse this voice for a new note without heap allocation
+        let tprm: &va_prm::SynthParameter = &inst_prm.get();
+        self.note = note;
+        self.vel = vel;
+        self.status = NoteStatus::DuringNoteOn;
+        self.damp_counter = 0;
+        self.lvl_check_buf.clr_abuf();
+        self.lbuf.reset();
+        self.aegbuf.reset();
+        self.fegbuf.reset();
+        self.osc = VoiceVa::osc_prms(tprm).map(|prm| msgf_osc::Osc::new(&prm, note, pmd, pit, self.cfg.sampling_freq));
+        self.sync_osc = msgf_osc::Osc::new(&tprm.osc, note, pmd, pit, self.cfg.sampling_freq);
+        self.sub_osc = msgf_osc::Osc::new(&VoiceVa::sub_prm(tprm), note, pmd, pit, self.cfg.sampling_freq);
+        self.noise.change_type(tprm.mixer.noise_type);
+        self.mixer = tprm.mixer;
+        self.vcf = msgf_vcf::Vcf::new(&tprm.vcf, note, vel, self.cfg.sampling_freq);
+        self.feg = msgf_aeg::Aeg::new(&tprm.feg, self.cfg.sampling_ratio());
+        self.aeg = msgf_aeg::Aeg::new(&tprm.aeg, self.cfg.sampling_ratio());
+        self.lfo = msgf_lfo::Lfo::new(&tprm.lfo, self.cfg.sampling_freq);
+        self.max_note_vol = VoiceVa::calc_vol(vol, exp);
+        self.emphasis_vol = 1.0;
+        self.crnt_level = 0.0;
+        self.ended = false;
+        self.chan_pit = pit;
+        self.note_pit = 0.0;
+    }
+    pub fn update_prm(&mut self, grp: usize, tprm: &va_prm::SynthParameter) {
+        //  Parameter change while sounding
+        match grp {
+            va_prm::PRM_GRP_OSC => {
+                self.osc[0].update_prm(&tprm.osc, self.note);
+                self.sync_osc.update_prm(&tprm.osc, self.note);
+                self.sub_osc.update_prm(&VoiceVa::sub_prm(tprm), self.note);
+            }
+            va_prm::PRM_GRP_OSC2 => self.osc[1].update_prm(&tprm.osc2, self.note),
+            va_prm::PRM_GRP_OSC3 => self.osc[2].update_prm(&tprm.osc3, self.note),
+            va_prm::PRM_GRP_MIXER => {
+                self.mixer = tprm.mixer;
+                self.sub_osc.update_prm(&VoiceVa::sub_prm(tprm), self.note);
+                self.noise.change_type(tprm.mixer.noise_type);
+            }
+            va_prm::PRM_GRP_VCF => self.vcf.update_prm(&tprm.vcf),
+            va_prm::PRM_GRP_FEG => self.feg.update_prm(&tprm.feg),
+            va_prm::PRM_GRP_AEG => self.aeg.update_prm(&tprm.aeg),
+            va_prm::PRM_GRP_LFO => self.lfo.update_prm(&tprm.lfo),
+            _ => (),
         }
     }
+    pub fn level(&self) -> f32 {self.crnt_level*self.max_note_vol}
+    pub fn glide_from(&mut self, src_note: u8, time: f32) {
+        //  Portamento from src_note to this note in time[sec]
+        let cents = ((src_note as f32) - (self.note as f32))*100.0;
+        self.for_each_osc(|osc| osc.glide(cents, time));
+    }
+    fn osc_prms(tprm: &va_prm::SynthParameter) -> [msgf_osc::OscParameter; OSC_NUM] {
+        [tprm.osc, tprm.osc2, tprm.osc3]
+    }
+    fn sub_prm(tprm: &va_prm::SynthParameter) -> msgf_osc::OscParameter {
+        //  octaves under osc1, without modulation and sync
+        msgf_osc::OscParameter {
+            coarse_tune: tprm.osc.coarse_tune - 12*tprm.mixer.sub_octave.clamp(1, 2),
+            wv_type: tprm.mixer.sub_wave,
+            pwm_lfo: 0.0,
+            pwm_eg: 0.0,
+            sync: msgf_osc::SyncType::Off,
+            ..tprm.osc
+        }
+    }
+    fn for_each_osc(&mut self, mut f: impl FnMut(&mut msgf_osc::Osc)) {
+        //  pitch controls go to every oscillator
+        self.osc.iter_mut().for_each(&mut f);
+        f(&mut self.sync_osc);
+        f(&mut self.sub_osc);
+    }
     fn calc_vol(vol:u8, exp:u8) -> f32 {
         let exp_sq = exp as f32;
         let vol_sq = vol as f32;
@@ -0,0 +1,303 @@
+//
+//  msgf_render.rs
+//	Musical Sound Generator Framework
+//      Offline Renderer (MIDI event list -> WAV file)
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Usage:
+//      msgf-render [options] <event list | SMF> <output.wav>
+//
+//  SMF:
+//      Standard MIDI File format 0/1 (recognized by "MThd" at the top)
+//
+//  Event list:
+//      one MIDI message per line, "time[sec] status data..." (bytes in hex)
+//      any length of message (e.g. SysEx) is available
+//      '#' starts a comment.
+//          0.0   90 3c 64
+//          1.0   80 3c 40
+//
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use msgf::msgf_if;
+
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+const DEFAULT_BLOCK_SIZE: usize = 512;
+const DEFAULT_TAIL_TIME: f32 = 2.0;    //  [sec]
+const MAX_PART: usize = msgf_if::MAX_PART_NUM;
+const USAGE: &str = "\
+usage: msgf-render [options] <event list | SMF> <output.wav>
+options:
+    -r, --sample-rate <Hz>      sampling frequency (default 44100)
+    -b, --block-size <frames>   frames per process() call (default 512)
+    -t, --tail <sec>            time rendered after the end of the song (default 2.0)
+    -d, --bits <16|24|32>       16/24bit integer or 32bit float (default 16)
+    -s, --solo <part,...>       render only these parts (1-16)
+    -p, --patch <file>          load a patch bank text
+    -l, --log <level>           off, error, warn, info or debug
+                                (default: debug in debug builds, off in release builds)";
+
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+struct Options {
+    sample_rate: f32,
+    block_size: usize,
+    tail: f32,
+    bits: u16,
+    solo: Vec<usize>,   //  0 origin
+    patch: Option<String>,
+    log: Option<msgf_if::LogLevel>,
+    input: String,
+    output: String,
+}
+struct Event {
+    frame: u64,
+    msg: Vec<u8>,
+}
+
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+fn main() {
+    let opt = match parse_args(env::args().skip(1).collect()) {
+        Ok(opt) => opt,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(1);
+        }
+    };
+    let data = match fs::read(&opt.input) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("{}: {}", opt.input, err);
+            process::exit(1);
+        }
+    };
+    if let Some(level) = opt.log {
+        msgf_if::set_log_level(level);
+    }
+    msgf_if::set_log_sink(Some(Box::new(|level, category, msg| {
+        println!("[{}] {}: {}", level.name(), category.name(), msg);
+    })));
+    let mut msgf = msgf_if::Msgf::new(opt.sample_rate, opt.block_size);
+    msgf.init();
+    if let Some(patch) = &opt.patch {
+        let loaded = fs::read_to_string(patch).map_err(|e| e.to_string())
+            .and_then(|text| msgf.load_patch_bank(&text));
+        if let Err(err) = loaded {
+            eprintln!("{}: {}", patch, err);
+            process::exit(1);
+        }
+    }
+    let mut events: Vec<Event> = Vec::new();
+    let loaded = if data.starts_with(b"MThd") {
+        msgf.load_smf(&data).map_err(String::from)
+    } else {
+        let text = String::from_utf8_lossy(&data);
+        parse_event_list(&text, opt.sample_rate).map(|ev| events = ev)
+    };
+    if let Err(err) = loaded {
+        eprintln!("{}: {}", opt.input, err);
+        process::exit(1);
+    }
+    let (wav_l, wav_r) = render(&mut msgf, &opt, &events);
+    if let Err(err) = write_wav(&opt.output, &wav_l, &wav_r, opt.sample_rate as u32, opt.bits) {
+        eprintln!("{}: {}", opt.output, err);
+        process::exit(1);
+    }
+}
+fn parse_log_level(name: &str) -> Result<msgf_if::LogLevel, String> {
+    (msgf_if::LOG_LEVEL_OFF..=msgf_if::LOG_LEVEL_DEBUG)
+        .map(msgf_if::LogLevel::from_u8)
+        .find(|level| level.name() == name)
+        .ok_or(format!("unknown log level: {}", name))
+}
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut opt = Options {
+        sample_rate: msgf_if::DEFAULT_SAMPLING_FREQ,
+        block_size: DEFAULT_BLOCK_SIZE,
+        tail: DEFAULT_TAIL_TIME,
+        bits: 16,
+        solo: Vec::new(),
+        patch: None,
+        log: None,
+        input: String::new(),
+        output: String::new(),
+    };
+    let mut files: Vec<String> = Vec::new();
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        let mut value = |name: &str| it.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "-r" | "--sample-rate" => opt.sample_rate = parse_num(&value(&arg)?)?,
+            "-b" | "--block-size" => opt.block_size = parse_num(&value(&arg)?)?,
+            "-t" | "--tail" => opt.tail = parse_num(&value(&arg)?)?,
+            "-d" | "--bits" => opt.bits = parse_num(&value(&arg)?)?,
+            "-s" | "--solo" => {
+                for pt in value(&arg)?.split(',') {
+                    let num: usize = parse_num(pt)?;
+                    if num == 0 || num > MAX_PART {
+                        return Err(format!("no such part: {}", num));
+                    }
+                    opt.solo.push(num-1);
+                }
+            }
+            "-p" | "--patch" => opt.patch = Some(value(&arg)?),
+            "-l" | "--log" => opt.log = Some(parse_log_level(&value(&arg)?)?),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ => files.push(arg),
+        }
+    }
+    if files.len() != 2 {
+        return Err(String::from("an event list and an output file are needed"));
+    }
+    if opt.sample_rate <= 0.0 || opt.block_size == 0 || opt.tail < 0.0 {
+        return Err(String::from("invalid sample rate, block size or tail"));
+    }
+    if opt.bits != 16 && opt.bits != 24 && opt.bits != 32 {
+        return Err(format!("unsupported bits: {}", opt.bits));
+    }
+    opt.output = files.pop().unwrap();
+    opt.input = files.pop().unwrap();
+    Ok(opt)
+}
+fn parse_num<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.trim().parse::<T>().map_err(|_| format!("not a number: {}", s))
+}
+fn parse_event_list(text: &str, sample_rate: f32) -> Result<Vec<Event>, String> {
+    let mut events: Vec<Event> = Vec::new();
+    for (num, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let time = match tokens.next() {
+            Some(tm) => tm,
+            None => continue,   //  empty line
+        };
+        let time: f64 = parse_num(time).map_err(|e| format!("line {}: {}", num+1, e))?;
+        let mut msg: Vec<u8> = Vec::new();
+        for tk in tokens {
+            msg.push(u8::from_str_radix(tk, 16)
+                .map_err(|_| format!("line {}: not a hex byte: {}", num+1, tk))?);
+        }
+        if msg.first().is_none_or(|st| *st < 0x80) || time < 0.0 {
+            return Err(format!("line {}: invalid message", num+1));
+        }
+        let frame = (time*(sample_rate as f64)).round() as u64;
+        events.push(Event {frame, msg});
+    }
+    events.sort_by_key(|ev| ev.frame);  //  stable: same time keeps the order
+    Ok(events)
+}
+fn render(msgf: &mut msgf_if::Msgf, opt: &Options, events: &[Event]) -> (Vec<f32>, Vec<f32>) {
+    //  events: event list, or empty when a SMF is loaded into msgf
+    if !opt.solo.is_empty() {
+        for pt in 0..MAX_PART {
+            msgf.set_part_mute(pt, !opt.solo.contains(&pt));
+        }
+    }
+    msgf.play();
+    let tail_frames = (opt.tail*opt.sample_rate) as u64;
+    let mut wav_l: Vec<f32> = Vec::new();
+    let mut wav_r: Vec<f32> = Vec::new();
+    let mut abuf_l = vec![0.0_f32; opt.block_size];
+    let mut abuf_r = vec![0.0_f32; opt.block_size];
+    let mut ev_idx = 0;
+    let mut crnt_frame: u64 = 0;
+    //  event list: the song ends at the last event, SMF: when the sequencer stops
+    let song_frames = events.last().map_or(0, |ev| ev.frame);
+    let mut end_frame: Option<u64> = None;     //  end of the song + tail
+    loop {
+        let mut frames = opt.block_size as u64;
+        if crnt_frame < song_frames {
+            frames = frames.min(song_frames - crnt_frame);
+        } else if end_frame.is_none() && !msgf.is_playing() {
+            end_frame = Some(crnt_frame + tail_frames);
+        }
+        if let Some(end) = end_frame {
+            if crnt_frame >= end {break;}
+            frames = frames.min(end - crnt_frame);
+        }
+        while ev_idx < events.len() && events[ev_idx].frame < crnt_frame + frames {
+            let ev = &events[ev_idx];
+            let ofs = (ev.frame - crnt_frame) as u32;
+            msgf.recieve_midi_bytes_at(&ev.msg, ofs);
+            ev_idx += 1;
+        }
+        let frames = frames as usize;
+        msgf.process(&mut abuf_l[..frames], &mut abuf_r[..frames], frames as u32);
+        msgf_if::flush_log();
+        wav_l.extend_from_slice(&abuf_l[..frames]);
+        wav_r.extend_from_slice(&abuf_r[..frames]);
+        crnt_frame += frames as u64;
+    }
+    (wav_l, wav_r)
+}
+fn write_wav(path: &str, wav_l: &[f32], wav_r: &[f32], sample_rate: u32, bits: u16) -> io::Result<()> {
+    const CHANNELS: u16 = 2;
+    let format: u16 = if bits == 32 {3} else {1};   //  3: IEEE float, 1: PCM
+    let block_align: u16 = CHANNELS*bits/8;
+    let data_size: u32 = (wav_l.len() as u32)*(block_align as u32);
+    let mut wav: Vec<u8> = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16_u32.to_le_bytes());
+    wav.extend_from_slice(&format.to_le_bytes());
+    wav.extend_from_slice(&CHANNELS.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate*(block_align as u32)).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&bits.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    for (l, r) in wav_l.iter().zip(wav_r.iter()) {
+        for smpl in [*l, *r] {
+            let smpl = smpl.clamp(-1.0, 1.0);
+            match bits {
+                16 => wav.extend_from_slice(&((smpl*32767.0) as i16).to_le_bytes()),
+                24 => wav.extend_from_slice(&((smpl*8388607.0) as i32).to_le_bytes()[0..3]),
+                _  => wav.extend_from_slice(&smpl.to_le_bytes()),
+            }
+        }
+    }
+    let mut file = fs::File::create(path)?;
+    file.write_all(&wav)
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_list(list: &str, tail: &str) -> usize {
+        let args = ["-t", tail, "-b", "500", "in.txt", "out.wav"];
+        let opt = parse_args(args.iter().map(|arg| arg.to_string()).collect()).unwrap();
+        let events = parse_event_list(list, opt.sample_rate).unwrap();
+        let mut msgf = msgf_if::Msgf::new(opt.sample_rate, opt.block_size);
+        msgf.init();
+        let (wav_l, wav_r) = render(&mut msgf, &opt, &events);
+        assert_eq!(wav_l.len(), wav_r.len());
+        wav_l.len()
+    }
+
+    #[test]
+    fn tail_is_added_to_the_song() {
+        let list = "0.0 90 3c 64\n0.5 80 3c 40\n";
+        let song = (0.5*msgf_if::DEFAULT_SAMPLING_FREQ) as usize;
+        assert_eq!(render_list(list, "0"), song);
+        assert_eq!(render_list(list, "1.0"), song + msgf_if::DEFAULT_SAMPLING_FREQ as usize);
+        assert_eq!(render_list("", "0"), 0);
+    }
+}
@@ -12,4 +12,15 @@ pub mod msgf_cfrm;
 pub mod msgf_inst;
 pub mod msgf_part;
 pub mod msgf_voice;
-pub mod msgf_disp;
\ No newline at end of file
+pub mod msgf_disp;
+pub mod msgf_smf;
+pub mod msgf_seq;
+pub mod msgf_midi;
+pub mod msgf_ump;
+pub mod msgf_pedal;
+pub mod msgf_note_stack;
+pub mod msgf_patch;
+pub mod msgf_program;
+pub mod msgf_log;
+pub mod msgf_param;
+pub mod msgf_rpn;
@@ -35,14 +35,12 @@ impl AudioFrame {
     pub fn set_sample_number(&mut self, snum: usize) {
         self.sample_number = snum;      
     }
-    pub fn copy_to_sysbuf(&self, ab: &mut [f32; msgf_if::MAX_BUFFER_SIZE]) {
-        for i in 0..self.sample_number {
-            ab[i] = self.abuf[i];
-        }
+    pub fn copy_to_sysbuf(&self, ab: &mut [f32], ofs: usize) {
+        ab[ofs..ofs+self.sample_number].copy_from_slice(&self.abuf[..self.sample_number]);
     }
-    pub fn add_to_sysbuf(&self, ab: &mut [f32; msgf_if::MAX_BUFFER_SIZE]) {
+    pub fn add_to_sysbuf(&self, ab: &mut [f32], ofs: usize) {
         for i in 0..self.sample_number {
-            ab[i] += self.abuf[i];
+            ab[ofs+i] += self.abuf[i];
         }
     }
     pub fn _copy_to_abuf(&self, ab: &mut AudioFrame) {
@@ -99,6 +97,16 @@ impl AudioFrame {
         }
         max_val
     }
+    pub fn get_peak_level(&self) -> f32 {
+        self.abuf[..self.sample_number].iter().fold(0.0, |max, val| max.max(val.abs()))
+    }
+    pub fn fade_out(&mut self) {
+        //  linearly to 0 at the end of the frame
+        let snum = self.sample_number as f32;
+        for i in 0..self.sample_number {
+            self.abuf[i] *= (snum - 1.0 - i as f32)/snum;
+        }
+    }
     pub fn mul_and_mix(&mut self, srcbuf: &AudioFrame, mul_value:f32) {
         for i in 0..self.sample_number {
             if let Some(src_dt) = srcbuf.get_from_abuf(i) {
@@ -117,10 +125,6 @@ impl AudioFrame {
                 }
             }
         }
-        if cnt >= self.sample_number {
-            true
-        } else {
-            false
-        }
+        cnt >= self.sample_number
     }
 }
@@ -16,6 +16,8 @@ use crate::msgf_if;
 pub struct CtrlFrame {
     cbuf: Vec<f32>,
     pub sample_number: usize,
+    phase: usize,       //  audio frames of cbuf[0] passed in the former blocks
+    frames: usize,      //  audio frames of the current block
 }
 //---------------------------------------------------------
 //		Implements
@@ -25,10 +27,35 @@ impl CtrlFrame {
         Self {
             cbuf: vec![0.0; sample_number],
             sample_number,
+            phase: 0,
+            frames: 0,
         }
     }
     pub fn get_cbuf_size(in_number_frames: usize) -> usize {
-        in_number_frames/msgf_if::AUDIO_FRAME_PER_CONTROL
+        //  control values for in_number_frames starting at any phase
+        (in_number_frames + msgf_if::AUDIO_FRAME_PER_CONTROL - 1).div_ceil(msgf_if::AUDIO_FRAME_PER_CONTROL)
+    }
+    pub fn reset(&mut self) {
+        //  for a new note
+        self.sample_number = 0;
+        self.phase = 0;
+        self.frames = 0;
+    }
+    pub fn next_block(&mut self, in_number_frames: usize) {
+        //  A control value lasts AUDIO_FRAME_PER_CONTROL audio frames even if
+        //  the audio buffer is split by MIDI messages. The last value of the
+        //  former block is carried to cbuf[0] until its control frame ends.
+        let phase = (self.phase + self.frames)%msgf_if::AUDIO_FRAME_PER_CONTROL;
+        if phase > 0 {
+            self.cbuf[0] = self.cbuf[self.sample_number-1];
+        }
+        self.phase = phase;
+        self.frames = in_number_frames;
+        self.sample_number = (phase + in_number_frames).div_ceil(msgf_if::AUDIO_FRAME_PER_CONTROL);
+    }
+    pub fn first_to_calc(&self) -> usize {
+        //  engines calculate the control values from this index
+        if self.phase > 0 {1} else {0}
     }
     pub fn set_cbuf(&mut self, num: usize, val: f32) {
         self.cbuf[num] = val;
@@ -41,7 +68,7 @@ impl CtrlFrame {
     }
     pub fn _get_cbuf(&self, num: usize) -> f32 { self.cbuf[num]}
     pub fn ctrl_for_audio(&self, num: usize) -> f32 { // for Audio Buffer
-        self.cbuf[num/msgf_if::AUDIO_FRAME_PER_CONTROL]
+        self.cbuf[(num + self.phase)/msgf_if::AUDIO_FRAME_PER_CONTROL]
     }
     pub fn get_max_level(&self) -> f32 {
         let mut max_val: f32 = 0.0;
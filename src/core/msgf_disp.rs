@@ -8,16 +8,24 @@
 //  Released under the MIT license
 //  https://opensource.org/licenses/mit-license.php
 //
+//  Messages go to the lock-free log queue (msgf_log),
+//  so that they can be used in the audio thread.
+//  print_str()/print_prm() are Info level, log() takes any level.
+//
+use std::fmt;
+use crate::core::msgf_log::{self, LogLevel, LogCategory};
 
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
 pub trait MsgfDisplay {
-    fn print_str(&self, string: &str) {println!("{}",string)}
-    fn print_prm<T: std::fmt::Display>(&self, string: &str, prm: T) {
-        let mut all_str = String::from(string);
-        let prm_str: &str = &prm.to_string();   //  to_string で、String型、&を付けると &str型
-        all_str += prm_str;                     // '+' は String + &str のみ可能
-        println!("{}", all_str)
+    fn log(&self, level: LogLevel, category: LogCategory, args: fmt::Arguments) {
+        msgf_log::post(level, category, args)
+    }
+    fn print_str(&self, category: LogCategory, string: &str) {
+        self.log(LogLevel::Info, category, format_args!("{}", string))
+    }
+    fn print_prm<T: fmt::Display>(&self, category: LogCategory, string: &str, prm: T) {
+        self.log(LogLevel::Info, category, format_args!("{}{}", string, prm))
     }
-}
\ No newline at end of file
+}
@@ -9,23 +9,52 @@
 //  https://opensource.org/licenses/mit-license.php
 //
 use crate::core::*;
+use crate::app;
 pub trait Inst {
     //fn new(inst_number: usize, vol: u8, pan: u8, exp: u8) -> Self;
-    fn change_inst(&mut self, inst_number: usize, vol: u8, pan: u8, exp: u8);
+    //  reused for another tone without allocation, the sounding voices are stopped at once
+    //  inst_number: a tone of the app in bank
+    fn change_inst(&mut self, bank: &app::PatchBank, inst_number: usize, vol: u8, pan: u8, exp: u8);
     fn note_off(&mut self, dt2: u8, dt3: u8);
-    fn note_on(&mut self, dt2: u8, dt3: u8);
-    fn per_note_after(&mut self, _dt2: u8, _dt3: u8){}  // Default Implementations
-    fn modulation(&mut self, _value: u8){}              // Default Implementations
+    fn note_on(&mut self, dt2: u8, dt3: u8){self.note_on_f(dt2, u7_to_f(dt3))}
+    fn note_on_f(&mut self, note: u8, vel: f32);         // vel: 0.0-1.0 (MIDI 2.0 16bit)
+    //  7bit controllers go to the high resolution ones (normalised 0.0-1.0)
+    fn per_note_after(&mut self, dt2: u8, dt3: u8){self.per_note_after_f(dt2, u7_to_f(dt3))}
+    fn channel_after(&mut self, value: u8){self.channel_after_f(u7_to_f(value))}
+    fn modulation(&mut self, value: u8){self.modulation_f(u7_to_f(value))}
+    fn per_note_after_f(&mut self, _note: u8, _value: f32){}    // Default Implementations
+    fn channel_after_f(&mut self, _value: f32){}        // Default Implementations
+    fn modulation_f(&mut self, _value: f32){}           // Default Implementations
+    fn per_note_pitch(&mut self, _note: u8, _pit: f32){}    // [cent] Default Implementations
+    fn per_note_reset(&mut self, note: u8){             // Per-Note Management
+        self.per_note_pitch(note, 0.0);
+        self.per_note_after_f(note, 0.0);
+    }
     fn volume(&mut self, value: u8);
     fn pan(&mut self, _value: u8){}                     // Default Implementations
     fn expression(&mut self, _value: u8){}              // Default Implementations
-    fn pitch(&mut self, bend:i16, tune_coarse:u8, tune_fine:u8);
+    fn pitch(&mut self, pit: f32);                      // [cent] bend + tuning
+    fn pb_sens(&self) -> u16 {200}                      // [cent] default of RPN#0
     fn sustain(&mut self, _value: u8){}                 // Default Implementations
+    fn sostenuto(&mut self, _value: u8){}               // Default Implementations
+    fn set_polyphony(&mut self, _max_voice: usize, _steal: msgf_voice::StealPolicy){}
+    fn mono(&mut self, _on: bool){}                     // CC#126(true)/127(false)
+    fn set_mono_config(&mut self, _priority: msgf_note_stack::NotePriority, _legato: bool){}
+    fn portamento(&mut self, _on: bool){}               // Default Implementations
+    fn portamento_time(&mut self, _value: u8){}         // Default Implementations
+    fn portamento_ctrl(&mut self, _src_note: u8){}      // Default Implementations
     fn all_sound_off(&mut self);
-    fn set_prm(&mut self, _prm_type: u8, _value: u8){}  // prm_type:0-15, value:0-127
+    fn release_all(&mut self){self.all_sound_off()}     // note offs ignoring pedals
+    fn set_prm(&mut self, prm_type: u8, value: u8){self.set_prm_f(prm_type, u7_to_f(value))}
+    fn set_prm_f(&mut self, _prm_type: u8, _value: f32){}   // prm_type:0-15, value:0.0-1.0
+    //  Parameter Tree: id is the index of the table of the app
+    fn params(&self) -> Option<&msgf_param::ParamSet> {None}   // Default Implementations
+    fn param_default(&self, _id: usize) -> f32 {0.0}    // value of the tone
+    fn set_param(&mut self, _id: usize, _value: f32){}  // Default Implementations
     //fn release_note(&mut self, nt: &msgf_voice::Voice);
     fn process(&mut self,
         abuf_l: &mut msgf_afrm::AudioFrame,
         abuf_r: &mut msgf_afrm::AudioFrame,
         in_number_frames: usize);
-}
\ No newline at end of file
+}
+pub fn u7_to_f(value: u8) -> f32 {(value as f32)/127.0}
@@ -0,0 +1,239 @@
+//
+//  msgf_log.rs
+//	Musical Sound Generator Framework
+//      Log Queue and Sink
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Messages from the audio thread are formatted into fixed size lines
+//  without heap allocation, and queued into a bounded lock-free queue
+//  (any number of producers and consumers).
+//  The host drains the queue from a non real-time thread.
+//  When the queue is full, new messages are dropped and counted.
+//
+//  Each message has a level and a category. Messages under the log level
+//  or out of the category mask are not even formatted.
+//  The default level is Debug in debug builds, and Off (silent) in release builds.
+//  flush() passes the queued messages to the sink set by the host.
+//
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+pub const LOG_LINE_SIZE: usize = 96;    //  [byte] longer messages are truncated
+const LOG_QUEUE_SIZE: usize = 256;      //  must be a power of 2
+pub const LOG_CATEGORY_ALL: u8 = 0x0f;
+#[cfg(debug_assertions)]
+const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Debug;
+#[cfg(not(debug_assertions))]
+const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Off;
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+#[repr(u8)]
+pub enum LogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(u8)]
+pub enum LogCategory {
+    System = 0x01,  //  setup, configuration
+    Midi = 0x02,    //  received MIDI messages, program selection
+    Voice = 0x04,   //  voice lifecycle
+    Param = 0x08,   //  tone parameters, patch bank
+}
+//  level, category, message (called from a non real-time thread)
+pub type LogSink = Box<dyn FnMut(LogLevel, LogCategory, &str) + Send>;
+#[derive(Copy, Clone)]
+pub struct LogLine {
+    pub level: LogLevel,
+    pub category: LogCategory,
+    text: [u8; LOG_LINE_SIZE],
+    len: usize,
+}
+struct Slot {
+    seq: AtomicUsize,   //  stored as (sequence - index), so that all slots start from 0
+    line: UnsafeCell<LogLine>,
+}
+struct LogQueue {
+    slots: [Slot; LOG_QUEUE_SIZE],
+    enqueue_pos: AtomicUsize,
+    dequeue_pos: AtomicUsize,
+    dropped: AtomicUsize,
+}
+//  A slot is accessed only by the thread which won its position
+unsafe impl Sync for LogQueue {}
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(DEFAULT_LOG_LEVEL as u8);
+static LOG_CATEGORY: AtomicU8 = AtomicU8::new(LOG_CATEGORY_ALL);
+static LOG_SINK: Mutex<Option<LogSink>> = Mutex::new(None);
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl LogLevel {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LogLevel::Off,
+            1 => LogLevel::Error,
+            2 => LogLevel::Warn,
+            3 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+impl LogCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogCategory::System => "system",
+            LogCategory::Midi => "midi",
+            LogCategory::Voice => "voice",
+            LogCategory::Param => "param",
+        }
+    }
+}
+impl LogLine {
+    const fn new(level: LogLevel, category: LogCategory) -> Self {
+        Self {level, category, text: [0; LOG_LINE_SIZE], len: 0}
+    }
+    pub fn as_str(&self) -> &str {
+        //  always cut at a char boundary by write_str()
+        std::str::from_utf8(&self.text[..self.len]).unwrap_or("")
+    }
+}
+impl fmt::Write for LogLine {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(LOG_LINE_SIZE - self.len);
+        while !s.is_char_boundary(len) {len -= 1;}
+        self.text[self.len..self.len+len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: Slot = Slot {
+    seq: AtomicUsize::new(0),
+    line: UnsafeCell::new(LogLine::new(LogLevel::Off, LogCategory::System)),
+};
+static LOG_QUEUE: LogQueue = LogQueue {
+    slots: [EMPTY_SLOT; LOG_QUEUE_SIZE],
+    enqueue_pos: AtomicUsize::new(0),
+    dequeue_pos: AtomicUsize::new(0),
+    dropped: AtomicUsize::new(0),
+};
+impl LogQueue {
+    fn seq(&self, pos: usize) -> (&Slot, usize) {
+        let idx = pos & (LOG_QUEUE_SIZE-1);
+        let slot = &self.slots[idx];
+        (slot, slot.seq.load(Ordering::Acquire).wrapping_add(idx))
+    }
+    fn set_seq(slot: &Slot, pos: usize, seq: usize) {
+        slot.seq.store(seq.wrapping_sub(pos & (LOG_QUEUE_SIZE-1)), Ordering::Release);
+    }
+    fn push(&self, line: &LogLine) -> bool {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let (slot, seq) = self.seq(pos);
+            let dif = seq as isize - pos as isize;
+            if dif == 0 {
+                match self.enqueue_pos.compare_exchange_weak(pos, pos+1, Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        unsafe {*slot.line.get() = *line;}
+                        Self::set_seq(slot, pos, pos+1);
+                        return true;
+                    }
+                    Err(crnt) => pos = crnt,
+                }
+            } else if dif < 0 {
+                return false;   //  full
+            } else {
+                pos = self.enqueue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+    fn pop(&self) -> Option<LogLine> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let (slot, seq) = self.seq(pos);
+            let dif = seq as isize - (pos+1) as isize;
+            if dif == 0 {
+                match self.dequeue_pos.compare_exchange_weak(pos, pos+1, Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        let line = unsafe {*slot.line.get()};
+                        Self::set_seq(slot, pos, pos+LOG_QUEUE_SIZE);
+                        return Some(line);
+                    }
+                    Err(crnt) => pos = crnt,
+                }
+            } else if dif < 0 {
+                return None;    //  empty
+            } else {
+                pos = self.dequeue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+}
+pub fn set_level(level: LogLevel) {LOG_LEVEL.store(level as u8, Ordering::Relaxed)}
+pub fn level() -> LogLevel {LogLevel::from_u8(LOG_LEVEL.load(Ordering::Relaxed))}
+pub fn set_category_mask(mask: u8) {LOG_CATEGORY.store(mask & LOG_CATEGORY_ALL, Ordering::Relaxed)}
+pub fn enabled(level: LogLevel, category: LogCategory) -> bool {
+    level != LogLevel::Off &&
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed) &&
+    (category as u8 & LOG_CATEGORY.load(Ordering::Relaxed)) != 0
+}
+pub fn post(level: LogLevel, category: LogCategory, args: fmt::Arguments) {
+    //  real-time safe: no allocation, no lock
+    if !enabled(level, category) {return}
+    let mut line = LogLine::new(level, category);
+    let _ = fmt::write(&mut line, args);
+    if !LOG_QUEUE.push(&line) {
+        LOG_QUEUE.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+pub fn pop() -> Option<LogLine> {LOG_QUEUE.pop()}
+pub fn take_dropped() -> usize {LOG_QUEUE.dropped.swap(0, Ordering::Relaxed)}
+pub fn set_sink(sink: Option<LogSink>) {
+    if let Ok(mut crnt) = LOG_SINK.lock() {*crnt = sink;}
+}
+pub fn flush() -> usize {
+    //  not real-time safe: the sink may allocate or block
+    //  without any sink, messages stay in the queue
+    let mut sink = match LOG_SINK.lock() {
+        Ok(sink) => sink,
+        Err(_) => return 0,
+    };
+    let sink = match sink.as_mut() {
+        Some(sink) => sink,
+        None => return 0,
+    };
+    let mut count = 0;
+    let dropped = take_dropped();
+    if dropped > 0 {
+        sink(LogLevel::Warn, LogCategory::System, &format!("({} log messages dropped)", dropped));
+        count += 1;
+    }
+    while let Some(line) = pop() {
+        sink(line.level, line.category, line.as_str());
+        count += 1;
+    }
+    count
+}
@@ -0,0 +1,229 @@
+//
+//  msgf_midi.rs
+//	Musical Sound Generator Framework
+//      MIDI 1.0 Byte Stream Parser
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Bytes from serial/USB-MIDI may be split at any point.
+//  The parser keeps its state between calls of parse().
+//      - Running Status
+//      - Real Time Messages (0xf8-0xff) interleaved in other messages
+//      - System Exclusive (0xf0 ... 0xf7)
+//
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+pub const MAX_SYSEX_SIZE: usize = 256;  //  longer SysEx is discarded
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+pub enum MidiMessage<'a> {
+    Channel(u8, u8, u8),    //  status, data1, data2 (0 for 2 byte messages)
+    SysEx(&'a [u8]),        //  0xf0 ... 0xf7 inclusive
+    RealTime(u8),
+}
+pub struct MidiParser {
+    running_status: u8,     //  0: none
+    data: [u8; 2],
+    data_cnt: usize,
+    data_len: usize,        //  data bytes of the current message
+    in_sysex: bool,
+    sysex_overflow: bool,
+    sysex: Vec<u8>,
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Default for MidiParser {
+    fn default() -> Self {Self::new()}
+}
+impl MidiParser {
+    pub fn new() -> Self {
+        Self {
+            running_status: 0,
+            data: [0; 2],
+            data_cnt: 0,
+            data_len: 0,
+            in_sysex: false,
+            sysex_overflow: false,
+            sysex: Vec::with_capacity(MAX_SYSEX_SIZE),
+        }
+    }
+    fn reset(&mut self) {
+        self.running_status = 0;
+        self.data_cnt = 0;
+        self.data_len = 0;
+        self.in_sysex = false;
+        self.sysex_overflow = false;
+        self.sysex.clear();
+    }
+    fn data_length(status: u8) -> usize {
+        match status {
+            0x80..=0xbf | 0xe0..=0xef => 2,
+            0xc0..=0xdf => 1,
+            0xf1 | 0xf3 => 1,
+            0xf2 => 2,
+            _ => 0,
+        }
+    }
+    pub fn parse<F>(&mut self, bytes: &[u8], mut out: F)
+      where F: FnMut(MidiMessage) {
+        for &byte in bytes {
+            self.parse_byte(byte, &mut out);
+        }
+    }
+    fn parse_byte<F>(&mut self, byte: u8, out: &mut F)
+      where F: FnMut(MidiMessage) {
+        match byte {
+            0xff => {
+                //  System Reset
+                self.reset();
+                out(MidiMessage::RealTime(byte));
+            }
+            0xf8..=0xfe => {
+                //  Real Time: doesn't affect any other state
+                out(MidiMessage::RealTime(byte));
+            }
+            0xf7 => {
+                if self.in_sysex {
+                    self.in_sysex = false;
+                    if !self.sysex_overflow {
+                        self.sysex.push(byte);
+                        out(MidiMessage::SysEx(&self.sysex));
+                    }
+                    self.sysex.clear();
+                }
+            }
+            0xf0 => {
+                self.running_status = 0;
+                self.data_len = 0;
+                self.in_sysex = true;
+                self.sysex_overflow = false;
+                self.sysex.clear();
+                self.sysex.push(byte);
+            }
+            0x80..=0xf6 => {
+                //  a status byte also terminates an unfinished SysEx (discarded)
+                self.in_sysex = false;
+                self.sysex.clear();
+                self.data_cnt = 0;
+                self.data_len = Self::data_length(byte);
+                if byte < 0xf0 {
+                    self.running_status = byte;
+                } else {
+                    //  System Common: cancels running status,
+                    //  and its data bytes are consumed and ignored
+                    self.running_status = 0;
+                }
+                self.data = [0; 2];
+            }
+            _ => {
+                if self.in_sysex {
+                    if self.sysex.len() < MAX_SYSEX_SIZE - 1 {
+                        self.sysex.push(byte);
+                    } else {
+                        self.sysex_overflow = true;
+                    }
+                    return;
+                }
+                if self.data_len == 0 {
+                    //  data byte without status
+                    if self.running_status == 0 {return;}
+                    self.data_len = Self::data_length(self.running_status);
+                    self.data_cnt = 0;
+                }
+                self.data[self.data_cnt] = byte;
+                self.data_cnt += 1;
+                if self.data_cnt < self.data_len {return;}
+                if self.running_status != 0 {
+                    out(MidiMessage::Channel(self.running_status, self.data[0],
+                        if self.data_len == 2 {self.data[1]} else {0}));
+                }
+                //  next data byte starts a new message by running status
+                self.data_cnt = 0;
+                self.data_len = 0;
+            }
+        }
+    }
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq, Debug)]
+    enum Msg {
+        Channel(u8, u8, u8),
+        SysEx(Vec<u8>),
+        RealTime(u8),
+    }
+    fn parse(prs: &mut MidiParser, bytes: &[u8]) -> Vec<Msg> {
+        let mut msgs = Vec::new();
+        prs.parse(bytes, |msg| msgs.push(match msg {
+            MidiMessage::Channel(st, dt2, dt3) => Msg::Channel(st, dt2, dt3),
+            MidiMessage::SysEx(dt) => Msg::SysEx(dt.to_vec()),
+            MidiMessage::RealTime(st) => Msg::RealTime(st),
+        }));
+        msgs
+    }
+
+    #[test]
+    fn split_messages() {
+        let mut prs = MidiParser::new();
+        assert_eq!(parse(&mut prs, &[0x90]), vec![]);
+        assert_eq!(parse(&mut prs, &[60]), vec![]);
+        assert_eq!(parse(&mut prs, &[100, 0xc1]), vec![Msg::Channel(0x90, 60, 100)]);
+        assert_eq!(parse(&mut prs, &[5]), vec![Msg::Channel(0xc1, 5, 0)]);
+        assert_eq!(parse(&mut prs, &[0xf0, 0x7e]), vec![]);
+        assert_eq!(parse(&mut prs, &[0x7f, 0xf7]), vec![Msg::SysEx(vec![0xf0, 0x7e, 0x7f, 0xf7])]);
+    }
+    #[test]
+    fn running_status() {
+        let mut prs = MidiParser::new();
+        assert_eq!(parse(&mut prs, &[0x90, 60, 100, 64, 100, 67]), vec![
+            Msg::Channel(0x90, 60, 100), Msg::Channel(0x90, 64, 100)]);
+        assert_eq!(parse(&mut prs, &[0]), vec![Msg::Channel(0x90, 67, 0)]);
+        assert_eq!(parse(&mut prs, &[0xd0, 10, 20]), vec![
+            Msg::Channel(0xd0, 10, 0), Msg::Channel(0xd0, 20, 0)]);
+        //  System Common and SysEx cancel running status
+        assert_eq!(parse(&mut prs, &[0xf3, 1, 30]), vec![]);
+        assert_eq!(parse(&mut prs, &[0xb0, 7, 100, 0xf0, 0xf7, 7, 90]), vec![
+            Msg::Channel(0xb0, 7, 100), Msg::SysEx(vec![0xf0, 0xf7])]);
+        //  data bytes without status are ignored
+        assert_eq!(parse(&mut MidiParser::new(), &[60, 100]), vec![]);
+    }
+    #[test]
+    fn real_time_inside_other_messages() {
+        let mut prs = MidiParser::new();
+        assert_eq!(parse(&mut prs, &[0x90, 0xf8, 60, 0xfe, 100]), vec![
+            Msg::RealTime(0xf8), Msg::RealTime(0xfe), Msg::Channel(0x90, 60, 100)]);
+        assert_eq!(parse(&mut prs, &[0xf0, 0x43, 0xf8, 0x10, 0xfa, 0xf7]), vec![
+            Msg::RealTime(0xf8), Msg::RealTime(0xfa), Msg::SysEx(vec![0xf0, 0x43, 0x10, 0xf7])]);
+        //  System Reset clears running status
+        assert_eq!(parse(&mut prs, &[0x90, 60, 100, 0xff, 64, 100]), vec![
+            Msg::Channel(0x90, 60, 100), Msg::RealTime(0xff)]);
+    }
+    #[test]
+    fn sysex_overflow() {
+        let mut prs = MidiParser::new();
+        let mut bytes = vec![0xf0];
+        bytes.extend(std::iter::repeat_n(0x11, MAX_SYSEX_SIZE - 2));
+        bytes.push(0xf7);
+        assert_eq!(parse(&mut prs, &bytes), vec![Msg::SysEx(bytes.clone())]);
+        //  one byte longer than MAX_SYSEX_SIZE is discarded
+        let mut bytes = vec![0xf0];
+        bytes.extend(std::iter::repeat_n(0x11, MAX_SYSEX_SIZE - 1));
+        bytes.push(0xf7);
+        assert_eq!(parse(&mut prs, &bytes), vec![]);
+        //  and the parser goes on
+        assert_eq!(parse(&mut prs, &[0x80, 60, 0]), vec![Msg::Channel(0x80, 60, 0)]);
+        //  a status byte terminates an unfinished SysEx
+        assert_eq!(parse(&mut prs, &[0xf0, 1, 2, 0x90, 60, 100, 0xf7]), vec![Msg::Channel(0x90, 60, 100)]);
+    }
+}
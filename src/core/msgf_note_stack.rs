@@ -0,0 +1,157 @@
+//
+//  msgf_note_stack.rs
+//	Musical Sound Generator Framework
+//      Note Stack Class for monophonic instruments
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Keeps pressed keys in the pressed order, and selects the note
+//  which should sound by the note priority.
+//  When the sounding note is released, the next selected note is
+//  returned to go back to the held note.
+//
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+pub const MAX_NOTE_STACK: usize = 16;   //  the oldest key is dropped when full
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum NotePriority {
+    Last,
+    Low,
+    High,
+}
+impl NotePriority {
+    pub fn from_u8(value: u8) -> NotePriority {
+        match value {
+            1 => NotePriority::Low,
+            2 => NotePriority::High,
+            _ => NotePriority::Last,
+        }
+    }
+}
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+#[derive(Copy, Clone)]
+pub struct StackedNote {
+    pub note: u8,
+    pub vel: f32,   //  0.0-1.0
+}
+pub struct NoteStack {
+    notes: [StackedNote; MAX_NOTE_STACK],   //  pressed order
+    count: usize,
+    priority: NotePriority,
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl NoteStack {
+    pub fn new(priority: NotePriority) -> Self {
+        Self {
+            notes: [StackedNote {note: 0, vel: 0.0}; MAX_NOTE_STACK],
+            count: 0,
+            priority,
+        }
+    }
+    pub fn set_priority(&mut self, priority: NotePriority) {self.priority = priority;}
+    pub fn push(&mut self, note: u8, vel: f32) {
+        self.remove(note);  //  the same key pressed again goes to the top
+        if self.count >= MAX_NOTE_STACK {
+            self.notes.copy_within(1.., 0);
+            self.count -= 1;
+        }
+        self.notes[self.count] = StackedNote {note, vel};
+        self.count += 1;
+    }
+    pub fn remove(&mut self, note: u8) {
+        if let Some(idx) = self.notes[..self.count].iter().position(|nt| nt.note == note) {
+            self.notes.copy_within(idx+1..self.count, idx);
+            self.count -= 1;
+        }
+    }
+    pub fn clear(&mut self) {self.count = 0;}
+    pub fn active(&self) -> Option<StackedNote> {
+        //  the note which should sound now
+        let held = self.notes[..self.count].iter();
+        match self.priority {
+            NotePriority::Last => held.last(),
+            NotePriority::Low => held.min_by_key(|nt| nt.note),
+            NotePriority::High => held.max_by_key(|nt| nt.note),
+        }.copied()
+    }
+    pub fn is_active(&self, note: u8) -> bool {
+        self.active().is_some_and(|nt| nt.note == note)
+    }
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(stack: &NoteStack) -> Vec<u8> {
+        stack.notes[..stack.count].iter().map(|nt| nt.note).collect()
+    }
+
+    #[test]
+    fn oldest_is_dropped_when_full() {
+        let mut stack = NoteStack::new(NotePriority::Last);
+        for note in 0..MAX_NOTE_STACK as u8 + 2 {
+            stack.push(note, 1.0);
+        }
+        assert_eq!(notes(&stack), (2..MAX_NOTE_STACK as u8 + 2).collect::<Vec<u8>>());
+        assert_eq!(stack.active().map(|nt| nt.note), Some(MAX_NOTE_STACK as u8 + 1));
+    }
+    #[test]
+    fn repeated_key_goes_to_the_top() {
+        let mut stack = NoteStack::new(NotePriority::Last);
+        stack.push(60, 1.0);
+        stack.push(64, 1.0);
+        stack.push(67, 1.0);
+        stack.push(60, 0.5);
+        assert_eq!(notes(&stack), [64, 67, 60]);
+        let active = stack.active().unwrap();
+        assert_eq!((active.note, active.vel), (60, 0.5));
+        //  a full stack is not shifted by a repeated key
+        let mut stack = NoteStack::new(NotePriority::Last);
+        for note in 0..MAX_NOTE_STACK as u8 {
+            stack.push(note, 1.0);
+        }
+        stack.push(0, 1.0);
+        assert_eq!(notes(&stack)[0], 1);
+        assert_eq!(notes(&stack).len(), MAX_NOTE_STACK);
+    }
+    #[test]
+    fn released_note_goes_back_to_the_held_one() {
+        let mut stack = NoteStack::new(NotePriority::Last);
+        stack.push(60, 1.0);
+        stack.push(64, 1.0);
+        assert!(stack.is_active(64));
+        stack.remove(64);
+        assert!(stack.is_active(60));
+        stack.remove(72);   //  not in the stack
+        assert!(stack.is_active(60));
+        stack.remove(60);
+        assert!(stack.active().is_none());
+    }
+    #[test]
+    fn priority() {
+        let mut stack = NoteStack::new(NotePriority::Low);
+        for note in [64, 60, 67] {
+            stack.push(note, 1.0);
+        }
+        assert!(stack.is_active(60));
+        stack.set_priority(NotePriority::High);
+        assert!(stack.is_active(67));
+        stack.set_priority(NotePriority::Last);
+        assert!(stack.is_active(67));
+        stack.remove(67);
+        assert!(stack.is_active(60));
+        stack.clear();
+        assert!(stack.active().is_none());
+    }
+}
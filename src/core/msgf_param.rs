@@ -0,0 +1,198 @@
+//
+//  msgf_param.rs
+//	Musical Sound Generator Framework
+//      Parameter Tree
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Every synth parameter is addressed by part -> instrument -> engine -> parameter.
+//  Each engine has a table of ParamDef, and each app lists the tables of its engines.
+//  The serial number in the list of the app is the parameter ID.
+//  "engine.name" is the same as the key of the patch text.
+//  A new value from the host goes to the target, and the current value follows it:
+//      None    : at once (switches, times)
+//      Control : ramps in SMOOTH_TIME, updated every control frame
+//      Audio   : ramps in SMOOTH_TIME, updated every sample by the engine
+//
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+pub const SMOOTH_TIME: f32 = 0.02;  //  [sec]
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(u8)]
+pub enum ParamUnit {
+    None = 0,
+    Semitone = 1,       //  integer
+    Cent = 2,
+    Hz = 3,
+    Sec = 4,
+    Rate = 5,           //  0.0-1.0 per control frame
+    Level = 6,          //  linear
+    Octave = 7,         //  1.0 means +-1oct.
+    ControlFrame = 8,   //  integer, AUDIO_FRAME_PER_CONTROL samples
+    Index = 9,          //  integer, item of a switch
+}
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(u8)]
+pub enum Smoothing {
+    None = 0,
+    Control = 1,
+    Audio = 2,
+}
+#[derive(Copy, Clone)]
+pub struct ParamDef {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub unit: ParamUnit,
+    pub smoothing: Smoothing,
+}
+pub struct ParamGroup {
+    pub engine: &'static str,   //  prefix of the patch text
+    pub params: &'static [ParamDef],
+}
+pub trait ParamPrm {
+    //  idx: index in the table of the engine
+    fn param(&self, idx: usize) -> f32;
+    fn set_param(&mut self, idx: usize, value: f32);
+}
+#[derive(Copy, Clone, Debug)]
+pub struct ParamInfo {
+    pub id: usize,
+    pub inst: &'static str,     //  app name, same as the section of the patch text
+    pub engine: &'static str,
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,           //  value of the tone
+    pub unit: ParamUnit,
+    pub smoothing: Smoothing,
+}
+#[derive(Copy, Clone, Default)]
+pub struct Smoother {
+    crnt: f32,
+    target: f32,
+    step: f32,      //  per sample
+    ramp: f32,      //  SMOOTH_TIME [sample]
+}
+#[derive(Default)]
+pub struct ParamSet {
+    inst: &'static str,
+    groups: &'static [ParamGroup],
+    value: Vec<Smoother>,
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl ParamUnit {
+    pub fn is_integer(&self) -> bool {
+        matches!(self, ParamUnit::Semitone | ParamUnit::ControlFrame | ParamUnit::Index)
+    }
+}
+impl ParamDef {
+    pub fn quantize(&self, value: f32) -> f32 {
+        let value = if value.is_nan() {self.min} else {value.clamp(self.min, self.max)};
+        if self.unit.is_integer() {value.round()} else {value}
+    }
+}
+impl ParamInfo {
+    pub fn path(&self) -> String {format!("{}.{}.{}", self.inst, self.engine, self.name)}
+}
+impl Smoother {
+    pub fn new(value: f32, fs: f32) -> Self {
+        Self {crnt: value, target: value, step: 0.0, ramp: SMOOTH_TIME*fs}
+    }
+    pub fn set(&mut self, value: f32) {
+        self.crnt = value;
+        self.target = value;
+        self.step = 0.0;
+    }
+    pub fn set_target(&mut self, target: f32) {
+        let frames = self.ramp;
+        self.target = target;
+        self.step = (target - self.crnt)/frames;
+        if frames < 1.0 || self.step == 0.0 {self.set(target);}
+    }
+    pub fn target(&self) -> f32 {self.target}
+    pub fn is_ramping(&self) -> bool {self.crnt != self.target}
+    pub fn advance(&mut self, frames: usize) -> f32 {
+        if self.is_ramping() {
+            let next = self.crnt + self.step*(frames as f32);
+            //  finished when it reaches or passes the target
+            if (self.step > 0.0 && next >= self.target) || (self.step < 0.0 && next <= self.target) {
+                self.crnt = self.target;
+            } else {
+                self.crnt = next;
+            }
+        }
+        self.crnt
+    }
+    pub fn next(&mut self) -> f32 {self.advance(1)}
+}
+pub fn locate(groups: &[ParamGroup], mut id: usize) -> Option<(usize, usize)> {
+    //  id -> index of the group, index in the group
+    for (num, grp) in groups.iter().enumerate() {
+        if id < grp.params.len() {return Some((num, id));}
+        id -= grp.params.len();
+    }
+    None
+}
+impl ParamSet {
+    pub fn new(inst: &'static str, groups: &'static [ParamGroup], fs: f32,
+      value: impl Fn(usize) -> f32) -> Self {
+        let count = groups.iter().map(|grp| grp.params.len()).sum();
+        Self {inst, groups, value: (0..count).map(|id| Smoother::new(value(id), fs)).collect()}
+    }
+    pub fn count(&self) -> usize {self.value.len()}
+    fn def(&self, id: usize) -> Option<(&'static str, &'static ParamDef)> {
+        let groups = self.groups;
+        locate(groups, id).map(|(grp, idx)| (groups[grp].engine, &groups[grp].params[idx]))
+    }
+    pub fn find(&self, path: &str) -> Option<usize> {
+        //  path: "engine.name" or "inst.engine.name"
+        let path = path.strip_prefix(self.inst).and_then(|p| p.strip_prefix('.')).unwrap_or(path);
+        (0..self.count()).find(|id| self.def(*id)
+            .is_some_and(|(engine, def)| path.split_once('.') == Some((engine, def.name))))
+    }
+    pub fn smoothing(&self, id: usize) -> Smoothing {
+        self.def(id).map_or(Smoothing::None, |(_, def)| def.smoothing)
+    }
+    pub fn info(&self, id: usize, default: f32) -> Option<ParamInfo> {
+        self.def(id).map(|(engine, def)| ParamInfo {
+            id, inst: self.inst, engine, name: def.name,
+            min: def.min, max: def.max, default, unit: def.unit, smoothing: def.smoothing,
+        })
+    }
+    pub fn get(&self, id: usize) -> Option<f32> {
+        //  the value set last (the target of the ramp)
+        self.value.get(id).map(|vl| vl.target())
+    }
+    pub fn set(&mut self, id: usize, value: f32) -> Option<f32> {
+        //  returns the clamped value
+        let (_, def) = self.def(id)?;
+        let value = def.quantize(value);
+        if def.smoothing == Smoothing::Control {
+            self.value[id].set_target(value);
+        } else {
+            self.value[id].set(value);
+        }
+        Some(value)
+    }
+    pub fn reset(&mut self, value: impl Fn(usize) -> f32) {
+        //  without smoothing, e.g. for a new tone
+        self.value.iter_mut().enumerate().for_each(|(id, vl)| vl.set(value(id)));
+    }
+    pub fn is_ramping(&self) -> bool {self.value.iter().any(|vl| vl.is_ramping())}
+    pub fn advance(&mut self, frames: usize, mut apply: impl FnMut(usize, f32)) {
+        //  Control: the next values of ramping parameters
+        for (id, vl) in self.value.iter_mut().enumerate().filter(|(_, vl)| vl.is_ramping()) {
+            apply(id, vl.advance(frames));
+        }
+    }
+}
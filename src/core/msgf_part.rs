@@ -11,10 +11,43 @@
 use crate::*;
 use crate::core::*;
 use crate::core::msgf_disp::MsgfDisplay;
+use crate::core::msgf_log::{LogLevel, LogCategory};
+
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+const NO_NOTE: u8 = 0xff;
+const MAX_PB_SENS: u16 = 2400;          //  RPN#0 [cent]
+const PER_NOTE_PB_SENS: f32 = 4800.0;   //  MIDI 2.0 Per-Note Pitch Bend [cent]
+//  Program Change: the old instrument keeps sounding its release until silent
+const MAX_FADING_INST: usize = 2;       //  the oldest one is stopped when over
+const FADING_TIME_LIMIT: f32 = 3.0;     //  [sec] faded out in a frame after this
+const SILENT_LEVEL: f32 = 0.0001;       //  -80dB
+const SILENT_TIME: f32 = 0.05;          //  [sec] under SILENT_LEVEL to be dropped
 
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
+#[derive(Copy, Clone)]
+pub struct PartZone {
+    //  Set by the host, kept over GM System On
+    rcv_ch: u8,     //  0x00-0x0f, RCV_CH_OMNI, RCV_CH_OFF
+    key_lo: u8,     //  note on is received in key_lo..=key_hi
+    key_hi: u8,
+    vel_lo: u8,     //  and vel_lo..=vel_hi
+    vel_hi: u8,
+    key_fade: u8,   //  crossfade width inside the key range edges [semitone]
+    vel_fade: u8,   //  crossfade width inside the velocity range edges
+    transpose: i8,  //  [semitone] applied after the range check
+}
+struct InstSlot {
+    //  an instrument kept for Program Change, reused by change_inst()
+    app: usize,             //  index of the app in the registry
+    inst: Box<dyn msgf_inst::Inst>,
+    fading: Option<usize>,  //  order of the program change while in release
+    frames: usize,          //  since the program change
+    silent_frames: usize,
+}
 pub struct Part {
     //	Part Latest Parameter Value
     cc0_msb: u8,
@@ -23,27 +56,43 @@ pub struct Part {
     cc7_volume: u8,
     cc10_pan: u8,
     cc11_expression: u8,
-    cc12_note_shift: u8,	//	out of MIDI
-    cc13_tune: u8,			//	out of MIDI
     cc32_lsb: u8,
     cc64_sustain: u8,
     cc65_portamento: u8,
     cc66_sostenuto: u8,
     cc91_revsend: u8,
-    _cc126_mono: u8,
+    mono: bool,             //  CC#126/127
     program_number: u8,
-    pitch_bend_value: i16,
+    pitch_bend_value: f32,  //  -8192.0..8191.0
+    rpn: msgf_rpn::Rpn,
+    pb_sens: Option<u16>,   //  RPN#0 [cent], None: default of the instrument
+    fine_tune: u16,         //  RPN#1 14bit
+    coarse_tune: u8,        //  RPN#2 MSB
     cc16_31_change_vprm: [u8; 16],
+    mute: bool,
+    zone: PartZone,
+    polyphony: usize,
+    steal: msgf_voice::StealPolicy,
+    note_priority: msgf_note_stack::NotePriority,
+    legato: bool,
+    sounding_note: [u8; 128],   //  key -> transposed note sent to inst
 
     //	Composite Object
     inst: Box<dyn msgf_inst::Inst>,
+    inst_app: usize,            //  index of the app of inst in the registry
+    slots: Vec<InstSlot>,       //  MAX_FADING_INST instruments of each app
+    fading_cnt: usize,          //  number of the program changes
+    fade_l: msgf_afrm::AudioFrame,
+    fade_r: msgf_afrm::AudioFrame,
+    cfg: msgf_if::AudioConfig,
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl MsgfDisplay for Part {}
 impl Part {
-    pub fn new() -> Self {
+    pub fn new(rcv_ch: u8, bank: &app::PatchBank, cfg: &msgf_if::AudioConfig) -> Self {
+        let prg = bank.select_program(0, 0);
         Self {
             cc0_msb: 0,
             cc1_modulation_wheel: 0,
@@ -51,28 +100,84 @@ impl Part {
             cc7_volume: 100,
             cc10_pan: 64,
             cc11_expression: 127,
-            cc12_note_shift: 64,
-            cc13_tune: 64,
             cc32_lsb: 0,
             cc64_sustain: 0,
             cc65_portamento: 0,
             cc66_sostenuto: 0,
             cc91_revsend: 127,
-            _cc126_mono: 1,
+            mono: false,
             program_number: 0,
-            pitch_bend_value: 0,
+            pitch_bend_value: 0.0,
+            rpn: msgf_rpn::Rpn::new(),
+            pb_sens: None,
+            fine_tune: msgf_rpn::DATA_CENTER,
+            coarse_tune: 64,
             cc16_31_change_vprm: [0; 16],
-            inst: app::get_inst(0,100,64,127), //pgn,vol,pan,exp,
+            mute: false,
+            zone: PartZone {rcv_ch, key_lo: 0, key_hi: 127, vel_lo: 1, vel_hi: 127,
+                            key_fade: 0, vel_fade: 0, transpose: 0},
+            sounding_note: [NO_NOTE; 128],
+            polyphony: msgf_voice::MAX_POLYPHONY,
+            steal: msgf_voice::StealPolicy::ReleaseFirst,
+            note_priority: msgf_note_stack::NotePriority::Last,
+            legato: true,
+            inst: prg.create_inst(bank,100,64,127,cfg), //vol,pan,exp
+            inst_app: prg.app,
+            slots: Self::new_slots(bank, cfg),
+            fading_cnt: 0,
+            fade_l: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
+            fade_r: msgf_afrm::AudioFrame::new(0,cfg.max_block_size),
+            cfg: *cfg,
         }
     }
     pub fn note_off(&mut self, dt2: u8, dt3: u8) {
-        self.inst.note_off(dt2, dt3)
+        //  only notes this part accepted are released
+        let note = std::mem::replace(&mut self.sounding_note[(dt2 & 0x7f) as usize], NO_NOTE);
+        if note != NO_NOTE {
+            self.inst.note_off(note, dt3)
+        }
     }
     pub fn note_on(&mut self, dt2: u8, dt3: u8) {
-        self.inst.note_on(dt2, dt3)
+        if let Some((note, gain)) = self.zone_note(dt2, dt3) {
+            let vel = ((dt3 as f32)*gain).round().max(1.0) as u8;
+            self.inst.note_on(note, vel)
+        }
+    }
+    fn zone_note(&mut self, dt2: u8, dt3: u8) -> Option<(u8, f32)> {
+        //  transposed note and the gain of velocity, None out of the zone
+        let zn = &self.zone;
+        if dt2 < zn.key_lo || zn.key_hi < dt2 || dt3 < zn.vel_lo || zn.vel_hi < dt3 {
+            return None;
+        }
+        //  Crossfade: velocity is lowered near the edges of the zone
+        let gain = Self::fade_gain(dt2, (zn.key_lo, zn.key_hi), 0, zn.key_fade)
+                 * Self::fade_gain(dt3, (zn.vel_lo, zn.vel_hi), 1, zn.vel_fade);
+        let note = (dt2 as i32 + zn.transpose as i32).clamp(0, 127) as u8;
+        self.sounding_note[dt2 as usize] = note;
+        Some((note, gain))
+    }
+    fn fade_gain(value: u8, (low, high): (u8, u8), bottom: u8, width: u8) -> f32 {
+        //  1.0 in the zone, falls linearly toward each edge in width
+        //  The edges at the end of the whole range (bottom/127) don't fade
+        let mut gain: f32 = 1.0;
+        if width == 0 {return gain;}
+        let w = (width as f32) + 1.0;
+        if low > bottom && value - low < width {
+            gain *= ((value - low) as f32 + 1.0)/w;
+        }
+        if high < 127 && high - value < width {
+            gain *= ((high - value) as f32 + 1.0)/w;
+        }
+        gain
     }
     pub fn per_note_after(&mut self, dt2: u8, dt3: u8) {
-        self.inst.per_note_after(dt2, dt3)
+        let note = self.sounding_note[(dt2 & 0x7f) as usize];
+        if note != NO_NOTE {
+            self.inst.per_note_after(note, dt3)
+        }
+    }
+    pub fn channel_after(&mut self, value: u8) {
+        self.inst.channel_after(value)
     }
     pub fn control_change(&mut self, controller: u8, value: u8) {
         match controller {
@@ -81,7 +186,10 @@ impl Part {
                 self.cc1_modulation_wheel = value;
                 self.inst.modulation(value);
             }
-            5 => self.cc5_portamento_time = value,
+            5 => {
+                self.cc5_portamento_time = value;
+                self.inst.portamento_time(value);
+            }
             7 => {
                 self.cc7_volume = value;
                 self.inst.volume(value);
@@ -94,30 +202,49 @@ impl Part {
                 self.cc11_expression = value;
                 self.inst.expression(value);
             }
+            6 | 38 | 96 | 97 => {
+                if let Some(entry) = msgf_rpn::DataEntry::from_cc(controller, value) {
+                    self.data_entry(entry);
+                }
+            }
+            98..=101 => self.rpn.select(controller, value),
             12 => {
-                self.cc12_note_shift = value;
-                let pb = self.pitch_bend_value;
-                let tn = self.cc13_tune;
-                self.inst.pitch(pb, value, tn);
+                //  Note Shift: out of MIDI, same as RPN#2
+                self.coarse_tune = value;
+                self.update_pitch();
             }
             13 => {
-                self.cc13_tune = value;
-                let pb = self.pitch_bend_value;
-                let ns = self.cc12_note_shift;
-                self.inst.pitch(pb, ns, value);
+                //  Tune: out of MIDI, same as MSB of RPN#1
+                self.fine_tune = (value as u16) << 7;
+                self.update_pitch();
             }
             32 => self.cc32_lsb = value,
             64 => {
                 self.cc64_sustain = value;
                 self.inst.sustain(value);
             }
-            65 => self.cc65_portamento = value,
-            66 => self.cc66_sostenuto = value,
+            65 => {
+                self.cc65_portamento = value;
+                self.inst.portamento(value >= 64);
+            }
+            84 => self.inst.portamento_ctrl(value),
+            66 => {
+                self.cc66_sostenuto = value;
+                self.inst.sostenuto(value);
+            }
             91 => self.cc91_revsend = value,
-            120 => {
-                if value == 0 {
-                    self.inst.all_sound_off();
-                }
+            120 if value == 0 => {
+                self.inst.all_sound_off();
+                self.slots.iter_mut().filter(|sl| sl.fading.is_some())
+                    .for_each(|sl| sl.inst.all_sound_off());
+            }
+            126 => {
+                self.mono = true;
+                self.inst.mono(true);
+            }
+            127 if value == 0 => {
+                self.mono = false;
+                self.inst.mono(false);
             }
             16..=31 => {
                 let vprm_num: u8 = controller-16;
@@ -126,26 +253,298 @@ impl Part {
             }
             _ => {}
         };
-        //self.print_prm("Control Change: ", controller);
+        //self.print_prm(LogCategory::Midi, "Control Change: ", controller);
+    }
+    fn new_slots(bank: &app::PatchBank, cfg: &msgf_if::AudioConfig) -> Vec<InstSlot> {
+        //  Instruments are built here, not by Program Change in process()
+        bank.registry().apps().iter().enumerate().flat_map(|(app, factory)| {
+            (0..MAX_FADING_INST).map(move |_| InstSlot {
+                app, inst: factory(bank, 0, 100, 64, 127, cfg), fading: None, frames: 0, silent_frames: 0})
+        }).collect()
     }
-    pub fn program_change(&mut self, dt2: u8) {
+    pub fn program_change(&mut self, bank: &app::PatchBank, dt2: u8) {
         self.program_number = dt2;
         let vol = self.cc7_volume;
         let pan = self.cc10_pan;
         let exp = self.cc11_expression;
-        let pb = self.pitch_bend_value;
-        let ns = self.cc12_note_shift;
-        let tn = self.cc13_tune;
-        self.print_prm("Program Change: ", dt2);
-        self.inst = app::get_inst(self.program_number as usize,vol,pan,exp); //pgn,vol,pan,exp,
-        self.inst.pitch(pb, ns, tn);
+        self.print_prm(LogCategory::Midi, "Program Change: ", dt2);
+        let bank_num = msgf_program::bank_number(self.cc0_msb, self.cc32_lsb);  //  Bank Select is applied here
+        let prg = bank.select_program(bank_num, self.program_number);
+        //  a free slot of the app, or the oldest one in release
+        let idx = self.slots.iter().enumerate()
+            .filter(|(_, sl)| sl.app == prg.app)
+            .min_by_key(|(_, sl)| sl.fading.map_or(0, |order| order + 1))
+            .map(|(idx, _)| idx)
+            .expect("every app has slots");
+        self.inst.release_all();
+        self.fading_cnt += 1;
+        let slot = &mut self.slots[idx];
+        std::mem::swap(&mut self.inst, &mut slot.inst);
+        std::mem::swap(&mut self.inst_app, &mut slot.app);
+        slot.fading = Some(self.fading_cnt);
+        slot.frames = 0;
+        slot.silent_frames = 0;
+        self.inst.change_inst(bank, prg.tone, vol, pan, exp);
+        //  the oldest one is stopped when over
+        if self.slots.iter().filter(|sl| sl.fading.is_some()).count() > MAX_FADING_INST {
+            if let Some(oldest) = self.slots.iter_mut().filter(|sl| sl.fading.is_some())
+                .min_by_key(|sl| sl.fading) {
+                oldest.inst.all_sound_off();
+                oldest.fading = None;
+            }
+        }
+        self.update_pitch();
+        self.inst.portamento(self.cc65_portamento >= 64);
+        self.inst.portamento_time(self.cc5_portamento_time);
+        self.inst.set_polyphony(self.polyphony, self.steal);
+        self.inst.set_mono_config(self.note_priority, self.legato);
+        self.inst.mono(self.mono);
+        self.sounding_note = [NO_NOTE; 128];
+    }
+    pub fn set_mute(&mut self, mute: bool) {self.mute = mute;}
+    pub fn reset(&mut self, bank: &app::PatchBank) {
+        //  GM System On: all controllers and the program go back to default
+        //  mute, zone, polyphony and mono config are kept, nothing is allocated
+        self.cc0_msb = 0;
+        self.cc1_modulation_wheel = 0;
+        self.cc5_portamento_time = 0;
+        self.cc7_volume = 100;
+        self.cc10_pan = 64;
+        self.cc11_expression = 127;
+        self.cc32_lsb = 0;
+        self.cc64_sustain = 0;
+        self.cc65_portamento = 0;
+        self.cc66_sostenuto = 0;
+        self.cc91_revsend = 127;
+        self.mono = false;
+        self.pitch_bend_value = 0.0;
+        self.rpn = msgf_rpn::Rpn::new();
+        self.pb_sens = None;
+        self.fine_tune = msgf_rpn::DATA_CENTER;
+        self.coarse_tune = 64;
+        self.cc16_31_change_vprm = [0; 16];
+        self.inst.all_sound_off();
+        self.program_change(bank, 0);
+        self.slots.iter_mut().filter(|sl| sl.fading.is_some()).for_each(|sl| {
+            sl.inst.all_sound_off();
+            sl.fading = None;
+        });
+    }
+    pub fn receives(&self, ch: u8) -> bool {
+        match self.zone.rcv_ch {
+            msgf_if::RCV_CH_OMNI => true,
+            rcv_ch => rcv_ch == ch,   //  RCV_CH_OFF never matches
+        }
+    }
+    pub fn set_rcv_ch(&mut self, rcv_ch: u8) {
+        self.zone.rcv_ch = if rcv_ch < 0x10 || rcv_ch == msgf_if::RCV_CH_OMNI {rcv_ch} else {msgf_if::RCV_CH_OFF};
+    }
+    pub fn set_key_range(&mut self, low: u8, high: u8) {
+        self.zone.key_lo = low.min(127);
+        self.zone.key_hi = high.min(127);
+    }
+    pub fn set_vel_range(&mut self, low: u8, high: u8) {
+        self.zone.vel_lo = low.clamp(1, 127);
+        self.zone.vel_hi = high.min(127);
+    }
+    pub fn set_zone_fade(&mut self, key_width: u8, vel_width: u8) {
+        self.zone.key_fade = key_width.min(127);
+        self.zone.vel_fade = vel_width.min(127);
+    }
+    pub fn set_transpose(&mut self, transpose: i8) {
+        self.zone.transpose = transpose.clamp(-64, 63);
+    }
+    pub fn set_polyphony(&mut self, max_voice: usize, steal: msgf_voice::StealPolicy) {
+        self.polyphony = max_voice.clamp(1, msgf_voice::MAX_POLYPHONY);
+        self.steal = steal;
+        self.inst.set_polyphony(self.polyphony, steal);
+    }
+    pub fn set_mono_config(&mut self, priority: msgf_note_stack::NotePriority, legato: bool) {
+        self.note_priority = priority;
+        self.legato = legato;
+        self.inst.set_mono_config(priority, legato);
+    }
+    //  Parameter Tree of the current instrument (reset by Program Change)
+    pub fn param_count(&self) -> usize {
+        self.inst.params().map_or(0, |prms| prms.count())
+    }
+    pub fn param_info(&self, id: usize) -> Option<msgf_param::ParamInfo> {
+        self.inst.params()?.info(id, self.inst.param_default(id))
+    }
+    pub fn find_param(&self, path: &str) -> Option<usize> {
+        self.inst.params()?.find(path)
+    }
+    pub fn get_param(&self, id: usize) -> Option<f32> {
+        self.inst.params()?.get(id)
+    }
+    pub fn set_param(&mut self, id: usize, value: f32) -> bool {
+        if id >= self.param_count() {return false;}
+        self.inst.set_param(id, value);
+        true
+    }
+    pub fn param_ramping(&self) -> bool {
+        self.inst.params().is_some_and(|prms| prms.is_ramping())
+    }
+    pub fn reinit(&mut self, bank: &app::PatchBank, cfg: &msgf_if::AudioConfig) {
+        //  Rebuild the instruments after the sampling freq. or the patch bank is changed
+        self.cfg = *cfg;
+        self.slots = Self::new_slots(bank, cfg);
+        self.program_change(bank, self.program_number);
+        self.slots.iter_mut().for_each(|sl| sl.fading = None);
     }
     pub fn pitch_bend(&mut self, bend: i16) {
-        self.pitch_bend_value = bend;
-        let ns = self.cc12_note_shift;
-        let tn = self.cc13_tune;
-        self.print_prm("Pitch Bend: ",bend);
-        self.inst.pitch(bend, ns, tn);
+        self.pitch_bend_value = bend as f32;
+        self.log(LogLevel::Debug, LogCategory::Midi, format_args!("Pitch Bend: {}", bend));
+        self.update_pitch();
+    }
+    //  MIDI 2.0 Channel Voice: data is 32bit (velocity is 16bit)
+    pub fn note_on_hr(&mut self, dt2: u8, vel: u16, attr_type: u8, attr: u16) {
+        //  the zone is checked by the 7bit velocity, the instrument gets all 16bit
+        if let Some((note, gain)) = self.zone_note(dt2, msgf_ump::velocity(vel)) {
+            self.inst.note_on_f(note, (vel as f32)/65535.0*gain);
+        }
+        if attr_type == msgf_ump::ATTR_PITCH_7_9 {
+            //  absolute pitch of the note
+            self.per_note_pitch(dt2, (attr as f32)/512.0 - (dt2 as f32));
+        }
+    }
+    pub fn per_note_after_hr(&mut self, dt2: u8, data: u32) {
+        let note = self.sounding_note[(dt2 & 0x7f) as usize];
+        if note != NO_NOTE {
+            self.inst.per_note_after_f(note, msgf_ump::unipolar(data))
+        }
+    }
+    pub fn per_note_pitch_bend(&mut self, dt2: u8, data: u32) {
+        self.per_note_pitch(dt2, msgf_ump::bipolar(data)*PER_NOTE_PB_SENS/100.0);
+    }
+    pub fn per_note_rcc(&mut self, dt2: u8, index: u8, data: u32) {
+        //  Registered Per-Note Controller: absolute pitch only
+        if index == msgf_ump::RCC_PITCH_7_25 {
+            let pitch = (data as f64)/((1u32 << 25) as f64);
+            self.per_note_pitch(dt2, pitch as f32 - (dt2 as f32));
+        }
+    }
+    pub fn per_note_manage(&mut self, dt2: u8, flags: u8) {
+        //  Detach is not supported: notes are identified by the note number
+        let note = self.sounding_note[(dt2 & 0x7f) as usize];
+        if note != NO_NOTE && flags & msgf_ump::MANAGE_RESET != 0 {
+            self.inst.per_note_reset(note);
+        }
+    }
+    fn per_note_pitch(&mut self, dt2: u8, semitone: f32) {
+        let note = self.sounding_note[(dt2 & 0x7f) as usize];
+        if note != NO_NOTE {
+            self.inst.per_note_pitch(note, semitone*100.0);
+        }
+    }
+    pub fn control_change_hr(&mut self, controller: u8, data: u32) {
+        let value = (data >> 25) as u8;
+        match controller {
+            1 => {
+                self.cc1_modulation_wheel = value;
+                self.inst.modulation_f(msgf_ump::unipolar(data));
+            }
+            16..=31 => {
+                let vprm_num: u8 = controller-16;
+                self.cc16_31_change_vprm[vprm_num as usize] = value;
+                self.inst.set_prm_f(vprm_num, msgf_ump::unipolar(data));
+            }
+            _ => self.control_change(controller, value),
+        }
+    }
+    pub fn channel_after_hr(&mut self, data: u32) {
+        self.inst.channel_after_f(msgf_ump::unipolar(data))
+    }
+    pub fn pitch_bend_hr(&mut self, data: u32) {
+        self.pitch_bend_value = msgf_ump::bipolar(data)*8192.0;
+        self.update_pitch();
+    }
+    pub fn rpn_hr(&mut self, msb: u8, lsb: u8, data: u32) {
+        //  same as CC#101/100 and Data Entry MSB/LSB
+        self.rpn.select(101, msb);
+        self.rpn.select(100, lsb);
+        self.data_entry(msgf_rpn::DataEntry::Msb((data >> 25) as u8));
+        self.data_entry(msgf_rpn::DataEntry::Lsb(((data >> 18) & 0x7f) as u8));
+    }
+    pub fn nrpn_hr(&mut self, msb: u8, lsb: u8, data: u32, relative: bool) {
+        //  32bit data is mapped on the range of the parameter
+        //  relative: data is signed, the full scale is the range
+        self.rpn.select(99, msb);
+        self.rpn.select(98, lsb);
+        let id = ((msb as usize) << 7) | (lsb as usize);
+        let (info, value) = match (self.param_info(id), self.get_param(id)) {
+            (Some(info), Some(value)) => (info, value),
+            _ => return,
+        };
+        let range = info.max - info.min;
+        let value = if relative {
+            value + range*((data as i32 as f64)/(0x8000_0000_u32 as f64)) as f32
+        } else {
+            info.min + range*msgf_ump::unipolar(data)
+        };
+        self.set_param(id, value);
+    }
+    fn update_pitch(&mut self) {
+        //  [cent] Pitch Bend * RPN#0 + RPN#2 + RPN#1
+        let sens = self.pb_sens.unwrap_or_else(|| self.inst.pb_sens());
+        let pit = (self.pitch_bend_value*(sens as f32))/8192.0
+            + ((self.coarse_tune as f32)-64.0)*100.0
+            + ((self.fine_tune as f32)-(msgf_rpn::DATA_CENTER as f32))*100.0/(msgf_rpn::DATA_CENTER as f32);
+        self.inst.pitch(pit);
+    }
+    fn data_entry(&mut self, entry: msgf_rpn::DataEntry) {
+        use msgf_rpn::{DataEntry, ParamNum};
+        match self.rpn.selected() {
+            Some(ParamNum::Rpn(msgf_rpn::RPN_PB_SENS)) => {
+                //  MSB: semitone, LSB: cent, Increment/Decrement: cent
+                let cents = self.pb_sens.unwrap_or_else(|| self.inst.pb_sens());
+                let cents = match entry {
+                    DataEntry::Msb(msb) => (msb as u16)*100,
+                    DataEntry::Lsb(lsb) => cents - cents%100 + (lsb as u16).min(99),
+                    DataEntry::Increment => cents + 1,
+                    DataEntry::Decrement => cents.saturating_sub(1),
+                };
+                self.pb_sens = Some(cents.min(MAX_PB_SENS));
+                self.log(LogLevel::Debug, LogCategory::Midi, format_args!("PB Sensitivity: {}", cents));
+            }
+            Some(ParamNum::Rpn(msgf_rpn::RPN_FINE_TUNE)) => {
+                self.fine_tune = entry.apply(self.fine_tune, 1);
+                self.log(LogLevel::Debug, LogCategory::Midi, format_args!("Fine Tune: {}", self.fine_tune));
+            }
+            Some(ParamNum::Rpn(msgf_rpn::RPN_COARSE_TUNE)) => {
+                //  MSB only
+                if let DataEntry::Lsb(_) = entry {return;}
+                let data = entry.apply((self.coarse_tune as u16) << 7, 0x80);
+                self.coarse_tune = (data >> 7) as u8;
+                self.log(LogLevel::Debug, LogCategory::Midi, format_args!("Coarse Tune: {}", self.coarse_tune));
+            }
+            Some(ParamNum::Nrpn(id)) => {
+                self.nrpn_entry(id as usize, entry);
+                return;
+            }
+            _ => return,
+        }
+        self.update_pitch();
+    }
+    fn nrpn_entry(&mut self, id: usize, entry: msgf_rpn::DataEntry) {
+        //  NRPN number is the parameter ID, 14bit data is mapped on its range
+        let (info, value) = match (self.param_info(id), self.get_param(id)) {
+            (Some(info), Some(value)) => (info, value),
+            _ => return,
+        };
+        let range = info.max - info.min;
+        let max = msgf_rpn::DATA_MAX as f32;
+        let value = match entry {
+            msgf_rpn::DataEntry::Increment | msgf_rpn::DataEntry::Decrement => {
+                let step = if info.unit.is_integer() {1.0} else {range/127.0};
+                if entry == msgf_rpn::DataEntry::Increment {value + step} else {value - step}
+            }
+            _ => {
+                let data = ((value - info.min)*max/range).round() as u16;
+                info.min + (entry.apply(data, 1) as f32)*range/max
+            }
+        };
+        self.set_param(id, value);
     }
     pub fn process(&mut self,
                    abuf_l: &mut msgf_afrm::AudioFrame,
@@ -156,10 +555,87 @@ impl Part {
         abuf_l.clr_abuf();
         abuf_r.clr_abuf();
         self.inst.process(abuf_l, abuf_r, in_number_frames);
+        self.process_fading(abuf_l, abuf_r, in_number_frames);
         abuf_eff_l.clr_abuf();
         abuf_eff_r.clr_abuf();
         let send: f32 = (self.cc91_revsend as f32)/128.0;
         abuf_eff_l.mul_and_mix(abuf_l, send);    //  effect send L
         abuf_eff_r.mul_and_mix(abuf_r, send);    //  effect send R
+        if self.mute {
+            //  keep the instrument running, but output nothing
+            abuf_l.clr_abuf();
+            abuf_r.clr_abuf();
+            abuf_eff_l.clr_abuf();
+            abuf_eff_r.clr_abuf();
+        }
+    }
+    fn process_fading(&mut self,
+                      abuf_l: &mut msgf_afrm::AudioFrame,
+                      abuf_r: &mut msgf_afrm::AudioFrame,
+                      in_number_frames: usize) {
+        let time_limit = (FADING_TIME_LIMIT*self.cfg.sampling_freq) as usize;
+        let silent_time = (SILENT_TIME*self.cfg.sampling_freq) as usize;
+        self.fade_l.set_sample_number(in_number_frames);
+        self.fade_r.set_sample_number(in_number_frames);
+        for fd in self.slots.iter_mut().filter(|sl| sl.fading.is_some()) {
+            self.fade_l.clr_abuf();
+            self.fade_r.clr_abuf();
+            fd.inst.process(&mut self.fade_l, &mut self.fade_r, in_number_frames);
+            fd.frames += in_number_frames;
+            if fd.frames >= time_limit {
+                self.fade_l.fade_out();
+                self.fade_r.fade_out();
+            }
+            let level = self.fade_l.get_peak_level().max(self.fade_r.get_peak_level());
+            if level < SILENT_LEVEL {
+                fd.silent_frames += in_number_frames;
+            } else {
+                fd.silent_frames = 0;
+            }
+            abuf_l.mul_and_mix(&self.fade_l, 1.0);
+            abuf_r.mul_and_mix(&self.fade_r, 1.0);
+            if fd.frames >= time_limit || fd.silent_frames >= silent_time {
+                fd.fading = None;   //  free for the next program change
+            }
+        }
+    }
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_of_note_on_hr(vel: u16) -> f32 {
+        //  VA Super Saw: the cutoff of the VCF follows the velocity
+        let cfg = msgf_if::AudioConfig::default();
+        let bank = app::PatchBank::default();
+        let mut pt = Part::new(0, &bank, &cfg);
+        pt.program_change(&bank, 6);
+        for (path, value) in [("vcf.cutoff", 500.0), ("vcf.vel_sens", 4.0)] {
+            let id = pt.find_param(path).expect(path);
+            pt.set_param(id, value);
+        }
+        pt.note_on_hr(48, vel, 0, 0);
+        let mut abuf = [0; 4].map(|_| msgf_afrm::AudioFrame::new(0, cfg.max_block_size));
+        let [abuf_l, abuf_r, abuf_eff_l, abuf_eff_r] = &mut abuf;
+        let mut level: f32 = 0.0;
+        for _ in 0..16 {
+            for buf in [&mut *abuf_l, &mut *abuf_r, &mut *abuf_eff_l, &mut *abuf_eff_r] {
+                buf.set_sample_number(256);
+            }
+            pt.process(abuf_l, abuf_r, abuf_eff_l, abuf_eff_r, 256);
+            level += (0..256).filter_map(|i| abuf_l.get_from_abuf(i)).map(|v| v*v).sum::<f32>();
+        }
+        level
+    }
+    #[test]
+    fn note_on_hr_keeps_16bit_velocity() {
+        //  the same velocity in 7bit
+        assert_eq!(msgf_ump::velocity(0x8000), msgf_ump::velocity(0x81ff));
+        let (low, high) = (level_of_note_on_hr(0x8000), level_of_note_on_hr(0x81ff));
+        assert!(low > 0.0);
+        assert!((high - low).abs() > low*0.001, "levels: {} {}", low, high);
     }
-}
\ No newline at end of file
+}
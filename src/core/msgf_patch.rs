@@ -0,0 +1,82 @@
+//
+//  msgf_patch.rs
+//	Musical Sound Generator Framework
+//      Patch Text Format
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Human readable text like TOML:
+//      # comment
+//      [va.0]                      <- section: app name and tone number
+//      osc.wv_type = Sine          <- key = value
+//      osc.fine_tune = 0.0
+//      aeg.attack_rate = 0.9
+//
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+pub struct PatchSection {
+    pub name: String,
+    pub line: usize,                    //  for error messages
+    pub items: Vec<(String, String, usize)>,  //  key, value, line
+}
+pub trait PatchPrm {
+    //  key: field name without the prefix of the parameter struct
+    //  errors are reported with the line number and the whole key by set_items()
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String>;
+    fn write_patch(&self, prefix: &str, out: &mut String);
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+pub fn parse(text: &str) -> Result<Vec<PatchSection>, String> {
+    let mut sections: Vec<PatchSection> = Vec::new();
+    for (num, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {continue;}
+        if line.starts_with('[') {
+            if !line.ends_with(']') {
+                return Err(format!("line {}: broken section", num+1));
+            }
+            let name = line[1..line.len()-1].trim().to_string();
+            sections.push(PatchSection {name, line: num+1, items: Vec::new()});
+        } else if let Some((key, value)) = line.split_once('=') {
+            match sections.last_mut() {
+                Some(sec) => sec.items.push((key.trim().to_string(), value.trim().to_string(), num+1)),
+                None => return Err(format!("line {}: parameter outside of a section", num+1)),
+            }
+        } else {
+            return Err(format!("line {}: syntax error", num+1));
+        }
+    }
+    Ok(sections)
+}
+pub fn set_items<T: PatchPrm>(prm: &mut T, sec: &PatchSection) -> Result<(), String> {
+    for (key, value, line) in sec.items.iter() {
+        prm.set_patch(key, value).map_err(|e| format!("line {}: {}: {}", line, key, e))?;
+    }
+    Ok(())
+}
+//---------------------------------------------------------
+//  Helpers for PatchPrm implementations
+pub fn unknown_key() -> String {
+    String::from("unknown parameter")
+}
+pub fn invalid_value(value: &str) -> String {
+    format!("invalid value: {}", value)
+}
+pub fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| invalid_value(value))
+}
+pub fn write_item<T: std::fmt::Debug>(out: &mut String, prefix: &str, key: &str, value: T) {
+    //  Debug format keeps the exact f32 value (round trip),
+    //  and writes enums by their variant names
+    out.push_str(&format!("{}{} = {:?}\n", prefix, key, value));
+}
+pub fn split_prefix<'a>(key: &'a str, prefix: &str) -> Option<&'a str> {
+    //  "osc.fine_tune", "osc" -> Some("fine_tune")
+    key.strip_prefix(prefix)?.strip_prefix('.')
+}
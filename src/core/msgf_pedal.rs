@@ -0,0 +1,207 @@
+//
+//  msgf_pedal.rs
+//	Musical Sound Generator Framework
+//      Sustain / Sostenuto Pedal Class
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Held-note bookkeeping shared by every Inst.
+//  Note offs while a pedal holds the note are deferred, and given back
+//  to the Inst when the pedal is released.
+//
+//  Sustain(CC64)
+//      0-63    : off
+//      64-126  : half pedal, notes are released with longer release time
+//      127     : full, note offs are deferred
+//  Sostenuto(CC66)
+//      64-127  : only the notes pressed at the pedal on are held
+//
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+const PEDAL_ON: u8 = 64;
+const PEDAL_FULL: u8 = 127;
+const MAX_RELEASE_STRETCH: f32 = 8.0;   //  release time ratio just below full pedal
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+pub type NoteCount = [u8; 128];
+pub struct Pedal {
+    sustain: u8,
+    sostenuto: bool,
+    key_on: NoteCount,          //  pressed keys
+    sustained: NoteCount,       //  deferred note offs
+    sos_captured: [bool; 128],  //  notes captured at sostenuto on
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Default for Pedal {
+    fn default() -> Self {Self::new()}
+}
+impl Pedal {
+    pub fn new() -> Self {
+        Self {
+            sustain: 0,
+            sostenuto: false,
+            key_on: [0; 128],
+            sustained: [0; 128],
+            sos_captured: [false; 128],
+        }
+    }
+    pub fn note_on(&mut self, note: u8) {
+        let nt = (note & 0x7f) as usize;
+        self.key_on[nt] = self.key_on[nt].saturating_add(1);
+    }
+    pub fn note_off(&mut self, note: u8) -> bool {
+        //  true: release the note now, false: deferred by a pedal
+        let nt = (note & 0x7f) as usize;
+        self.key_on[nt] = self.key_on[nt].saturating_sub(1);
+        if self.is_held(nt) {
+            self.sustained[nt] = self.sustained[nt].saturating_add(1);
+            false
+        } else {
+            true
+        }
+    }
+    pub fn sustain(&mut self, value: u8) -> NoteCount {
+        //  returns deferred note offs to release now
+        self.sustain = value;
+        self.take_released()
+    }
+    pub fn sostenuto(&mut self, value: u8) -> NoteCount {
+        let on = value >= PEDAL_ON;
+        if on && !self.sostenuto {
+            for nt in 0..128 {
+                self.sos_captured[nt] = self.key_on[nt] > 0;
+            }
+        } else if !on {
+            self.sos_captured = [false; 128];
+        }
+        self.sostenuto = on;
+        self.take_released()
+    }
+    pub fn release_time_ratio(&self) -> f32 {
+        //  1.0 (off) .. MAX_RELEASE_STRETCH (half pedal just below full)
+        if self.sustain < PEDAL_ON || self.sustain >= PEDAL_FULL {return 1.0;}
+        let depth = ((self.sustain - PEDAL_ON + 1) as f32)/((PEDAL_FULL - PEDAL_ON) as f32);
+        1.0 + (MAX_RELEASE_STRETCH - 1.0)*depth
+    }
+    pub fn reset(&mut self) {
+        //  All Sound Off: nothing is held any more
+        self.key_on = [0; 128];
+        self.sustained = [0; 128];
+        self.sos_captured = [false; 128];
+    }
+    pub fn for_each_note<F: FnMut(u8)>(notes: &NoteCount, mut f: F) {
+        for (nt, cnt) in notes.iter().enumerate() {
+            for _ in 0..*cnt {
+                f(nt as u8);
+            }
+        }
+    }
+    fn is_held(&self, nt: usize) -> bool {
+        self.sustain >= PEDAL_FULL || (self.sostenuto && self.sos_captured[nt])
+    }
+    fn take_released(&mut self) -> NoteCount {
+        let mut released: NoteCount = [0; 128];
+        for (nt, rel) in released.iter_mut().enumerate() {
+            if self.sustained[nt] > 0 && !self.is_held(nt) {
+                *rel = self.sustained[nt];
+                self.sustained[nt] = 0;
+            }
+        }
+        released
+    }
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn released(notes: &NoteCount) -> Vec<u8> {
+        let mut list = Vec::new();
+        Pedal::for_each_note(notes, |nt| list.push(nt));
+        list
+    }
+
+    #[test]
+    fn sustain_defers_note_offs() {
+        let mut pdl = Pedal::new();
+        pdl.note_on(60);
+        assert!(released(&pdl.sustain(127)).is_empty());
+        assert!(!pdl.note_off(60));
+        //  a note pressed while sustained is also held
+        pdl.note_on(64);
+        assert!(!pdl.note_off(64));
+        //  the same key twice: two note offs are given back
+        pdl.note_on(60);
+        assert!(!pdl.note_off(60));
+        assert_eq!(released(&pdl.sustain(0)), [60, 60, 64]);
+        assert!(pdl.note_off(67));
+        assert!(released(&pdl.sustain(0)).is_empty());
+    }
+    #[test]
+    fn sostenuto_captures_only_held_keys() {
+        let mut pdl = Pedal::new();
+        pdl.note_on(60);
+        pdl.note_on(64);
+        assert!(pdl.note_off(64));
+        assert!(released(&pdl.sostenuto(127)).is_empty());
+        //  keys pressed after the pedal on are not held
+        pdl.note_on(67);
+        assert!(pdl.note_off(67));
+        assert!(!pdl.note_off(60));
+        //  the pedal moving while on does not capture again
+        pdl.note_on(72);
+        assert!(released(&pdl.sostenuto(100)).is_empty());
+        assert!(pdl.note_off(72));
+        assert_eq!(released(&pdl.sostenuto(0)), [60]);
+        //  the next pedal on captures again
+        pdl.note_on(60);
+        pdl.sostenuto(127);
+        assert!(!pdl.note_off(60));
+        pdl.reset();
+        assert!(released(&pdl.sostenuto(0)).is_empty());
+    }
+    #[test]
+    fn sustain_and_sostenuto() {
+        let mut pdl = Pedal::new();
+        pdl.note_on(60);
+        pdl.sostenuto(127);
+        pdl.sustain(127);
+        pdl.note_on(64);
+        assert!(!pdl.note_off(60));
+        assert!(!pdl.note_off(64));
+        //  60 is still held by sostenuto
+        assert_eq!(released(&pdl.sustain(0)), [64]);
+        assert_eq!(released(&pdl.sostenuto(0)), [60]);
+    }
+    #[test]
+    fn half_pedal_release_ratio() {
+        let mut pdl = Pedal::new();
+        assert_eq!(pdl.release_time_ratio(), 1.0);
+        pdl.sustain(63);
+        assert_eq!(pdl.release_time_ratio(), 1.0);
+        pdl.sustain(64);
+        let lowest = pdl.release_time_ratio();
+        assert!(lowest > 1.0 && lowest < 1.2);
+        pdl.sustain(126);
+        assert_eq!(pdl.release_time_ratio(), MAX_RELEASE_STRETCH);
+        pdl.sustain(95);
+        let mid = pdl.release_time_ratio();
+        assert!(lowest < mid && mid < MAX_RELEASE_STRETCH);
+        //  full pedal holds the notes instead
+        pdl.sustain(127);
+        assert_eq!(pdl.release_time_ratio(), 1.0);
+        //  half pedal does not defer note offs
+        pdl.sustain(100);
+        pdl.note_on(60);
+        assert!(pdl.note_off(60));
+    }
+}
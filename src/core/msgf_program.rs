@@ -0,0 +1,187 @@
+//
+//  msgf_program.rs
+//	Musical Sound Generator Framework
+//      Program Registry
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Each app registers its instrument factory with named programs.
+//  A program is selected by the 14bit bank number (CC#0 MSB / CC#32 LSB)
+//  and Program Change, like GM2:
+//      1. the registered program of the bank
+//      2. the user tone bank of the bank MSB, if the tone exists
+//      3. the same program of the capital bank (LSB = 0)
+//      4. the same program of bank 0 (default bank)
+//      5. the first program
+//
+use crate::msgf_if;
+use crate::core::*;
+use crate::app;
+
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+pub const TONES_PER_BANK: usize = 128;
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+//  tone tables, tone number, vol, pan, exp, audio config of the instance
+pub type InstFactory = fn(&app::PatchBank, usize, u8, u8, u8, &msgf_if::AudioConfig) -> Box<dyn msgf_inst::Inst>;
+#[derive(Copy, Clone)]
+pub struct Program {
+    pub bank: u16,          //  MSB << 7 | LSB
+    pub number: u8,
+    pub name: &'static str,
+    pub app: usize,         //  index of the factory in the registry
+    pub tone: usize,        //  tone number of the app
+    factory: InstFactory,
+}
+#[derive(Copy, Clone)]
+struct ToneBank {
+    //  bank MSB/LSB, program -> tone number (LSB * 128 + program)
+    bank_msb: u8,
+    name: &'static str,
+    app: usize,
+    factory: InstFactory,
+    tone_count: usize,
+}
+#[derive(Default)]
+pub struct ProgramRegistry {
+    apps: Vec<InstFactory>,     //  one factory for each app
+    registered: Vec<Program>,   //  sorted by bank and program number
+    tone_banks: Vec<ToneBank>,
+    programs: Vec<Program>,     //  registered ones and tones of the tone banks, sorted
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+pub fn bank_number(msb: u8, lsb: u8) -> u16 {((msb as u16 & 0x7f) << 7) | (lsb as u16 & 0x7f)}
+impl Program {
+    pub fn bank_msb(&self) -> u8 {(self.bank >> 7) as u8}
+    pub fn bank_lsb(&self) -> u8 {(self.bank & 0x7f) as u8}
+    pub fn create_inst(&self, bank: &app::PatchBank, vol: u8, pan: u8, exp: u8, cfg: &msgf_if::AudioConfig) -> Box<dyn msgf_inst::Inst> {
+        (self.factory)(bank, self.tone, vol, pan, exp, cfg)
+    }
+    fn key(&self) -> (u16, u8) {(self.bank, self.number)}
+}
+impl ToneBank {
+    fn program(&self, bank: u16, number: u8) -> Option<Program> {
+        let tone = (bank as usize & 0x7f)*TONES_PER_BANK + number as usize;
+        if tone < self.tone_count {
+            Some(Program {bank, number, name: self.name, app: self.app, tone, factory: self.factory})
+        } else {
+            None
+        }
+    }
+}
+impl ProgramRegistry {
+    pub fn new() -> Self {
+        Self {apps: Vec::new(), registered: Vec::new(), tone_banks: Vec::new(), programs: Vec::new()}
+    }
+    fn app_of(&mut self, factory: InstFactory) -> usize {
+        //  programs of the same factory are played by the same kind of instrument
+        match self.apps.iter().position(|f| std::ptr::fn_addr_eq(*f, factory)) {
+            Some(app) => app,
+            None => {
+                self.apps.push(factory);
+                self.apps.len() - 1
+            }
+        }
+    }
+    pub fn apps(&self) -> &[InstFactory] {&self.apps}
+    pub fn add(&mut self, bank: u16, number: u8, name: &'static str,
+      tone: usize, factory: InstFactory) {
+        let app = self.app_of(factory);
+        let prg = Program {bank: bank & 0x3fff, number: number & 0x7f, name, app, tone, factory};
+        //  the same program is overwritten
+        match self.registered.binary_search_by_key(&prg.key(), |p| p.key()) {
+            Ok(idx) => self.registered[idx] = prg,
+            Err(idx) => self.registered.insert(idx, prg),
+        }
+        self.update_list();
+    }
+    pub fn add_tone_bank(&mut self, bank_msb: u8, name: &'static str,
+      factory: InstFactory, tone_count: usize) {
+        //  every LSB of the bank MSB selects 128 tones of the app (e.g. from a patch bank)
+        let app = self.app_of(factory);
+        self.tone_banks.retain(|tb| tb.bank_msb != bank_msb);
+        self.tone_banks.push(ToneBank {bank_msb: bank_msb & 0x7f, name, app, factory, tone_count});
+        self.tone_banks.sort_by_key(|tb| tb.bank_msb);
+        self.update_list();
+    }
+    pub fn find(&self, bank: u16, number: u8) -> Option<Program> {
+        if let Ok(idx) = self.registered.binary_search_by_key(&(bank, number), |p| p.key()) {
+            return Some(self.registered[idx]);
+        }
+        self.tone_banks.iter().find(|tb| tb.bank_msb as u16 == bank >> 7)
+            .and_then(|tb| tb.program(bank, number))
+    }
+    pub fn select(&self, bank: u16, number: u8) -> Program {
+        //  at least one program must be registered
+        self.find(bank, number)
+            .or_else(|| self.find(bank & 0x3f80, number))
+            .or_else(|| self.find(0, number))
+            .unwrap_or(self.registered[0])
+    }
+    pub fn programs(&self) -> &[Program] {&self.programs}
+    fn update_list(&mut self) {
+        //  registered programs and existing tones of the tone banks
+        let mut list = self.registered.clone();
+        for tb in self.tone_banks.iter() {
+            for tone in 0..tb.tone_count.min(TONES_PER_BANK*TONES_PER_BANK) {
+                let bank = ((tb.bank_msb as u16) << 7) | (tone/TONES_PER_BANK) as u16;
+                let number = (tone%TONES_PER_BANK) as u8;
+                if self.registered.binary_search_by_key(&(bank, number), |p| p.key()).is_err() {
+                    list.extend(tb.program(bank, number));
+                }
+            }
+        }
+        list.sort_by_key(|p| p.key());
+        self.programs = list;
+    }
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ProgramRegistry {
+        let mut reg = ProgramRegistry::new();
+        app::va::register(&mut reg, 8);
+        app::sg::register(&mut reg, 1);
+        app::sgf::register(&mut reg, 1);
+        reg
+    }
+    #[test]
+    fn registered_programs() {
+        let reg = registry();
+        assert_eq!(reg.apps().len(), 3);
+        let (va, sg) = (reg.select(0, 0), reg.select(0, 8));
+        assert_eq!((va.name, va.tone), ("VA Sine Bell", 0));
+        assert_eq!((sg.name, sg.tone), ("Sing", 0));
+        assert_ne!(va.app, sg.app);
+        assert_eq!(reg.select(0, 17).app, va.app);
+        //  sorted by bank and program number
+        assert!(reg.programs().windows(2).all(|w| w[0].key() < w[1].key()));
+    }
+    #[test]
+    fn unknown_program_number() {
+        let reg = registry();
+        assert!(reg.find(0, 100).is_none());
+        let prg = reg.select(0, 100);
+        assert_eq!((prg.bank, prg.number, prg.name), (0, 0, "VA Sine Bell"));
+    }
+    #[test]
+    fn same_program_is_overwritten() {
+        let mut reg = registry();
+        let cnt = reg.programs().len();
+        reg.add(0, 1, "VA Square", 2, reg.select(0, 2).factory);
+        assert_eq!(reg.programs().len(), cnt);
+        assert_eq!((reg.select(0, 1).name, reg.select(0, 1).tone), ("VA Square", 2));
+    }
+}
@@ -0,0 +1,166 @@
+//
+//  msgf_rpn.rs
+//	Musical Sound Generator Framework
+//      RPN / NRPN Class
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Parameter number selection and Data Entry of a part.
+//      CC#101/100 : RPN MSB/LSB
+//      CC#99/98   : NRPN MSB/LSB
+//      CC#6/38    : Data Entry MSB/LSB
+//      CC#96/97   : Data Increment/Decrement
+//  Data Entry goes to the RPN or NRPN selected last.
+//  RPN Null (127/127) disables Data Entry until the next selection.
+//
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+pub const RPN_PB_SENS: u16 = 0x0000;        //  MSB: semitone, LSB: cent
+pub const RPN_FINE_TUNE: u16 = 0x0001;      //  8192: center, +-100[cent]
+pub const RPN_COARSE_TUNE: u16 = 0x0002;    //  MSB 64: center, [semitone]
+pub const RPN_NULL: u16 = 0x3fff;
+pub const DATA_CENTER: u16 = 0x2000;
+pub const DATA_MAX: u16 = 0x3fff;
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ParamNum {
+    Rpn(u16),
+    Nrpn(u16),      //  parameter ID of the instrument
+}
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DataEntry {
+    Msb(u8),
+    Lsb(u8),
+    Increment,
+    Decrement,
+}
+pub struct Rpn {
+    rpn: u16,
+    nrpn: u16,
+    selected: Option<ParamNum>,
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Default for Rpn {
+    fn default() -> Self {Self::new()}
+}
+impl Rpn {
+    pub fn new() -> Self {
+        Self {
+            rpn: RPN_NULL,
+            nrpn: RPN_NULL,
+            selected: None,
+        }
+    }
+    pub fn select(&mut self, controller: u8, value: u8) {
+        let value = (value & 0x7f) as u16;
+        match controller {
+            101 => self.rpn = (self.rpn & 0x007f) | (value << 7),
+            100 => self.rpn = (self.rpn & 0x3f80) | value,
+            99 => self.nrpn = (self.nrpn & 0x007f) | (value << 7),
+            98 => self.nrpn = (self.nrpn & 0x3f80) | value,
+            _ => return,
+        }
+        self.selected = match controller {
+            100 | 101 if self.rpn != RPN_NULL => Some(ParamNum::Rpn(self.rpn)),
+            98 | 99 if self.nrpn != RPN_NULL => Some(ParamNum::Nrpn(self.nrpn)),
+            _ => None,
+        };
+    }
+    pub fn selected(&self) -> Option<ParamNum> {self.selected}
+}
+impl DataEntry {
+    pub fn from_cc(controller: u8, value: u8) -> Option<Self> {
+        match controller {
+            6 => Some(DataEntry::Msb(value & 0x7f)),
+            38 => Some(DataEntry::Lsb(value & 0x7f)),
+            96 => Some(DataEntry::Increment),
+            97 => Some(DataEntry::Decrement),
+            _ => None,
+        }
+    }
+    pub fn apply(&self, data: u16, step: u16) -> u16 {
+        //  data: 14bit, Msb clears the LSB
+        match *self {
+            DataEntry::Msb(msb) => (msb as u16) << 7,
+            DataEntry::Lsb(lsb) => (data & 0x3f80) | (lsb as u16),
+            DataEntry::Increment => data.saturating_add(step).min(DATA_MAX),
+            DataEntry::Decrement => data.saturating_sub(step),
+        }
+    }
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_in_any_order() {
+        let mut rpn = Rpn::new();
+        assert_eq!(rpn.selected(), None);
+        //  MSB then LSB
+        rpn.select(101, 0);
+        rpn.select(100, 2);
+        assert_eq!(rpn.selected(), Some(ParamNum::Rpn(RPN_COARSE_TUNE)));
+        //  LSB then MSB: the other half is kept
+        rpn.select(100, 1);
+        assert_eq!(rpn.selected(), Some(ParamNum::Rpn(RPN_FINE_TUNE)));
+        rpn.select(101, 0x12);
+        assert_eq!(rpn.selected(), Some(ParamNum::Rpn(0x12 << 7 | 1)));
+        //  NRPN is kept apart from RPN
+        rpn.select(98, 0x05);
+        rpn.select(99, 0x01);
+        assert_eq!(rpn.selected(), Some(ParamNum::Nrpn(0x0085)));
+        rpn.select(100, 0);
+        assert_eq!(rpn.selected(), Some(ParamNum::Rpn(0x12 << 7)));
+        //  other controllers do not change the selection
+        rpn.select(7, 0);
+        assert_eq!(rpn.selected(), Some(ParamNum::Rpn(0x12 << 7)));
+    }
+    #[test]
+    fn rpn_null_disables_data_entry() {
+        let mut rpn = Rpn::new();
+        rpn.select(101, 0);
+        rpn.select(100, 0);
+        assert_eq!(rpn.selected(), Some(ParamNum::Rpn(RPN_PB_SENS)));
+        rpn.select(101, 127);
+        rpn.select(100, 127);
+        assert_eq!(rpn.selected(), None);
+        rpn.select(99, 127);
+        rpn.select(98, 127);
+        assert_eq!(rpn.selected(), None);
+        //  the next selection enables it again
+        rpn.select(100, 1);
+        assert_eq!(rpn.selected(), Some(ParamNum::Rpn(0x3f81)));
+    }
+    #[test]
+    fn data_entry() {
+        assert_eq!(DataEntry::from_cc(6, 0x80 | 3), Some(DataEntry::Msb(3)));
+        assert_eq!(DataEntry::from_cc(38, 5), Some(DataEntry::Lsb(5)));
+        assert_eq!(DataEntry::from_cc(7, 5), None);
+        //  Msb clears the LSB, Lsb keeps the MSB
+        assert_eq!(DataEntry::Msb(2).apply(0x0155, 1), 0x0100);
+        assert_eq!(DataEntry::Lsb(0x7f).apply(0x0155, 1), 0x017f);
+    }
+    #[test]
+    fn increment_and_decrement_are_clamped() {
+        let inc = DataEntry::from_cc(96, 0).unwrap();
+        let dec = DataEntry::from_cc(97, 0).unwrap();
+        assert_eq!(inc.apply(DATA_CENTER, 1), DATA_CENTER + 1);
+        assert_eq!(dec.apply(DATA_CENTER, 128), DATA_CENTER - 128);
+        assert_eq!(inc.apply(DATA_MAX - 1, 128), DATA_MAX);
+        assert_eq!(inc.apply(DATA_MAX, 1), DATA_MAX);
+        assert_eq!(inc.apply(0xffff, 0xffff), DATA_MAX);
+        assert_eq!(dec.apply(100, 128), 0);
+        assert_eq!(dec.apply(0, 1), 0);
+    }
+}
@@ -0,0 +1,197 @@
+//
+//  msgf_seq.rs
+//	Musical Sound Generator Framework
+//      SMF Sequencer Class
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+use crate::core::msgf_smf::*;
+
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+pub struct Sequencer {
+    smf: Option<Smf>,
+    ev_time: Vec<f64>,          //  [sec] time of each smf.events
+    next_ev: usize,
+    crnt_time: f64,             //  [sec] time of the top of the next audio buffer
+    playing: bool,
+    sounding: [[bool; 128]; 16],//  notes which need note off when stopped
+    used: [bool; 16],           //  channels which need the pedals off when stopped
+    fs: f64,                    //  sampling freq. of the instance
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Sequencer {
+    pub fn new(fs: f32) -> Self {
+        Self {
+            smf: None,
+            ev_time: Vec::new(),
+            next_ev: 0,
+            crnt_time: 0.0,
+            playing: false,
+            sounding: [[false; 128]; 16],
+            used: [false; 16],
+            fs: fs as f64,
+        }
+    }
+    pub fn set_sampling_freq(&mut self, fs: f32) {self.fs = fs as f64;}
+    pub fn load<F: FnMut(u8,u8,u8)>(&mut self, smf: Smf, out: F) {
+        self.stop(out);
+        //  convert tick to time along the tempo map
+        self.ev_time.clear();
+        let mut tp_idx = 0;
+        let mut tp_sec: f64 = 0.0;  //  time of smf.tempo[tp_idx]
+        for ev in smf.events.iter() {
+            while tp_idx+1 < smf.tempo.len() && smf.tempo[tp_idx+1].tick <= ev.tick {
+                tp_idx += 1;
+                tp_sec = smf.tick_to_sec(smf.tempo[tp_idx].tick);
+            }
+            let tp = smf.tempo[tp_idx];
+            let sec = if smf.division & 0x8000 != 0 {
+                smf.tick_to_sec(ev.tick)
+            } else {
+                tp_sec + ((ev.tick - tp.tick) as f64)*(tp.usec_per_beat as f64)
+                          /((smf.division as f64)*1000000.0)
+            };
+            self.ev_time.push(sec);
+        }
+        self.smf = Some(smf);
+        self.next_ev = 0;
+        self.crnt_time = 0.0;
+    }
+    pub fn is_playing(&self) -> bool {self.playing}
+    pub fn play(&mut self) {
+        if self.smf.is_some() && self.next_ev < self.ev_time.len() {
+            self.playing = true;
+        }
+    }
+    pub fn stop<F: FnMut(u8,u8,u8)>(&mut self, out: F) {
+        self.playing = false;
+        self.all_sound_off(out);
+    }
+    pub fn locate<F: FnMut(u8,u8,u8)>(&mut self, tick: u32, mut out: F) {
+        //  out: messages to be sent now
+        self.all_sound_off(&mut out);
+        let smf = match &self.smf {
+            Some(smf) => smf,
+            None => return,
+        };
+        self.next_ev = smf.events.partition_point(|ev| ev.tick < tick);
+        self.crnt_time = smf.tick_to_sec(tick);
+        //  chase program change / control change / pitch bend before the position
+        for ev in smf.events[0..self.next_ev].iter() {
+            match ev.msg[0] & 0xf0 {
+                0x80 | 0x90 | 0xa0 => {}
+                _ => {
+                    self.used[(ev.msg[0] & 0x0f) as usize] = true;
+                    out(ev.msg[0], ev.msg[1], ev.msg[2]);
+                }
+            }
+        }
+    }
+    pub fn process<F: FnMut(u8,u8,u8,u32)>(&mut self, in_number_frames: u32, mut out: F) {
+        //  out: messages with the frame offset in this audio buffer
+        if !self.playing || in_number_frames == 0 {return}
+        let smf = match &self.smf {
+            Some(smf) => smf,
+            None => return,
+        };
+        let fs = self.fs;
+        let end_time = self.crnt_time + (in_number_frames as f64)/fs;
+        while self.next_ev < smf.events.len() && self.ev_time[self.next_ev] < end_time {
+            let ev = smf.events[self.next_ev];
+            let ofs = ((self.ev_time[self.next_ev] - self.crnt_time)*fs).max(0.0) as u32;
+            let ch = (ev.msg[0] & 0x0f) as usize;
+            let note = (ev.msg[1] & 0x7f) as usize;
+            self.used[ch] = true;
+            match ev.msg[0] & 0xf0 {
+                0x80 => self.sounding[ch][note] = false,
+                0x90 => self.sounding[ch][note] = ev.msg[2] != 0,
+                _ => {}
+            }
+            out(ev.msg[0], ev.msg[1], ev.msg[2], ofs.min(in_number_frames-1));
+            self.next_ev += 1;
+        }
+        self.crnt_time = end_time;
+        if self.next_ev >= smf.events.len() {
+            self.playing = false;   //  end of the song
+        }
+    }
+    fn all_sound_off<F: FnMut(u8,u8,u8)>(&mut self, mut out: F) {
+        //  notes held by Sustain/Sostenuto are also stopped
+        for ch in 0..16 {
+            for note in 0..128 {
+                if self.sounding[ch][note] {
+                    out(0x80 | ch as u8, note as u8, 64);
+                    self.sounding[ch][note] = false;
+                }
+            }
+            if self.used[ch] {
+                out(0xb0 | ch as u8, 64, 0);
+                out(0xb0 | ch as u8, 66, 0);
+                out(0xb0 | ch as u8, 120, 0);
+                self.used[ch] = false;
+            }
+        }
+    }
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song() -> Smf {
+        let ev = |tick, msg| SmfEvent {tick, msg};
+        Smf {
+            division: 480,
+            events: vec![
+                ev(0, [0xb1, 64, 127]), ev(0, [0x91, 60, 100]), ev(240, [0x81, 60, 64]),
+                ev(480, [0x93, 62, 100]), ev(960, [0x83, 62, 64]),
+            ],
+            tempo: vec![SmfTempo {tick: 0, usec_per_beat: 500000}],
+        }
+    }
+    fn load(seq: &mut Sequencer) -> Vec<[u8; 3]> {
+        let mut msgs = Vec::new();
+        seq.load(song(), |dt1, dt2, dt3| msgs.push([dt1, dt2, dt3]));
+        seq.play();
+        msgs
+    }
+
+    #[test]
+    fn stop_releases_notes_and_pedals() {
+        let mut seq = Sequencer::new(48000.0);
+        assert!(load(&mut seq).is_empty());
+        //  0.375[sec]: note 60 is off but sustained, note 62 is not played yet
+        seq.process(18000, |_, _, _, _| {});
+        let mut msgs = Vec::new();
+        seq.stop(|dt1, dt2, dt3| msgs.push([dt1, dt2, dt3]));
+        assert_eq!(msgs, [[0xb1, 64, 0], [0xb1, 66, 0], [0xb1, 120, 0]]);
+        assert!(!seq.is_playing());
+        //  nothing is left to stop
+        msgs.clear();
+        seq.stop(|dt1, dt2, dt3| msgs.push([dt1, dt2, dt3]));
+        assert!(msgs.is_empty());
+    }
+    #[test]
+    fn locate_releases_and_chases() {
+        let mut seq = Sequencer::new(48000.0);
+        load(&mut seq);
+        //  0.6[sec]: note 62 is sounding
+        seq.process(28800, |_, _, _, _| {});
+        let mut msgs = Vec::new();
+        seq.locate(240, |dt1, dt2, dt3| msgs.push([dt1, dt2, dt3]));
+        assert_eq!(msgs, [
+            [0xb1, 64, 0], [0xb1, 66, 0], [0xb1, 120, 0],
+            [0x83, 62, 64], [0xb3, 64, 0], [0xb3, 66, 0], [0xb3, 120, 0],
+            [0xb1, 64, 127],    //  chased
+        ]);
+    }
+}
@@ -0,0 +1,288 @@
+//
+//  msgf_smf.rs
+//	Musical Sound Generator Framework
+//      Standard MIDI File (format 0/1) Parser
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+const DEFAULT_TEMPO: u32 = 500000;      //  [usec/quarter note] = 120bpm
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+#[derive(Copy, Clone)]
+pub struct SmfEvent {
+    pub tick: u32,
+    pub msg: [u8; 3],       //  channel message (2 byte message is padded by 0)
+}
+#[derive(Copy, Clone)]
+pub struct SmfTempo {
+    pub tick: u32,
+    pub usec_per_beat: u32,
+}
+pub struct Smf {
+    pub division: u16,              //  ticks per quarter note, or SMPTE if MSB is set
+    pub events: Vec<SmfEvent>,      //  all tracks merged, sorted by tick
+    pub tempo: Vec<SmfTempo>,       //  sorted by tick, tempo[0] is at tick 0
+}
+struct SmfReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl<'a> SmfReader<'a> {
+    fn new(data: &'a [u8]) -> Self {Self {data, pos: 0}}
+    fn is_end(&self) -> bool {self.pos >= self.data.len()}
+    fn read_u8(&mut self) -> Result<u8, &'static str> {
+        let dt = *self.data.get(self.pos).ok_or("SMF: unexpected end of data")?;
+        self.pos += 1;
+        Ok(dt)
+    }
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if self.pos + len > self.data.len() {
+            return Err("SMF: unexpected end of data");
+        }
+        let bytes = &self.data[self.pos..self.pos+len];
+        self.pos += len;
+        Ok(bytes)
+    }
+    fn read_u16(&mut self) -> Result<u16, &'static str> {
+        let b = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+    fn read_u32(&mut self) -> Result<u32, &'static str> {
+        let b = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    fn read_varlen(&mut self) -> Result<u32, &'static str> {
+        let mut value: u32 = 0;
+        for _ in 0..4 {
+            let dt = self.read_u8()?;
+            value = (value << 7) | (dt & 0x7f) as u32;
+            if dt & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("SMF: too long variable length quantity")
+    }
+}
+impl Smf {
+    pub fn parse(data: &[u8]) -> Result<Smf, &'static str> {
+        let mut rd = SmfReader::new(data);
+        if rd.read_bytes(4)? != b"MThd" {
+            return Err("SMF: no MThd chunk");
+        }
+        let hd_len = rd.read_u32()? as usize;
+        if hd_len < 6 {
+            return Err("SMF: broken MThd chunk");
+        }
+        let format = rd.read_u16()?;
+        let track_num = rd.read_u16()?;
+        let division = rd.read_u16()?;
+        rd.read_bytes(hd_len - 6)?;
+        if format > 1 {
+            return Err("SMF: format 2 is not supported");
+        }
+        if division == 0 {
+            return Err("SMF: invalid division");
+        }
+
+        let mut smf = Smf {division, events: Vec::new(), tempo: Vec::new()};
+        let mut trk_cnt = 0;
+        while trk_cnt < track_num && !rd.is_end() {
+            let id = rd.read_bytes(4)?;
+            let len = rd.read_u32()? as usize;
+            let chunk = rd.read_bytes(len)?;
+            if id == b"MTrk" {  //  unknown chunks are skipped
+                smf.parse_track(chunk)?;
+                trk_cnt += 1;
+            }
+        }
+        //  stable sort: events of the same tick keep the track order
+        smf.events.sort_by_key(|ev| ev.tick);
+        smf.tempo.sort_by_key(|tp| tp.tick);
+        if smf.tempo.first().is_none_or(|tp| tp.tick != 0) {
+            smf.tempo.insert(0, SmfTempo {tick: 0, usec_per_beat: DEFAULT_TEMPO});
+        }
+        Ok(smf)
+    }
+    fn parse_track(&mut self, chunk: &[u8]) -> Result<(), &'static str> {
+        let mut rd = SmfReader::new(chunk);
+        let mut tick: u32 = 0;
+        let mut running_status: u8 = 0;
+        while !rd.is_end() {
+            tick = tick.checked_add(rd.read_varlen()?).ok_or("SMF: tick overflow")?;
+            let mut status = rd.read_u8()?;
+            match status {
+                0xff => {   //  Meta Event
+                    running_status = 0;
+                    let meta_type = rd.read_u8()?;
+                    let len = rd.read_varlen()? as usize;
+                    let dt = rd.read_bytes(len)?;
+                    match meta_type {
+                        0x2f => break,  //  End of Track
+                        0x51 if len == 3 => {
+                            let usec_per_beat = ((dt[0] as u32) << 16) | ((dt[1] as u32) << 8) | dt[2] as u32;
+                            self.tempo.push(SmfTempo {tick, usec_per_beat});
+                        }
+                        _ => {}
+                    }
+                }
+                0xf0 | 0xf7 => {   //  System Exclusive (skipped)
+                    let len = rd.read_varlen()? as usize;
+                    rd.read_bytes(len)?;
+                    running_status = 0;
+                }
+                0xf1..=0xfe => return Err("SMF: invalid status"),
+                _ => {
+                    let dt2 = if status < 0x80 {  //  running status
+                        if running_status == 0 {
+                            return Err("SMF: data byte without status");
+                        }
+                        let dt2 = status;
+                        status = running_status;
+                        dt2
+                    } else {
+                        running_status = status;
+                        rd.read_u8()?
+                    };
+                    let dt3 = match status & 0xf0 {
+                        0xc0 | 0xd0 => 0,
+                        _ => rd.read_u8()?,
+                    };
+                    self.events.push(SmfEvent {tick, msg: [status, dt2, dt3]});
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn tick_to_sec(&self, tick: u32) -> f64 {
+        if self.division & 0x8000 != 0 {
+            //  SMPTE: -frames per second, ticks per frame
+            let fps = -((self.division >> 8) as u8 as i8) as f64;
+            let tpf = (self.division & 0x00ff) as f64;
+            return (tick as f64)/(fps*tpf);
+        }
+        let tpqn = self.division as f64;
+        let mut sec: f64 = 0.0;
+        for (i, tp) in self.tempo.iter().enumerate() {
+            if tp.tick >= tick {break;}
+            let end_tick = match self.tempo.get(i+1) {
+                Some(next) if next.tick < tick => next.tick,
+                _ => tick,
+            };
+            sec += ((end_tick - tp.tick) as f64)*(tp.usec_per_beat as f64)/(tpqn*1000000.0);
+        }
+        sec
+    }
+}
+
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smf(format: u16, division: u16, tracks: &[&[u8]]) -> Vec<u8> {
+        let mut data = b"MThd".to_vec();
+        data.extend_from_slice(&6u32.to_be_bytes());
+        data.extend_from_slice(&format.to_be_bytes());
+        data.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+        data.extend_from_slice(&division.to_be_bytes());
+        for trk in tracks {
+            data.extend_from_slice(b"MTrk");
+            data.extend_from_slice(&(trk.len() as u32).to_be_bytes());
+            data.extend_from_slice(trk);
+        }
+        data
+    }
+    fn msgs(smf: &Smf) -> Vec<(u32, [u8; 3])> {
+        smf.events.iter().map(|ev| (ev.tick, ev.msg)).collect()
+    }
+
+    #[test]
+    fn format0_with_running_status() {
+        let trk: &[u8] = &[
+            0x00, 0x90, 60, 100,
+            0x10, 64, 100,          //  running status
+            0x10, 0xc0, 5,          //  2 byte message
+            0x00, 60,               //  data byte after 0xc0 is a running Program Change
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        let smf = Smf::parse(&smf(0, 480, &[trk])).unwrap();
+        assert_eq!(msgs(&smf), vec![
+            (0, [0x90, 60, 100]), (0x10, [0x90, 64, 100]),
+            (0x20, [0xc0, 5, 0]), (0x20, [0xc0, 60, 0]),
+        ]);
+        assert_eq!(smf.tempo.len(), 1);
+        assert_eq!(smf.tempo[0].usec_per_beat, DEFAULT_TEMPO);
+    }
+    #[test]
+    fn meta_and_sysex_cancel_running_status() {
+        let trk: &[u8] = &[0x00, 0x90, 60, 100, 0x00, 0xf0, 0x01, 0xf7, 0x00, 61, 100];
+        assert_eq!(Smf::parse(&smf(0, 480, &[trk])).err(), Some("SMF: data byte without status"));
+        let trk: &[u8] = &[0x00, 0x90, 60, 100, 0x00, 0xff, 0x01, 0x00, 0x00, 61, 100];
+        assert_eq!(Smf::parse(&smf(0, 480, &[trk])).err(), Some("SMF: data byte without status"));
+    }
+    #[test]
+    fn meta_and_sysex_lengths_are_skipped() {
+        let trk: &[u8] = &[
+            0x00, 0xff, 0x03, 0x04, b'S', b'o', b'n', b'g',     //  Track Name
+            0x00, 0xf0, 0x81, 0x00,                             //  SysEx of 128 bytes
+        ];
+        let mut trk = trk.to_vec();
+        trk.extend_from_slice(&[0x00; 127]);
+        trk.push(0xf7);
+        trk.extend_from_slice(&[0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20]); //  Tempo: 500000
+        trk.extend_from_slice(&[0x05, 0x80, 60, 0x00, 0x00, 0xff, 0x2f, 0x00]);
+        let smf = Smf::parse(&smf(0, 480, &[&trk])).unwrap();
+        assert_eq!(msgs(&smf), vec![(5, [0x80, 60, 0])]);
+        assert_eq!(smf.tempo.len(), 1);
+        assert_eq!(smf.tempo[0].usec_per_beat, 500000);
+    }
+    #[test]
+    fn truncated_track_is_an_error() {
+        for trk in [&[0x00, 0x90, 60][..], &[0x00, 0xff, 0x51, 0x03, 0x07][..], &[0x81][..]] {
+            assert_eq!(Smf::parse(&smf(0, 480, &[trk])).err(), Some("SMF: unexpected end of data"));
+        }
+        //  the chunk length over the end of the file
+        let mut data = smf(0, 480, &[&[0x00, 0x90, 60, 100]]);
+        data.truncate(data.len() - 1);
+        assert_eq!(Smf::parse(&data).err(), Some("SMF: unexpected end of data"));
+    }
+    #[test]
+    fn tick_overflow_is_an_error() {
+        //  the longest delta time is 0x0fffffff, and the 17th one is over u32
+        let mut trk = vec![0x00, 0x90, 60, 100];
+        for _ in 0..16 {trk.extend_from_slice(&[0xff, 0xff, 0xff, 0x7f, 60, 100]);}
+        assert!(Smf::parse(&smf(0, 480, &[&trk])).is_ok());
+        trk.extend_from_slice(&[0xff, 0xff, 0xff, 0x7f, 60, 100]);
+        assert_eq!(Smf::parse(&smf(0, 480, &[&trk])).err(), Some("SMF: tick overflow"));
+    }
+    #[test]
+    fn format1_tracks_are_merged_by_tick() {
+        let conductor: &[u8] = &[0x00, 0xff, 0x51, 0x03, 0x0f, 0x42, 0x40, 0x00, 0xff, 0x2f, 0x00];
+        let trk1: &[u8] = &[0x00, 0x90, 60, 100, 0x60, 0x80, 60, 0];
+        let trk2: &[u8] = &[0x30, 0x91, 64, 100, 0x30, 0x81, 64, 0];
+        let smf = Smf::parse(&smf(1, 96, &[conductor, trk1, trk2])).unwrap();
+        assert_eq!(msgs(&smf), vec![
+            (0, [0x90, 60, 100]), (0x30, [0x91, 64, 100]),
+            (0x60, [0x80, 60, 0]), (0x60, [0x81, 64, 0]),
+        ]);
+        assert_eq!(smf.tempo[0].usec_per_beat, 1000000);
+        assert_eq!(smf.tick_to_sec(96), 1.0);
+    }
+    #[test]
+    fn format2_is_not_supported() {
+        assert_eq!(Smf::parse(&smf(2, 480, &[])).err(), Some("SMF: format 2 is not supported"));
+    }
+}
@@ -0,0 +1,230 @@
+//
+//  msgf_ump.rs
+//	Musical Sound Generator Framework
+//      Universal MIDI Packet Parser (MIDI 2.0)
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  32bit words of UMP may be split at any point.
+//  The parser keeps an unfinished packet between calls of parse().
+//      MT 0x1 : System Real Time / Common
+//      MT 0x2 : MIDI 1.0 Channel Voice
+//      MT 0x3 : Data 64 (SysEx7), only complete in one packet
+//      MT 0x4 : MIDI 2.0 Channel Voice
+//  Other message types are skipped by their size.
+//  Groups are not distinguished: every group goes to the same 16 channels.
+//
+//---------------------------------------------------------
+//		Constants
+//---------------------------------------------------------
+//  MIDI 2.0 Channel Voice: opcode (upper 4bit of the status byte)
+//  Assignable Per-Note Controller (0x10) and Relative RPN (0x40) are ignored
+pub const OP_PER_NOTE_RCC: u8 = 0x00;   //  Registered Per-Note Controller
+pub const OP_RPN: u8 = 0x20;
+pub const OP_NRPN: u8 = 0x30;
+pub const OP_RELATIVE_NRPN: u8 = 0x50;
+pub const OP_PER_NOTE_PB: u8 = 0x60;
+pub const OP_NOTE_OFF: u8 = 0x80;
+pub const OP_NOTE_ON: u8 = 0x90;
+pub const OP_POLY_PRESSURE: u8 = 0xa0;
+pub const OP_CONTROL_CHANGE: u8 = 0xb0;
+pub const OP_PROGRAM_CHANGE: u8 = 0xc0;
+pub const OP_CHANNEL_PRESSURE: u8 = 0xd0;
+pub const OP_PITCH_BEND: u8 = 0xe0;
+pub const OP_PER_NOTE_MANAGE: u8 = 0xf0;
+//  Registered Per-Note Controller
+pub const RCC_PITCH_7_25: u8 = 3;       //  absolute pitch: note number in 7.25 fixed point
+//  Note On attribute type
+pub const ATTR_PITCH_7_9: u8 = 3;       //  absolute pitch: note number in 7.9 fixed point
+//  Per-Note Management flags
+pub const MANAGE_RESET: u8 = 0x01;      //  S: per-note controllers to default
+//  Program Change option flags
+pub const PROGRAM_BANK_VALID: u8 = 0x01;
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+pub enum UmpMessage<'a> {
+    Midi1(u8, u8, u8),      //  status, data1, data2 of MIDI 1.0
+    System(u8),             //  status 0xf1-0xff
+    SysEx(&'a [u8]),        //  data bytes without 0xf0/0xf7
+    Midi2 {
+        opcode: u8,         //  OP_*
+        ch: u8,
+        index: u8,          //  note / controller / bank / RPN MSB
+        extra: u8,          //  attribute type / RPN LSB / flags
+        data: u32,
+    },
+}
+pub struct UmpParser {
+    words: [u32; 4],
+    word_cnt: usize,
+    sysex: [u8; 6],
+}
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Default for UmpParser {
+    fn default() -> Self {Self::new()}
+}
+impl UmpParser {
+    pub fn new() -> Self {
+        Self {
+            words: [0; 4],
+            word_cnt: 0,
+            sysex: [0; 6],
+        }
+    }
+    pub fn packet_size(mt: u32) -> usize {
+        //  words of a packet by message type
+        match mt {
+            0x0..=0x2 | 0x6 | 0x7 => 1,
+            0x3 | 0x4 | 0x8..=0xa => 2,
+            0xb | 0xc => 3,
+            _ => 4,
+        }
+    }
+    pub fn parse<F>(&mut self, words: &[u32], mut out: F)
+      where F: FnMut(UmpMessage) {
+        for &word in words {
+            self.words[self.word_cnt] = word;
+            self.word_cnt += 1;
+            if self.word_cnt < Self::packet_size(self.words[0] >> 28) {continue;}
+            self.parse_packet(&mut out);
+            self.word_cnt = 0;
+        }
+    }
+    fn parse_packet<F>(&mut self, out: &mut F)
+      where F: FnMut(UmpMessage) {
+        let [w0, w1, _, _] = self.words;
+        let status = ((w0 >> 16) & 0xff) as u8;
+        let dt1 = ((w0 >> 8) & 0xff) as u8;
+        let dt2 = (w0 & 0xff) as u8;
+        match w0 >> 28 {
+            0x1 => out(UmpMessage::System(status)),
+            0x2 => out(UmpMessage::Midi1(status, dt1 & 0x7f, dt2 & 0x7f)),
+            0x3 => {
+                //  0x0: complete in one packet, start/continue/end are discarded
+                if status >> 4 != 0x0 {return;}
+                let len = ((status & 0x0f) as usize).min(6);
+                let bytes = [dt1, dt2, (w1 >> 24) as u8, (w1 >> 16) as u8, (w1 >> 8) as u8, w1 as u8];
+                self.sysex = bytes;
+                out(UmpMessage::SysEx(&self.sysex[..len]));
+            }
+            0x4 => out(UmpMessage::Midi2 {
+                opcode: status & 0xf0, ch: status & 0x0f,
+                index: dt1 & 0x7f, extra: dt2, data: w1}),
+            _ => {}
+        }
+    }
+}
+//  MIDI 2.0 data -> normalised value
+pub fn unipolar(data: u32) -> f32 {
+    //  0.0..1.0
+    (data as f64/u32::MAX as f64) as f32
+}
+pub fn bipolar(data: u32) -> f32 {
+    //  -1.0..1.0, 0x80000000 is the center
+    ((data as i64 - 0x8000_0000) as f64/0x8000_0000_u32 as f64) as f32
+}
+pub fn velocity(vel: u16) -> u8 {
+    //  16bit -> 7bit for the MIDI 1.0 velocity range of a part,
+    //  0 is raised to 1 (note on with velocity 0 is not note off)
+    ((vel >> 9) as u8).max(1)
+}
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq, Debug)]
+    enum Msg {
+        Midi1(u8, u8, u8),
+        System(u8),
+        SysEx(Vec<u8>),
+        Midi2(u8, u8, u8, u8, u32),
+    }
+    fn parse(parser: &mut UmpParser, words: &[u32]) -> Vec<Msg> {
+        let mut msgs = Vec::new();
+        parser.parse(words, |msg| msgs.push(match msg {
+            UmpMessage::Midi1(st, dt1, dt2) => Msg::Midi1(st, dt1, dt2),
+            UmpMessage::System(st) => Msg::System(st),
+            UmpMessage::SysEx(bytes) => Msg::SysEx(bytes.to_vec()),
+            UmpMessage::Midi2 {opcode, ch, index, extra, data} => Msg::Midi2(opcode, ch, index, extra, data),
+        }));
+        msgs
+    }
+
+    #[test]
+    fn packet_sizes() {
+        let sizes = [1, 1, 1, 2, 2, 4, 1, 1, 2, 2, 2, 3, 3, 4, 4, 4];
+        for (mt, size) in sizes.iter().enumerate() {
+            assert_eq!(UmpParser::packet_size(mt as u32), *size, "MT {:x}", mt);
+        }
+        //  unknown packets are skipped by their size
+        let mut parser = UmpParser::new();
+        let words = [
+            0x0000_0000,                                        //  MT 0: NOOP
+            0x5000_0000, 1, 2, 3,                               //  MT 5: Data 128
+            0xd000_0000, 1, 2, 3,                               //  MT d: Flex Data
+            0xb000_0000, 1, 2,                                  //  MT b: reserved
+            0x8000_0000, 1,                                     //  MT 8: reserved
+            0x2090_3c64,
+        ];
+        assert_eq!(parse(&mut parser, &words), [Msg::Midi1(0x90, 0x3c, 0x64)]);
+    }
+    #[test]
+    fn messages() {
+        let mut parser = UmpParser::new();
+        let words = [
+            0x10f8_0000,                    //  Timing Clock
+            0x21b3_07ff,                    //  data bytes are 7bit
+            0x4495_3c03, 0xffff_1e00,       //  group 4 is not distinguished
+        ];
+        assert_eq!(parse(&mut parser, &words), [
+            Msg::System(0xf8),
+            Msg::Midi1(0xb3, 0x07, 0x7f),
+            Msg::Midi2(OP_NOTE_ON, 5, 0x3c, ATTR_PITCH_7_9, 0xffff_1e00),
+        ]);
+    }
+    #[test]
+    fn words_split_across_calls() {
+        let mut parser = UmpParser::new();
+        assert_eq!(parse(&mut parser, &[0x40b0_0700]), []);
+        assert_eq!(parse(&mut parser, &[0x8000_0000, 0x20e0_0040]), [
+            Msg::Midi2(OP_CONTROL_CHANGE, 0, 7, 0, 0x8000_0000),
+            Msg::Midi1(0xe0, 0x00, 0x40),
+        ]);
+        //  a 4 word packet split over three calls
+        assert_eq!(parse(&mut parser, &[0x5000_0000]), []);
+        assert_eq!(parse(&mut parser, &[1, 2]), []);
+        assert_eq!(parse(&mut parser, &[3, 0x1ffe_0000]), [Msg::System(0xfe)]);
+    }
+    #[test]
+    fn sysex7_length() {
+        let mut parser = UmpParser::new();
+        //  GM System On in one packet: 7e 7f 09 01
+        assert_eq!(parse(&mut parser, &[0x3004_7e7f, 0x0901_0000]),
+            [Msg::SysEx(vec![0x7e, 0x7f, 0x09, 0x01])]);
+        assert_eq!(parse(&mut parser, &[0x3000_0000, 0]), [Msg::SysEx(vec![])]);
+        assert_eq!(parse(&mut parser, &[0x3006_0102, 0x0304_0506]),
+            [Msg::SysEx(vec![1, 2, 3, 4, 5, 6])]);
+        //  over 6 is limited to the packet
+        assert_eq!(parse(&mut parser, &[0x300f_0102, 0x0304_0506]),
+            [Msg::SysEx(vec![1, 2, 3, 4, 5, 6])]);
+        //  start/continue/end are discarded
+        assert_eq!(parse(&mut parser, &[0x3116_0102, 0x0304_0506, 0x3312_0708, 0]), []);
+    }
+    #[test]
+    fn velocity_zero_is_raised() {
+        assert_eq!(velocity(0), 1);
+        assert_eq!(velocity(0x01ff), 1);
+        assert_eq!(velocity(0x0400), 2);
+        assert_eq!(velocity(0x8000), 64);
+        assert_eq!(velocity(0xffff), 127);
+    }
+}
@@ -11,6 +11,7 @@
 use crate::core::*;
 use crate::msgf_if;
 use crate::core::msgf_disp::MsgfDisplay;
+use crate::core::msgf_log::{LogLevel, LogCategory};
 //---------------------------------------------------------
 //		Constants
 //---------------------------------------------------------
@@ -20,24 +21,62 @@ pub enum NoteStatus {
     AfterNoteOff,
     DuringDamp,
 }
-const DAMP_TIME: u32 = 300;		// * dac time(22.68usec)
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum StealPolicy {
+    Oldest,         //  the oldest voice
+    Quietest,       //  the voice of the lowest level
+    SameNote,       //  the same note is retriggered, otherwise the oldest
+    ReleaseFirst,   //  the oldest voice after note off, otherwise the oldest
+}
+impl StealPolicy {
+    pub fn from_u8(value: u8) -> StealPolicy {
+        match value {
+            0 => StealPolicy::Oldest,
+            1 => StealPolicy::Quietest,
+            2 => StealPolicy::SameNote,
+            _ => StealPolicy::ReleaseFirst,
+        }
+    }
+    pub fn select<T, S, L>(&self, voices: &[T], status: S, level: L) -> Option<usize>
+      where S: Fn(&T) -> NoteStatus, L: Fn(&T) -> f32 {
+        //  voices: the oldest first, returns the index of the voice to be stolen
+        //  voices during damp are never chosen
+        let candidates = || voices.iter().enumerate()
+            .filter(|(_, vce)| status(vce) != NoteStatus::DuringDamp);
+        let oldest = candidates().map(|(i, _)| i).next();
+        match self {
+            StealPolicy::Oldest | StealPolicy::SameNote => oldest,
+            StealPolicy::Quietest => candidates()
+                .min_by(|(_, a), (_, b)| level(a).total_cmp(&level(b)))
+                .map(|(i, _)| i),
+            StealPolicy::ReleaseFirst => candidates()
+                .find(|(_, vce)| status(vce) == NoteStatus::AfterNoteOff)
+                .map(|(i, _)| i)
+                .or(oldest),
+        }
+    }
+}
+pub const MAX_POLYPHONY: usize = 32;    //  capacity of a voice pool
+const DAMP_TIME: u32 = 300;		// * dac time(22.68usec) at DEFAULT_SAMPLING_FREQ
 
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
 pub trait Voice {
     fn start_sound(&mut self);
-    fn slide(&mut self, _note: u8, _vel: u8){}
+    fn slide(&mut self, _note: u8, _vel: f32){}     //  vel: 0.0-1.0
     fn note_off(&mut self);
+    fn set_release_time_ratio(&mut self, _ratio: f32){}   //  half pedal
     fn damp(&mut self);
     fn change_pmd(&mut self, value: f32);
     fn amplitude(&mut self, volume: u8, expression: u8);
     fn pitch(&mut self, pitch:f32);
+    fn per_note_pitch(&mut self, _pitch: f32){}     //  [cent] added to pitch()
     fn status(&self) -> NoteStatus;
     fn process(&mut self, abuf: &mut msgf_afrm::AudioFrame, in_number_frames: usize) -> bool;
     fn note_num(&self) -> u8;
-    fn velocity(&self) -> u8;
-    fn set_prm(&mut self, prm_type: u8, value: u8);
+    #[allow(dead_code)]
+    fn velocity(&self) -> f32;
 
     //  Setter/Getter
     fn put_lvl_check_buf(&mut self, lvl: f32);
@@ -46,31 +85,35 @@ pub trait Voice {
     fn ended(&self) -> bool;
     fn set_ended(&mut self, which: bool);
 }
+pub fn lvl_check_buf_size(fs: f32) -> usize {
+    (fs/100.0) as usize   // 10msec
+}
 //---------------------------------------------------------
 //		Trait Bound
 //---------------------------------------------------------
 pub fn manage_note_level<T: Voice+MsgfDisplay>(t: &mut T, 
     abuf:   &mut msgf_afrm::AudioFrame,
-    aegbuf: &mut msgf_cfrm::CtrlFrame) -> bool {
+    level:  f32,        //  max level of the AEG in this frame
+    fs_ratio: f32) -> bool {    //  sampling freq. / DEFAULT_SAMPLING_FREQ
     if t.status() != NoteStatus::DuringDamp {
         //	Check Level
-        let level = aegbuf.get_max_level();
         t.put_lvl_check_buf(level);
         if msgf_if::DAMP_LIMIT_DEPTH > level {
-            t.print_str("Damped!");
+            t.log(LogLevel::Debug, LogCategory::Voice, format_args!("Damped!"));
             t.damp();
         }
     } else {    //	Damp
+        let damp_time = ((DAMP_TIME as f32)*fs_ratio) as u32;
         for snum in 0..abuf.sample_number {
             let mut rate: f32 = 0.0;
-            if t.damp_counter() <= DAMP_TIME {
-                let cntdwn = DAMP_TIME - t.damp_counter();
-                rate = (cntdwn as f32)/(DAMP_TIME as f32);
+            if t.damp_counter() <= damp_time {
+                let cntdwn = damp_time - t.damp_counter();
+                rate = (cntdwn as f32)/(damp_time as f32);
                 rate *= rate;
             }
             abuf.mul_rate(snum, rate);
             t.inc_damp_counter();
-            if t.damp_counter() > DAMP_TIME {
+            if t.damp_counter() > damp_time {
                 t.set_ended(true);
                 break;
             }
@@ -78,3 +121,44 @@ pub fn manage_note_level<T: Voice+MsgfDisplay>(t: &mut T,
     }
     t.ended()
 }
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use NoteStatus::*;
+
+    fn select(policy: StealPolicy, voices: &[(NoteStatus, f32)]) -> Option<usize> {
+        policy.select(voices, |vce| vce.0, |vce| vce.1)
+    }
+
+    #[test]
+    fn steal_order() {
+        let voices = [
+            (DuringDamp, 0.0), (DuringNoteOn, 0.5), (DuringNoteOn, 0.2),
+            (AfterNoteOff, 0.4), (AfterNoteOff, 0.1),
+        ];
+        assert_eq!(select(StealPolicy::Oldest, &voices), Some(1));
+        assert_eq!(select(StealPolicy::SameNote, &voices), Some(1));
+        assert_eq!(select(StealPolicy::Quietest, &voices), Some(4));
+        assert_eq!(select(StealPolicy::ReleaseFirst, &voices), Some(3));
+    }
+    #[test]
+    fn release_first_falls_back_to_the_oldest() {
+        let voices = [(DuringDamp, 0.0), (DuringNoteOn, 0.5), (DuringNoteOn, 0.2)];
+        assert_eq!(select(StealPolicy::ReleaseFirst, &voices), Some(1));
+        //  the first of the same level
+        let voices = [(DuringNoteOn, 0.2), (DuringNoteOn, 0.2)];
+        assert_eq!(select(StealPolicy::Quietest, &voices), Some(0));
+    }
+    #[test]
+    fn damping_voices_are_not_stolen() {
+        let voices = [(DuringDamp, 0.0), (DuringDamp, 0.0)];
+        for policy in [StealPolicy::Oldest, StealPolicy::Quietest,
+                       StealPolicy::SameNote, StealPolicy::ReleaseFirst] {
+            assert_eq!(select(policy, &voices), None);
+            assert_eq!(select(policy, &[]), None);
+        }
+    }
+}
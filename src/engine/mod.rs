@@ -11,8 +11,12 @@ pub mod msgf_gen;
 pub mod msgf_additive;
 pub mod msgf_aeg;
 pub mod msgf_osc;
+pub mod msgf_noise;
 pub mod msgf_lfo;
 pub mod msgf_delay;
 pub mod msgf_sd_delay;
 pub mod msgf_biquad;
+pub mod msgf_vcf;
+pub mod msgf_svf;
+pub mod msgf_ladder;
 pub mod msgf_vocal;
\ No newline at end of file
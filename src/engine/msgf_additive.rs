@@ -8,8 +8,8 @@
 //  Released under the MIT license
 //  https://opensource.org/licenses/mit-license.php
 //
-use crate::msgf_if;
 use crate::core::*;
+use crate::core::msgf_param::{ParamUnit, Smoothing};
 use crate::engine::msgf_gen;
 use crate::engine::msgf_gen::*;
 use crate::engine::msgf_osc::*;
@@ -24,6 +24,53 @@ pub struct AdditiveParameter {
     pub prtm_spd: f32,      //  speed of portamento: 0.0(fastest)-?
     pub magnitude: f32,
 }
+impl msgf_patch::PatchPrm for AdditiveParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "coarse_tune" => self.coarse_tune = msgf_patch::parse_value(value)?,
+            "fine_tune" => self.fine_tune = msgf_patch::parse_value(value)?,
+            "pmd" => self.pmd = msgf_patch::parse_value(value)?,
+            "prtm_spd" => self.prtm_spd = msgf_patch::parse_value(value)?,
+            "magnitude" => self.magnitude = msgf_patch::parse_value(value)?,
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "coarse_tune", self.coarse_tune);
+        msgf_patch::write_item(out, prefix, "fine_tune", self.fine_tune);
+        msgf_patch::write_item(out, prefix, "pmd", self.pmd);
+        msgf_patch::write_item(out, prefix, "prtm_spd", self.prtm_spd);
+        msgf_patch::write_item(out, prefix, "magnitude", self.magnitude);
+    }
+}
+pub const ADDITIVE_PARAMS: [msgf_param::ParamDef; 5] = [
+    msgf_param::ParamDef {name: "coarse_tune", min: -24.0, max: 24.0, unit: ParamUnit::Semitone, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "fine_tune", min: -100.0, max: 100.0, unit: ParamUnit::Cent, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "pmd", min: 0.0, max: 1.0, unit: ParamUnit::Octave, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "prtm_spd", min: 0.0, max: 1.0, unit: ParamUnit::Rate, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "magnitude", min: 0.0, max: 4.0, unit: ParamUnit::Level, smoothing: Smoothing::Audio},
+];
+impl msgf_param::ParamPrm for AdditiveParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.coarse_tune as f32,
+            1 => self.fine_tune,
+            2 => self.pmd,
+            3 => self.prtm_spd,
+            _ => self.magnitude,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.coarse_tune = value as i32,
+            1 => self.fine_tune = value,
+            2 => self.pmd = value,
+            3 => self.prtm_spd = value,
+            _ => self.magnitude = value,
+        }
+    }
+}
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
@@ -37,9 +84,11 @@ pub struct Additive {
     real_prtm_spd: f32,
     //  for Pitch Bend
     cnt_ratio: f32,     //  ratio of Hz
+    magnitude: msgf_param::Smoother,    //  smoothed every sample
     //  Formant
     f1: f32,
     f2: f32,
+    fs: f32,            //  sampling freq.
 }
 //---------------------------------------------------------
 //		Implements
@@ -51,7 +100,7 @@ pub struct Additive {
 //  e   500     1900    2500    3500
 //  o   500     800     2500    3500
 impl Additive {
-    pub fn new(prms:&AdditiveParameter, note:u8, cnt_pitch:f32) -> Additive {
+    pub fn new(prms:&AdditiveParameter, note:u8, cnt_pitch:f32, fs:f32) -> Additive {
         let pit = Osc::calc_base_pitch(prms.coarse_tune, prms.fine_tune, note);
         Self {
             prms_variable: *prms,
@@ -61,10 +110,20 @@ impl Additive {
             target_pitch: pit,
             real_prtm_spd: 0.0,
             cnt_ratio: Osc::calc_cnt_pitch(cnt_pitch),
+            magnitude: msgf_param::Smoother::new(prms.magnitude, fs),
             f1: 300.0,
             f2: 2300.0,
+            fs,
         }
     }
+    pub fn update_prm(&mut self, prms: &AdditiveParameter, note: u8) {
+        //  Parameter change while sounding: the pitch moves without portamento
+        self.prms_variable = *prms;
+        self.pmd = prms.pmd;
+        self.target_pitch = Osc::calc_base_pitch(prms.coarse_tune, prms.fine_tune, note);
+        self.base_pitch = self.target_pitch;
+        self.magnitude.set_target(prms.magnitude);
+    }
     pub fn change_pmd(&mut self, value:f32) {self.pmd = value;}
     pub fn change_note(&mut self, note:u8) {
         self.target_pitch = Osc::calc_base_pitch(self.prms_variable.coarse_tune,
@@ -77,6 +136,8 @@ impl Additive {
     }
     pub fn change_f1(&mut self, f1:f32) {self.f1 = f1;}
     pub fn change_f2(&mut self, f2:f32) {self.f2 = f2;}
+    #[allow(clippy::if_same_then_else)]
+    #[allow(clippy::needless_range_loop)]
     fn formant_filter(&self, pitch:f32) -> [f32; 33] {
         //  各倍音に一番近いフォルマントを探し、そのフォルマントから
         //  各倍音のレベルを 0.5..1.5 の間で生成する
@@ -103,6 +164,7 @@ impl Additive {
         }
         flt
     }
+    #[allow(clippy::needless_range_loop)]
     fn scaling_filter(pitch:f32) -> [f32; 33] {
         // 音程が上がるにつれ、倍音が減る割合を設定する
         const CENTER_FREQ: f32 = 200.0; //[freq]
@@ -124,6 +186,7 @@ impl Additive {
         }
         sflt
     }
+    #[allow(clippy::needless_range_loop)]
     fn wave_func(&self, phase: f32, ot_num: usize, filter: [f32;33]) -> f32 {
         let mut pls: f32 = 0.0;
         const PHASE_STREWING: f32 = 0.01;
@@ -145,20 +208,19 @@ impl Additive {
 }
 impl Engine for Additive {
     fn process_ac(&mut self, abuf: &mut msgf_afrm::AudioFrame, lbuf: &mut msgf_cfrm::CtrlFrame) {
-        let lvl_variable = self.prms_variable.magnitude;
         if self.target_pitch != self.base_pitch {
             //  Portameneto Operation
             let diff = self.target_pitch - self.base_pitch;
             self.pitch_interporation(diff);
         }
-        let delta_phase = self.base_pitch*self.cnt_ratio/msgf_if::SAMPLING_FREQ;
+        let delta_phase = self.base_pitch*self.cnt_ratio/self.fs;
         let mut phase = self.next_phase;
         let ot: usize = (msgf_gen::ABORT_FREQUENCY/self.base_pitch) as usize;
         let filter: [f32; 33] = self.generate_filter();
         let max_overtone = if ot <= 32 {ot} else {32};
         for i in 0..abuf.sample_number {
             let sample = self.wave_func(phase, max_overtone, filter);
-            abuf.set_val(i, sample*lvl_variable);
+            abuf.set_val(i, sample*self.magnitude.next());
             let magnitude = lbuf.ctrl_for_audio(i)*self.pmd;
             phase += delta_phase*(2.0_f32.powf(magnitude));
             while phase > 1.0 { phase -= 1.0 }
@@ -9,18 +9,61 @@
 //  https://opensource.org/licenses/mit-license.php
 //
 use crate::core::*;
+use crate::core::msgf_param::{ParamUnit, Smoothing};
 use crate::engine::msgf_gen::*;
 
 //---------------------------------------------------------
 //		Synth. Parameter
 //---------------------------------------------------------
 #[derive(Copy, Clone)]
-pub struct AegParameter {
+pub struct AegParameter {   //  rates are tuned for DEFAULT_SAMPLING_FREQ
     pub attack_rate: f32,
     pub decay_rate: f32,
     pub sustain_level: f32,
     pub release_rate: f32,
 }
+impl msgf_patch::PatchPrm for AegParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "attack_rate" => self.attack_rate = msgf_patch::parse_value(value)?,
+            "decay_rate" => self.decay_rate = msgf_patch::parse_value(value)?,
+            "sustain_level" => self.sustain_level = msgf_patch::parse_value(value)?,
+            "release_rate" => self.release_rate = msgf_patch::parse_value(value)?,
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "attack_rate", self.attack_rate);
+        msgf_patch::write_item(out, prefix, "decay_rate", self.decay_rate);
+        msgf_patch::write_item(out, prefix, "sustain_level", self.sustain_level);
+        msgf_patch::write_item(out, prefix, "release_rate", self.release_rate);
+    }
+}
+pub const AEG_PARAMS: [msgf_param::ParamDef; 4] = [
+    msgf_param::ParamDef {name: "attack_rate", min: 0.0, max: 1.0, unit: ParamUnit::Rate, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "decay_rate", min: 0.0, max: 1.0, unit: ParamUnit::Rate, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "sustain_level", min: 0.0, max: 1.0, unit: ParamUnit::Level, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "release_rate", min: 0.0, max: 1.0, unit: ParamUnit::Rate, smoothing: Smoothing::None},
+];
+impl msgf_param::ParamPrm for AegParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.attack_rate,
+            1 => self.decay_rate,
+            2 => self.sustain_level,
+            _ => self.release_rate,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.attack_rate = value,
+            1 => self.decay_rate = value,
+            2 => self.sustain_level = value,
+            _ => self.release_rate = value,
+        }
+    }
+}
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
@@ -44,14 +87,22 @@ pub struct Aeg {
     interpolate_value: f32,
     release_rsv: bool,
     last_value: f32,
+    step_before_reached: f32,
+    lpf_coef: f32,
+    release_time_ratio: f32,    //  release time is stretched by this (sustain pedal)
+    fs_ratio: f32,              //  sampling freq. / DEFAULT_SAMPLING_FREQ
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl Aeg {
-    pub fn new(ref_prms: &AegParameter) -> Aeg {
+    pub fn new(ref_prms: &AegParameter, fs_ratio: f32) -> Aeg {
+        let mut prms = *ref_prms;
+        prms.attack_rate = Aeg::stretch_rate(prms.attack_rate, fs_ratio);
+        prms.decay_rate = Aeg::stretch_rate(prms.decay_rate, fs_ratio);
+        prms.release_rate = Aeg::stretch_rate(prms.release_rate, fs_ratio);
         Aeg {
-            prms: *ref_prms,
+            prms,
             state: EgState::NotYet,
             tgt_value: 0.0,
             src_value: 0.0,
@@ -60,8 +111,43 @@ impl Aeg {
             interpolate_value: 0.0,
             release_rsv: false,
             last_value: 0.0,
+            step_before_reached: 0.001/fs_ratio,
+            lpf_coef: Aeg::stretch_rate(0.2, fs_ratio),
+            release_time_ratio: 1.0,
+            fs_ratio,
+        }
+    }
+    pub fn update_prm(&mut self, ref_prms: &AegParameter) {
+        //  Parameter change while sounding: the current segment goes on with the new rate
+        self.prms = *ref_prms;
+        self.prms.attack_rate = self.rate_for_fs(self.prms.attack_rate);
+        self.prms.decay_rate = self.rate_for_fs(self.prms.decay_rate);
+        self.prms.release_rate = self.rate_for_fs(self.prms.release_rate);
+        match self.state {
+            EgState::Attack => self.crnt_rate = self.prms.attack_rate,
+            EgState::Decay => {
+                self.crnt_rate = self.prms.decay_rate;
+                self.tgt_value = self.prms.sustain_level;
+            }
+            EgState::Sustain => self.tgt_value = self.prms.sustain_level,
+            EgState::Release => self.crnt_rate =
+                Aeg::stretch_rate(self.prms.release_rate, self.release_time_ratio),
+            _ => {}
         }
     }
+    fn rate_for_fs(&self, rate: f32) -> f32 {
+        //  Convert a rate per control frame at DEFAULT_SAMPLING_FREQ
+        //  to the one at the sampling freq. of the instance (same time constant)
+        Aeg::stretch_rate(rate, self.fs_ratio)
+    }
+    fn stretch_rate(rate: f32, time_ratio: f32) -> f32 {
+        //  the rate whose time constant is time_ratio times longer
+        if rate >= 1.0 || rate <= 0.0 || time_ratio == 1.0 {return rate;}
+        1.0 - (1.0 - rate).powf(1.0/time_ratio)
+    }
+    pub fn set_release_time_ratio(&mut self, ratio: f32) {
+        self.release_time_ratio = ratio;
+    }
     pub fn move_to_attack(&mut self) {
         self.src_value = 0.0;
         self.tgt_value = 1.0;
@@ -101,7 +187,7 @@ impl Aeg {
         else {
             self.src_value = self.crnt_value;
             self.tgt_value = 0.0;
-            self.crnt_rate = self.prms.release_rate;
+            self.crnt_rate = Aeg::stretch_rate(self.prms.release_rate, self.release_time_ratio);
             self.state = EgState::Release;
             self.interpolate_value = 0.0;
         }
@@ -116,10 +202,9 @@ impl Aeg {
     fn calc_delta_eg(&mut self, eg_diff: f32) -> f32 {
         // 0.0 -> 1.0 の動きを作り出し、interpolate_value に格納
         // その値を eg_diff にかけて、現在の到達値を返す
-        const STEP_BEFORE_REACHED: f32 = 0.001;
         let mut intplt = self.interpolate_value;
         if intplt > 0.98 { // この数値で到達タイミングを調整
-            intplt += STEP_BEFORE_REACHED;
+            intplt += self.step_before_reached;
             if intplt > 1.0 {intplt = 1.0;}
         } else {
             intplt += (1.0-intplt)*(self.crnt_rate);
@@ -130,8 +215,9 @@ impl Aeg {
 }
 impl Engine for Aeg {
     fn process_c(&mut self, cbuf: &mut msgf_cfrm::CtrlFrame) {
-        let eg_diff: f32 = self.tgt_value - self.src_value;
-        for i in 0..cbuf.sample_number {
+        for i in cbuf.first_to_calc()..cbuf.sample_number {
+            //  the segment may change in the loop
+            let eg_diff: f32 = self.tgt_value - self.src_value;
             let mut eg_crnt: f32 = self.tgt_value;
             match self.state {
                 EgState::Attack => {
@@ -160,7 +246,7 @@ impl Engine for Aeg {
                 _ => {},
             }
             //  AEG の動きでノイズが出ないように LPF をかける
-            let lpf_coef = 0.2;
+            let lpf_coef = self.lpf_coef;
             eg_crnt = (1.0-lpf_coef)*self.last_value + lpf_coef*eg_crnt;
             self.last_value = eg_crnt;
             cbuf.set_cbuf(i, eg_crnt);
@@ -16,6 +16,7 @@ use crate::engine::msgf_gen::*;
 //		Synth. Parameter
 //---------------------------------------------------------
 #[derive(Copy, Clone)]
+#[allow(dead_code)]
 pub struct BiquadParameter {
     pub freq: f32,
     pub resonance: f32,
@@ -33,12 +34,13 @@ pub struct Biquad {
     x_z2: f32,
     y_z1: f32,
     y_z2: f32,
+    fs: f32,        //  sampling freq.
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl Biquad {
-    pub fn new() -> Biquad {
+    pub fn new(fs: f32) -> Biquad {
         Self {
             a1: 0.0,
             a2: 0.0,
@@ -49,11 +51,12 @@ impl Biquad {
             x_z2: 0.0,
             y_z1: 0.0,
             y_z2: 0.0,
+            fs,
         }
     }
     fn calc_analog_cutoff(&self, fd: f32) -> f32 {
         // cutoff : fd/fs = digital cutoff/sampling freq
-        (msgf_if::PI*fd/msgf_if::SAMPLING_FREQ).tan()/(2.0*msgf_if::PI)
+        (msgf_if::PI*fd/self.fs).tan()/(2.0*msgf_if::PI)
     }
     pub fn set_thru(&mut self) {
         self.a1 = 0.0;
@@ -84,6 +87,32 @@ impl Biquad {
         self.b1 = 0.0;
         self.b2 = -self.b0;
     }
+    pub fn set_hpf(&mut self, cutoff:f32, reso:f32) {
+        let fa = self.calc_analog_cutoff(cutoff);
+        let sqfc = fa * fa;
+        let sqpi = msgf_if::PI * msgf_if::PI;
+        let a0 = 1.0 + (2.0 * msgf_if::PI * fa)/reso + 4.0 * sqpi * sqfc;
+        self.a1 = (8.0 * sqpi * sqfc - 2.0)/a0;
+        self.a2 = (1.0 - 2.0 * msgf_if::PI * fa/reso + 4.0 * sqpi * sqfc)/a0;
+        self.b0 = 1.0/a0;
+        self.b1 = -2.0/a0;
+        self.b2 = self.b0;
+    }
+    pub fn set_notch(&mut self, cutoff:f32, reso:f32) {
+        let fa = self.calc_analog_cutoff(cutoff);
+        let sqfc = fa * fa;
+        let sqpi = msgf_if::PI * msgf_if::PI;
+        let a0 = 1.0 + (2.0 * msgf_if::PI * fa)/reso + 4.0 * sqpi * sqfc;
+        self.a1 = (8.0 * sqpi * sqfc - 2.0)/a0;
+        self.a2 = (1.0 - 2.0 * msgf_if::PI * fa/reso + 4.0 * sqpi * sqfc)/a0;
+        self.b0 = (1.0 + 4.0 * sqpi * sqfc)/a0;
+        self.b1 = self.a1;
+        self.b2 = self.b0;
+    }
+    pub fn filter(&mut self, input: f32) -> f32 {
+        //  one sample, for coefficients changing every sample
+        self.core_job(input)
+    }
     fn core_job(&mut self, input: f32) -> f32 {
 		let mut output: f32 = self.b0*input + self.b1*self.x_z1 + self.b2*self.x_z2;
 		output += - self.a1*self.y_z1 - self.a2*self.y_z2;
@@ -10,6 +10,7 @@
 //
 use crate::msgf_if;
 use crate::core::*;
+use crate::core::msgf_param::{ParamUnit, Smoothing};
 use crate::engine::msgf_gen::*;
 
 //---------------------------------------------------------
@@ -21,50 +22,116 @@ pub struct DelayParameter {
     pub r_time: f32,    //  0.0 - 1.0 [sec]
     pub att_ratio: f32,     //  attenuation
 }
+impl msgf_patch::PatchPrm for DelayParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "l_time" => self.l_time = msgf_patch::parse_value(value)?,
+            "r_time" => self.r_time = msgf_patch::parse_value(value)?,
+            "att_ratio" => self.att_ratio = msgf_patch::parse_value(value)?,
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "l_time", self.l_time);
+        msgf_patch::write_item(out, prefix, "r_time", self.r_time);
+        msgf_patch::write_item(out, prefix, "att_ratio", self.att_ratio);
+    }
+}
+pub const DELAY_PARAMS: [msgf_param::ParamDef; 3] = [
+    msgf_param::ParamDef {name: "l_time", min: 0.0, max: 1.0, unit: ParamUnit::Sec, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "r_time", min: 0.0, max: 1.0, unit: ParamUnit::Sec, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "att_ratio", min: 0.0, max: 1.0, unit: ParamUnit::Level, smoothing: Smoothing::Audio},
+];
+impl msgf_param::ParamPrm for DelayParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.l_time,
+            1 => self.r_time,
+            _ => self.att_ratio,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.l_time = value,
+            1 => self.r_time = value,
+            _ => self.att_ratio = value,
+        }
+    }
+}
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
 pub struct Delay {
-    att_ratio: f32,
+    att_ratio: msgf_param::Smoother,    //  smoothed every sample
+    buf_size: usize,
     delay_buffer: [msgf_afrm::AudioFrame; 2],
     rd_ptr: [usize;2],
     wr_ptr: [usize;2],
+    fs: f32,
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl Delay {
 
-    pub fn new(ref_prms: &DelayParameter) -> Self {
+    pub fn new(ref_prms: &DelayParameter, fs: f32) -> Self {
+        let buf_size = fs as usize;    // 1[sec]
         Delay {
-            att_ratio: ref_prms.att_ratio,
+            att_ratio: msgf_param::Smoother::new(ref_prms.att_ratio, fs),
+            buf_size,
             delay_buffer: 
-                [msgf_afrm::AudioFrame::new(44100,44100),
-                msgf_afrm::AudioFrame::new(44100,44100)],  // 1[sec]
+                [msgf_afrm::AudioFrame::new(buf_size,buf_size),
+                msgf_afrm::AudioFrame::new(buf_size,buf_size)],
             rd_ptr: [0,0],
-            wr_ptr: [(ref_prms.l_time*44100.0) as usize, (ref_prms.r_time*44100.0) as usize],
+            wr_ptr: [((ref_prms.l_time*fs) as usize).min(buf_size-1),
+                     ((ref_prms.r_time*fs) as usize).min(buf_size-1)],
+            fs,
+        }
+    }
+    pub fn reset(&mut self, ref_prms: &DelayParameter) {
+        //  Another tone: same as new() without allocation
+        let fs = self.fs;
+        self.att_ratio.set(ref_prms.att_ratio);
+        self.delay_buffer.iter_mut().for_each(|buf| buf.clr_abuf());
+        self.rd_ptr = [0,0];
+        self.wr_ptr = [((ref_prms.l_time*fs) as usize).min(self.buf_size-1),
+                       ((ref_prms.r_time*fs) as usize).min(self.buf_size-1)];
+    }
+    pub fn update_prm(&mut self, ref_prms: &DelayParameter) {
+        //  Parameter change while sounding: the delay time moves the write pointer
+        let fs = self.fs;
+        let time = [ref_prms.l_time, ref_prms.r_time];
+        for (str, tm) in time.iter().enumerate() {
+            let dly = ((tm*fs) as usize).min(self.buf_size-1);
+            self.wr_ptr[str] = (self.rd_ptr[str] + dly)%self.buf_size;
         }
+        self.att_ratio.set_target(ref_prms.att_ratio);
     }
     fn inc_ptr(&mut self, str: usize) {
         self.rd_ptr[str] += 1;
         self.wr_ptr[str] += 1;
-        if self.rd_ptr[str] >= 44100 {
+        if self.rd_ptr[str] >= self.buf_size {
             self.rd_ptr[str] = 0;
         }
-        if self.wr_ptr[str] >= 44100 {
+        if self.wr_ptr[str] >= self.buf_size {
             self.wr_ptr[str] = 0;
         }
     }
 }
 impl Engine for Delay {
+    #[allow(clippy::needless_range_loop)]
     fn process_as(&mut self, in_abuf: [&mut msgf_afrm::AudioFrame;2]) {
         let snum = in_abuf[0].sample_number;
+        let att_ratio = self.att_ratio;
         for str in 0..2 {
+            self.att_ratio = att_ratio;     //  same ramp for L/R
             for i in 0..snum {
+                let att = self.att_ratio.next();
                 if let Some(input_dt) = in_abuf[str].get_from_abuf(i)  {
                     let mut crnt_dt: f32 = 0.0;
                     if let Some(output_dt) = self.delay_buffer[str].get_from_abuf(self.rd_ptr[str]) {
-                        crnt_dt = input_dt + output_dt*self.att_ratio;
+                        crnt_dt = input_dt + output_dt*att;
                         if crnt_dt < msgf_if::DAMP_LIMIT_DEPTH && -msgf_if::DAMP_LIMIT_DEPTH < crnt_dt {
                             crnt_dt = 0.0;
                         }
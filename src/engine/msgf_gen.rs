@@ -8,6 +8,8 @@
 //  Released under the MIT license
 //  https://opensource.org/licenses/mit-license.php
 //
+#![allow(clippy::excessive_precision)]  // tables are written with full precision
+#![allow(clippy::approx_constant)]
 use crate::core::*;
 //---------------------------------------------------------
 //		Definition
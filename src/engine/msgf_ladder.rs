@@ -0,0 +1,138 @@
+//
+//  msgf_ladder.rs
+//	Musical Sound Generator Framework
+//      Ladder Filter Class
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+use crate::msgf_if;
+use crate::core::*;
+use crate::engine::msgf_gen::*;
+//---------------------------------------------------------
+//		Synth. Parameter
+//---------------------------------------------------------
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum LadderType {
+    Lpf,
+    Hpf,
+    Bpf,
+    Notch,
+}
+#[derive(Copy, Clone)]
+pub struct LadderParameter {
+    pub filter_type: LadderType,
+    pub four_pole: bool,    //  24dB/oct, false: 12dB/oct
+    pub cutoff: f32,        //  [Hz]
+    pub resonance: f32,     //  0.0-1.2, self-oscillation over 1.0
+    pub drive: f32,         //  1.0: clean, saturated by tanh() over it
+}
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+//  Moog-style ladder: four one-pole stages in Zero Delay Feedback,
+//  and tanh() in the feedback loop.
+//  Other types and 12dB/oct are mixed from the input u of the
+//  first stage and the outputs y1-y4 of the stages.
+pub struct Ladder {
+    prms: LadderParameter,
+    g: f32,             //  G of one stage
+    k: f32,             //  feedback 0.0-4.8
+    state: [f32; 4],
+    crnt_oct: f32,      //  log2 of the cutoff, smoothed every sample
+    fs: f32,            //  sampling freq.
+}
+const MIN_CUTOFF: f32 = 20.0;
+const MAX_CUTOFF_RATIO: f32 = 0.45;     //  of the sampling freq.
+const MAX_RESONANCE: f32 = 1.2;
+const PASSBAND_COMP: f32 = 0.5;         //  LPF: makes up part of the loss by feedback
+const KICK: f32 = 1.0e-4;               //  self-oscillation starts without input
+//  control frames are smoothed out in about one frame
+const SMOOTH_COEF: f32 = 1.0/(msgf_if::AUDIO_FRAME_PER_CONTROL as f32);
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Ladder {
+    pub fn new(prms: &LadderParameter, fs: f32) -> Ladder {
+        let mut ldr = Ladder {
+            prms: *prms,
+            g: 0.0,
+            k: 0.0,
+            state: [KICK, 0.0, 0.0, 0.0],
+            crnt_oct: prms.cutoff.max(MIN_CUTOFF).log2(),
+            fs,
+        };
+        ldr.set_cutoff(prms.cutoff);
+        ldr
+    }
+    pub fn update_prm(&mut self, prms: &LadderParameter) {
+        //  the states are kept, so the sound continues
+        self.prms = *prms;
+        self.set_cutoff(prms.cutoff);
+    }
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        //  cheap enough to be called every sample
+        let fc = cutoff.clamp(MIN_CUTOFF, self.fs*MAX_CUTOFF_RATIO);
+        let g = (msgf_if::PI*fc/self.fs).tan();
+        self.g = g/(1.0 + g);
+        self.k = 4.0*self.prms.resonance.clamp(0.0, MAX_RESONANCE);
+    }
+    pub fn filter(&mut self, input: f32) -> f32 {
+        //  The output of the fourth stage is G^4*u + sum, then the feedback
+        //  is solved without delay, and saturated by tanh()
+        let g = self.g;
+        let sum = self.state.iter().fold(0.0, |acc, s| acc*g + s*(1.0 - g));
+        let drive = self.prms.drive.max(1.0);
+        let comp = if self.prms.filter_type == LadderType::Lpf {1.0 + self.k*PASSBAND_COMP} else {1.0};
+        let x = input*drive*comp;
+        let u = ((x - self.k*sum)/(1.0 + self.k*g*g*g*g)).tanh();
+        let mut y = [0.0; 4];
+        let mut stg_in = u;
+        for (s, yn) in self.state.iter_mut().zip(y.iter_mut()) {
+            let v = (stg_in - *s)*g;
+            stg_in = v + *s;
+            *s = stg_in + v;
+            *yn = stg_in;
+        }
+        self.tap(u, y)
+    }
+    fn tap(&self, u: f32, y: [f32; 4]) -> f32 {
+        //  L: one stage, HPF = (1-L)^n, BPF = (2L(1-L))^(n/2), Notch = (1-2L+2L^2)^(n/2)
+        let [y1, y2, y3, y4] = y;
+        match (self.prms.filter_type, self.prms.four_pole) {
+            (LadderType::Lpf, false) => y2,
+            (LadderType::Lpf, true) => y4,
+            (LadderType::Hpf, false) => u - 2.0*y1 + y2,
+            (LadderType::Hpf, true) => u - 4.0*y1 + 6.0*y2 - 4.0*y3 + y4,
+            (LadderType::Bpf, false) => 2.0*(y1 - y2),
+            (LadderType::Bpf, true) => 4.0*(y2 - 2.0*y3 + y4),
+            (LadderType::Notch, false) => u - 2.0*y1 + 2.0*y2,
+            (LadderType::Notch, true) => u - 4.0*y1 + 8.0*y2 - 8.0*y3 + 4.0*y4,
+        }
+    }
+}
+impl Engine for Ladder {
+    fn process_a(&mut self, abuf: &mut msgf_afrm::AudioFrame) {
+        for i in 0..abuf.sample_number {
+            if let Some(x) = abuf.get_from_abuf(i) {
+                let y = self.filter(x);
+                abuf.set_val(i, y);
+            }
+        }
+    }
+    fn process_ac(&mut self, abuf: &mut msgf_afrm::AudioFrame, cbuf: &mut msgf_cfrm::CtrlFrame) {
+        //  cbuf: cutoff modulation [oct]
+        let base_oct = self.prms.cutoff.max(MIN_CUTOFF).log2();
+        for i in 0..abuf.sample_number {
+            let tgt = base_oct + cbuf.ctrl_for_audio(i);
+            self.crnt_oct += (tgt - self.crnt_oct)*SMOOTH_COEF;
+            self.set_cutoff(2.0_f32.powf(self.crnt_oct));
+            if let Some(x) = abuf.get_from_abuf(i) {
+                let y = self.filter(x);
+                abuf.set_val(i, y);
+            }
+        }
+    }
+}
@@ -10,19 +10,21 @@
 //
 use crate::msgf_if;
 use crate::core::*;
+use crate::core::msgf_param::{ParamUnit, Smoothing};
 use crate::engine::msgf_gen::*;
 
 //---------------------------------------------------------
 //		Synth. Parameter
 //---------------------------------------------------------
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 #[allow(dead_code)]
+#[allow(clippy::enum_variant_names)]
 pub enum LfoDirection {
     LfoBoth,
     LfoUpper,
     LfoLower,
 }
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 #[allow(dead_code)]
 pub enum LfoWave {
     Tri,
@@ -30,6 +32,25 @@ pub enum LfoWave {
     Squ,
     Sin,
 }
+impl LfoDirection {
+    pub fn from_u8(value: u8) -> LfoDirection {
+        match value {
+            0 => LfoDirection::LfoBoth,
+            1 => LfoDirection::LfoUpper,
+            _ => LfoDirection::LfoLower,
+        }
+    }
+}
+impl LfoWave {
+    pub fn from_u8(value: u8) -> LfoWave {
+        match value {
+            0 => LfoWave::Tri,
+            1 => LfoWave::Saw,
+            2 => LfoWave::Squ,
+            _ => LfoWave::Sin,
+        }
+    }
+}
 #[derive(Copy, Clone)]
 pub struct LfoParameter {
     pub freq: f32,              // RTP, prm#0
@@ -38,6 +59,64 @@ pub struct LfoParameter {
     pub fadein_time: u64,       // NKP, prm#2
     pub delay_time: u64,        // NKP, prm#3
 }
+impl msgf_patch::PatchPrm for LfoParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "freq" => self.freq = msgf_patch::parse_value(value)?,
+            "wave" => self.wave = match value {
+                "Tri" => LfoWave::Tri,
+                "Saw" => LfoWave::Saw,
+                "Squ" => LfoWave::Squ,
+                "Sin" => LfoWave::Sin,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "direction" => self.direction = match value {
+                "LfoBoth" => LfoDirection::LfoBoth,
+                "LfoUpper" => LfoDirection::LfoUpper,
+                "LfoLower" => LfoDirection::LfoLower,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "fadein_time" => self.fadein_time = msgf_patch::parse_value(value)?,
+            "delay_time" => self.delay_time = msgf_patch::parse_value(value)?,
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "freq", self.freq);
+        msgf_patch::write_item(out, prefix, "wave", self.wave);
+        msgf_patch::write_item(out, prefix, "direction", self.direction);
+        msgf_patch::write_item(out, prefix, "fadein_time", self.fadein_time);
+        msgf_patch::write_item(out, prefix, "delay_time", self.delay_time);
+    }
+}
+pub const LFO_PARAMS: [msgf_param::ParamDef; 5] = [
+    msgf_param::ParamDef {name: "freq", min: 0.0, max: 50.0, unit: ParamUnit::Hz, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "wave", min: 0.0, max: 3.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "direction", min: 0.0, max: 2.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "fadein_time", min: 0.0, max: 1000.0, unit: ParamUnit::ControlFrame, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "delay_time", min: 0.0, max: 1000.0, unit: ParamUnit::ControlFrame, smoothing: Smoothing::None},
+];
+impl msgf_param::ParamPrm for LfoParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.freq,
+            1 => self.wave as u8 as f32,
+            2 => self.direction as u8 as f32,
+            3 => self.fadein_time as f32,
+            _ => self.delay_time as f32,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.freq = value,
+            1 => self.wave = LfoWave::from_u8(value as u8),
+            2 => self.direction = LfoDirection::from_u8(value as u8),
+            3 => self.fadein_time = value as u64,
+            _ => self.delay_time = value as u64,
+        }
+    }
+}
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
@@ -52,24 +131,26 @@ pub struct Lfo {
     y: f32,
     z: f32,
     dac_counter: u64,
+    fs: f32,            //  sampling freq.
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl Lfo {
-    pub fn new(ref_prms: &LfoParameter) -> Lfo {
+    pub fn new(ref_prms: &LfoParameter, fs: f32) -> Lfo {
         let coef = Lfo::calc_wave(ref_prms.wave, ref_prms.direction);
         Lfo {
-            fadein_time: ref_prms.fadein_time,
-            delay_time: ref_prms.delay_time,
+            fadein_time: Lfo::calc_time(ref_prms.fadein_time, fs),
+            delay_time: Lfo::calc_time(ref_prms.delay_time, fs),
             next_phase: 0.0,
-            delta_phase: Lfo::calc_freq(ref_prms.freq),
+            delta_phase: Lfo::calc_freq(ref_prms.freq, fs),
             direction: coef.4,
             x1: coef.0,
             x2: coef.1,
             y: coef.2,
             z: coef.3,
             dac_counter: 0,
+            fs,
         }
     }
     fn calc_wave(wv: LfoWave, dir: LfoDirection) -> (f32, f32, f32, f32, LfoDirection) {
@@ -82,37 +163,25 @@ impl Lfo {
         };
         (x1, x2, y, z, dir)
     }
-    fn calc_freq(freq: f32) -> f32 {
-        freq*(msgf_if::AUDIO_FRAME_PER_CONTROL as f32)/msgf_if::SAMPLING_FREQ
+    fn calc_time(time: u64, fs: f32) -> u64 {
+        //  time is counted at DEFAULT_SAMPLING_FREQ
+        ((time as f32)*fs/msgf_if::DEFAULT_SAMPLING_FREQ) as u64
     }
-    pub fn set_wave(&mut self, value: u8) {
-        let dir_num: u8 = value&0x30;
-        let dir: LfoDirection;
-        match dir_num {
-            0 => dir = LfoDirection::LfoBoth,
-            1 => dir = LfoDirection::LfoUpper,
-            2 => dir = LfoDirection::LfoLower,
-            _ => dir = LfoDirection::LfoBoth,
-        }
-        let wv_num: u8 = value&0x60;
-        let wv: LfoWave;
-        match wv_num {
-            0x00 => wv = LfoWave::Tri,
-            0x20 => wv = LfoWave::Saw,
-            0x40 => wv = LfoWave::Squ,
-            0x60 => wv = LfoWave::Sin,
-            _ => wv = LfoWave::Sin,
-        }
-        let coef = Lfo::calc_wave(wv, dir);
+    fn calc_freq(freq: f32, fs: f32) -> f32 {
+        freq*(msgf_if::AUDIO_FRAME_PER_CONTROL as f32)/fs
+    }
+    pub fn update_prm(&mut self, ref_prms: &LfoParameter) {
+        //  Parameter change while sounding, the phase continues
+        let coef = Lfo::calc_wave(ref_prms.wave, ref_prms.direction);
+        self.fadein_time = Lfo::calc_time(ref_prms.fadein_time, self.fs);
+        self.delay_time = Lfo::calc_time(ref_prms.delay_time, self.fs);
+        self.delta_phase = Lfo::calc_freq(ref_prms.freq, self.fs);
         self.direction = coef.4;
         self.x1 = coef.0;
         self.x2 = coef.1;
-        self.y =  coef.2;
+        self.y = coef.2;
         self.z = coef.3;
     }
-    pub fn set_freq(&mut self, value: u8) {
-        self.delta_phase = Lfo::calc_freq((value as f32)/10.0);
-    }
     pub fn start(&mut self) {
         self.dac_counter = 0;
     }
@@ -120,7 +189,7 @@ impl Lfo {
 impl Engine for Lfo {
     fn process_c(&mut self, abuf: &mut msgf_cfrm::CtrlFrame) {
         let mut phase = self.next_phase;
-        for i in 0..abuf.sample_number {
+        for i in abuf.first_to_calc()..abuf.sample_number {
             let mut value = phase;
             if value < self.x1-phase {
                 value = self.x1-phase;
@@ -137,11 +206,7 @@ impl Engine for Lfo {
                 phase -= 1.0;
             }
             //	Limit
-            if value > 1.0 {
-                value = 1.0;
-            } else if value < -1.0 {
-                value = -1.0;
-            }
+            value = value.clamp(-1.0, 1.0);
             //	Fadein, Delay
             let mut lvl = 1.0;
             let mut ofs = 0.0;
@@ -0,0 +1,89 @@
+//
+//  msgf_noise.rs
+//	Musical Sound Generator Framework
+//      Noise Class
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+use std::sync::atomic::{AtomicU32, Ordering};
+use crate::core::*;
+use crate::engine::msgf_gen::*;
+//---------------------------------------------------------
+//		Synth. Parameter
+//---------------------------------------------------------
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum NoiseType {
+    White,
+    Pink,       //  -3dB/oct
+}
+impl NoiseType {
+    pub fn from_u8(value: u8) -> NoiseType {
+        match value {
+            0 => NoiseType::White,
+            _ => NoiseType::Pink,
+        }
+    }
+}
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+pub struct Noise {
+    ntype: NoiseType,
+    seed: u32,          //  xorshift32
+    pink: [f32; 7],     //  state of the pink filter
+}
+//  every Noise starts from a different seed, so voices aren't correlated
+static NEXT_SEED: AtomicU32 = AtomicU32::new(0x1234_5678);
+const SEED_STEP: u32 = 0x9e37_79b9;
+const PINK_LEVEL: f32 = 0.11;   //  peaks stay under 1.0
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Noise {
+    pub fn new(ntype: NoiseType) -> Noise {
+        let seed = NEXT_SEED.fetch_add(SEED_STEP, Ordering::Relaxed);
+        Noise {
+            ntype,
+            seed: if seed == 0 {SEED_STEP} else {seed},
+            pink: [0.0; 7],
+        }
+    }
+    pub fn change_type(&mut self, ntype: NoiseType) {self.ntype = ntype;}
+    fn white(&mut self) -> f32 {
+        //  -1.0 - 1.0
+        let mut x = self.seed;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.seed = x;
+        (x as f32)/(u32::MAX as f32)*2.0 - 1.0
+    }
+    fn pink(&mut self) -> f32 {
+        //  Paul Kellet's filter
+        let white = self.white();
+        let b = &mut self.pink;
+        b[0] = 0.99886*b[0] + white*0.0555179;
+        b[1] = 0.99332*b[1] + white*0.0750759;
+        b[2] = 0.96900*b[2] + white*0.153852;
+        b[3] = 0.86650*b[3] + white*0.3104856;
+        b[4] = 0.55000*b[4] + white*0.5329522;
+        b[5] = -0.7616*b[5] - white*0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white*0.5362;
+        b[6] = white*0.115926;
+        pink*PINK_LEVEL
+    }
+}
+impl Engine for Noise {
+    fn process_a(&mut self, abuf: &mut msgf_afrm::AudioFrame) {
+        for i in 0..abuf.sample_number {
+            let val = match self.ntype {
+                NoiseType::White => self.white(),
+                NoiseType::Pink => self.pink(),
+            };
+            abuf.set_val(i, val);
+        }
+    }
+}
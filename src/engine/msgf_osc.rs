@@ -8,8 +8,10 @@
 //  Released under the MIT license
 //  https://opensource.org/licenses/mit-license.php
 //
+use std::sync::OnceLock;
 use crate::msgf_if;
 use crate::core::*;
+use crate::core::msgf_param::{ParamUnit, Smoothing};
 use crate::engine::msgf_gen;
 use crate::engine::msgf_gen::*;
 //---------------------------------------------------------
@@ -23,14 +25,174 @@ pub enum WvType {
     Square,
     Pulse,
 }
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PrtmCurve {
+    Linear,         //  constant speed in cents
+    Exponential,    //  fast at first, and slow near the target
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum OscAlgorithm {
+    Additive,       //  sum of partials up to ABORT_FREQUENCY
+    PolyBlep,       //  naive wave with polynomial band-limited steps
+    Blit,           //  integrated band-limited impulse train
+    Wavetable,      //  band-limited tables per octave
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SyncType {
+    Off,
+    Hard,           //  restarts the phase at every cycle of the master
+    Soft,           //  reverses the direction at every cycle of the master
+}
+impl WvType {
+    pub fn from_u8(value: u8) -> WvType {
+        match value {
+            0 => WvType::Sine,
+            1 => WvType::Saw,
+            2 => WvType::Square,
+            _ => WvType::Pulse,
+        }
+    }
+}
+impl OscAlgorithm {
+    pub fn from_u8(value: u8) -> OscAlgorithm {
+        match value {
+            0 => OscAlgorithm::Additive,
+            1 => OscAlgorithm::PolyBlep,
+            2 => OscAlgorithm::Blit,
+            _ => OscAlgorithm::Wavetable,
+        }
+    }
+}
+impl SyncType {
+    pub fn from_u8(value: u8) -> SyncType {
+        match value {
+            0 => SyncType::Off,
+            1 => SyncType::Hard,
+            _ => SyncType::Soft,
+        }
+    }
+}
+impl PrtmCurve {
+    pub fn from_u8(value: u8) -> PrtmCurve {
+        match value {
+            0 => PrtmCurve::Linear,
+            _ => PrtmCurve::Exponential,
+        }
+    }
+}
 #[derive(Copy, Clone)]
 pub struct OscParameter {
     pub coarse_tune: i32,   //  [semitone]
     pub fine_tune: f32,     //  [cent]
     pub lfo_depth: f32,     //  1.0 means +-1oct.
     pub wv_type: WvType,
+    pub prtm_curve: PrtmCurve,
+    pub algorithm: OscAlgorithm,    //  for Saw/Square/Pulse
+    pub pulse_width: f32,   //  duty of Pulse: 0.0-1.0
+    pub pwm_lfo: f32,       //  pulse width added by LFO (+-1.0)
+    pub pwm_eg: f32,        //  pulse width added by AEG (+-1.0)
+    pub sync: SyncType,     //  master runs at the note pitch
+    pub sync_pitch: f32,    //  [cent] pitch over the master while synced
+}
+impl msgf_patch::PatchPrm for OscParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "coarse_tune" => self.coarse_tune = msgf_patch::parse_value(value)?,
+            "fine_tune" => self.fine_tune = msgf_patch::parse_value(value)?,
+            "lfo_depth" => self.lfo_depth = msgf_patch::parse_value(value)?,
+            "wv_type" => self.wv_type = match value {
+                "Sine" => WvType::Sine,
+                "Saw" => WvType::Saw,
+                "Square" => WvType::Square,
+                "Pulse" => WvType::Pulse,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "prtm_curve" => self.prtm_curve = match value {
+                "Linear" => PrtmCurve::Linear,
+                "Exponential" => PrtmCurve::Exponential,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "algorithm" => self.algorithm = match value {
+                "Additive" => OscAlgorithm::Additive,
+                "PolyBlep" => OscAlgorithm::PolyBlep,
+                "Blit" => OscAlgorithm::Blit,
+                "Wavetable" => OscAlgorithm::Wavetable,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "pulse_width" => self.pulse_width = msgf_patch::parse_value(value)?,
+            "pwm_lfo" => self.pwm_lfo = msgf_patch::parse_value(value)?,
+            "pwm_eg" => self.pwm_eg = msgf_patch::parse_value(value)?,
+            "sync" => self.sync = match value {
+                "Off" => SyncType::Off,
+                "Hard" => SyncType::Hard,
+                "Soft" => SyncType::Soft,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "sync_pitch" => self.sync_pitch = msgf_patch::parse_value(value)?,
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "coarse_tune", self.coarse_tune);
+        msgf_patch::write_item(out, prefix, "fine_tune", self.fine_tune);
+        msgf_patch::write_item(out, prefix, "lfo_depth", self.lfo_depth);
+        msgf_patch::write_item(out, prefix, "wv_type", self.wv_type);
+        msgf_patch::write_item(out, prefix, "prtm_curve", self.prtm_curve);
+        msgf_patch::write_item(out, prefix, "algorithm", self.algorithm);
+        msgf_patch::write_item(out, prefix, "pulse_width", self.pulse_width);
+        msgf_patch::write_item(out, prefix, "pwm_lfo", self.pwm_lfo);
+        msgf_patch::write_item(out, prefix, "pwm_eg", self.pwm_eg);
+        msgf_patch::write_item(out, prefix, "sync", self.sync);
+        msgf_patch::write_item(out, prefix, "sync_pitch", self.sync_pitch);
+    }
+}
+pub const OSC_PARAMS: [msgf_param::ParamDef; 11] = [
+    msgf_param::ParamDef {name: "coarse_tune", min: -24.0, max: 24.0, unit: ParamUnit::Semitone, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "fine_tune", min: -100.0, max: 100.0, unit: ParamUnit::Cent, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "lfo_depth", min: 0.0, max: 1.0, unit: ParamUnit::Octave, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "wv_type", min: 0.0, max: 3.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "prtm_curve", min: 0.0, max: 1.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "algorithm", min: 0.0, max: 3.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "pulse_width", min: 0.0, max: 1.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "pwm_lfo", min: -1.0, max: 1.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "pwm_eg", min: -1.0, max: 1.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "sync", min: 0.0, max: 2.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "sync_pitch", min: 0.0, max: 4800.0, unit: ParamUnit::Cent, smoothing: Smoothing::Control},
+];
+impl msgf_param::ParamPrm for OscParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.coarse_tune as f32,
+            1 => self.fine_tune,
+            2 => self.lfo_depth,
+            3 => self.wv_type as u8 as f32,
+            4 => self.prtm_curve as u8 as f32,
+            5 => self.algorithm as u8 as f32,
+            6 => self.pulse_width,
+            7 => self.pwm_lfo,
+            8 => self.pwm_eg,
+            9 => self.sync as u8 as f32,
+            _ => self.sync_pitch,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.coarse_tune = value as i32,
+            1 => self.fine_tune = value,
+            2 => self.lfo_depth = value,
+            3 => self.wv_type = WvType::from_u8(value as u8),
+            4 => self.prtm_curve = PrtmCurve::from_u8(value as u8),
+            5 => self.algorithm = OscAlgorithm::from_u8(value as u8),
+            6 => self.pulse_width = value,
+            7 => self.pwm_lfo = value,
+            8 => self.pwm_eg = value,
+            9 => self.sync = SyncType::from_u8(value as u8),
+            _ => self.sync_pitch = value,
+        }
+    }
 }
-type WvFn = fn(f32, usize) -> f32;
+type WvFn = fn(f32, usize, f32) -> f32;
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
@@ -40,22 +202,116 @@ pub struct Osc {
     base_pitch: f32,    //  [Hz]
     cnt_ratio: f32,     //  ratio of Hz
     next_phase: f32,    //  0.0 - 1.0
+    prtm_cent: f32,     //  [cent] pitch offset of portamento, goes to 0
+    prtm_step: f32,     //  Linear: [cent] per sample, Exponential: coef per sample
+    blit_integ: f32,    //  leaky integrator of Blit
+    sync_dir: f32,      //  1.0 / -1.0 : reversed by Soft sync
+    sync_blep: f32,     //  correction of the next sample after Hard sync
+    fs: f32,            //  sampling freq.
 }
+//  Exponential portamento reaches 1% of the interval in the portamento time
+const PRTM_EXP_TIME_CONSTANTS: f32 = 4.6;   //  = ln(100)
+const PRTM_END_CENT: f32 = 0.1;
+//  Band-limited waves: the same level as the sum of partials
+const SAW_LEVEL: f32 = msgf_if::PI/4.0;
+const SQU_LEVEL: f32 = msgf_if::PI/16.0;
+const PLS_OFFSET: f32 = 0.1;
+const PLS_LEVEL: f32 = 0.5;
+const PLS_MAX_OVERTONE: usize = 32;
+const PW_MIN: f32 = 0.02;           //  narrowest pulse width
+const BLIT_LEAK: f32 = 0.999;
+//  Wavetable: level n has partials up to WT_MAX_PARTIAL >> n
+const WT_SIZE: usize = 1024;
+const WT_LEVELS: usize = 10;
+const WT_MAX_PARTIAL: usize = 512;
+static WAVE_TABLES: OnceLock<Vec<[f32; WT_SIZE+1]>> = OnceLock::new();
+//  Sync: a frame of the master has the position of its cycle end in each sample,
+//  as a ratio of the sample interval after the end, or NO_SYNC
+pub const NO_SYNC: f32 = -1.0;
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl Osc {
-    pub fn new(prms:&OscParameter, note:u8, pmd:f32, cnt_pitch:f32) -> Osc {
+    pub fn new(prms:&OscParameter, note:u8, pmd:f32, cnt_pitch:f32, fs:f32) -> Osc {
         Osc {
             prms_variable: *prms,
             pmd,
             base_pitch: Osc::calc_base_pitch(prms.coarse_tune, prms.fine_tune, note),
             cnt_ratio: Osc::calc_cnt_pitch(cnt_pitch),
             next_phase: 0.0,
+            prtm_cent: 0.0,
+            prtm_step: 0.0,
+            blit_integ: Osc::blit_start(prms.wv_type, prms.pulse_width),
+            sync_dir: 1.0,
+            sync_blep: 0.0,
+            fs,
         }
     }
+    pub fn init_tables() {
+        //  Wavetables are made once before process(), without allocation in it
+        WAVE_TABLES.get_or_init(Osc::make_tables);
+    }
+    fn make_tables() -> Vec<[f32; WT_SIZE+1]> {
+        //  [Saw levels.., Square levels..], the same series as get_wave_func()
+        //  Pulse is the difference of two Saws, so any width can be made
+        let sin_tbl: Vec<f64> = (0..WT_SIZE)
+            .map(|i| (2.0*std::f64::consts::PI*(i as f64)/(WT_SIZE as f64)).sin()).collect();
+        let mut tables = Vec::with_capacity(2*WT_LEVELS);
+        for wv in 0..2 {
+            for level in 0..WT_LEVELS {
+                let partials = WT_MAX_PARTIAL >> level;
+                let mut tbl = [0.0f32; WT_SIZE+1];
+                for (i, val) in tbl.iter_mut().take(WT_SIZE).enumerate() {
+                    let mut sum: f64 = 0.0;
+                    for j in 1..=partials {
+                        let s = sin_tbl[(i*j)%WT_SIZE];
+                        let ot = j as f64;
+                        sum += match wv {
+                            0 => 0.5*s/ot,
+                            _ => if j%2 == 1 {0.25*s/ot} else {0.0},
+                        };
+                    }
+                    *val = sum as f32;
+                }
+                tbl[WT_SIZE] = tbl[0];  //  for interpolation
+                tables.push(tbl);
+            }
+        }
+        tables
+    }
+    pub fn update_prm(&mut self, prms: &OscParameter, note: u8) {
+        //  Parameter change while sounding
+        self.prms_variable = *prms;
+        if prms.sync != SyncType::Soft {self.sync_dir = 1.0;}
+        self.change_note(note);
+    }
+    pub fn is_synced(&self) -> bool {self.prms_variable.sync != SyncType::Off}
+    pub fn glide(&mut self, cents: f32, time: f32) {
+        //  cents: start pitch relative to the note, time: [sec]
+        let samples = time*self.fs;
+        if samples < 1.0 || cents == 0.0 {
+            self.prtm_cent = 0.0;
+            return;
+        }
+        self.prtm_cent = cents;
+        self.prtm_step = match self.prms_variable.prtm_curve {
+            PrtmCurve::Linear => cents.abs()/samples,
+            PrtmCurve::Exponential => (-PRTM_EXP_TIME_CONSTANTS/samples).exp(),
+        };
+    }
+    fn next_prtm_cent(&mut self) -> f32 {
+        let crnt = self.prtm_cent;
+        if crnt == 0.0 {return crnt;}
+        let next = match self.prms_variable.prtm_curve {
+            PrtmCurve::Linear => crnt - crnt.signum()*self.prtm_step,
+            PrtmCurve::Exponential => crnt*self.prtm_step,
+        };
+        //  finished when it reaches or passes the target
+        self.prtm_cent = if next.abs() < PRTM_END_CENT || next.signum() != crnt.signum() {0.0} else {next};
+        crnt
+    }
     pub fn change_pmd(&mut self, value:f32) {self.pmd = value;} // value: 1.0:1oct,-1.0:-1oct
-    pub fn _change_note(&mut self, note:u8) {
+    pub fn change_note(&mut self, note:u8) {
         self.base_pitch = Osc::calc_base_pitch(self.prms_variable.coarse_tune,
                                             self.prms_variable.fine_tune, note);
     }
@@ -90,10 +346,8 @@ impl Osc {
         //            -(x0*x1*x3*SIN_TABLE[phase_locate+3]/2.0) + (x0*x1*x2*SIN_TABLE[phase_locate+4]/6.0);
         //assert!(phase_locate < 258, "{},{},{},{}:{}->{}", x0,x1,x2,x3,phase_locate,y);
         let y0 = msgf_gen::SIN_TABLE[phase_locate+2];                    //  linear interpolation
-        let mut y = (msgf_gen::SIN_TABLE[phase_locate+3] - y0)*x1 + y0;  //
-        if y > 1.0 { y = 1.0 }
-        else if y < -1.0 { y = -1.0 }
-        y
+        let y = (msgf_gen::SIN_TABLE[phase_locate+3] - y0)*x1 + y0;  //
+        y.clamp(-1.0, 1.0)
     }
     pub fn calc_cnt_pitch(pitch: f32) -> f32 {    //  pitch : [cent]
         let mut pt: f32 = 1.0;
@@ -108,14 +362,14 @@ impl Osc {
     fn get_wave_func(&self) -> WvFn {
         match self.prms_variable.wv_type {
             WvType::Sine => {
-                //wave_func = |x, _y| {
+                //wave_func = |x, _y, _w| {
                 //  let phase = x * 2.0 * msgf_if::PI;
                 //  phase.sin()
                 //}
-                return |x, _y| Osc::pseudo_sine(x);
+                |x, _y, _w| Osc::pseudo_sine(x)
             }
             WvType::Saw => {
-                return |x, y| {
+                |x, y, _w| {
                     let mut saw: f32 = 0.0;
                     for j in 1..y {
                         let ot:f32 = j as f32;
@@ -123,10 +377,10 @@ impl Osc {
                         saw += 0.5*Osc::pseudo_sine(phase)/ot;
                     }
                     saw
-                };
+                }
             }
             WvType::Square => {
-                return |x, y| {
+                |x, y, _w| {
                     let mut sq: f32 = 0.0;
                     for j in (1..y).step_by(2) {
                         let ot:f32 = j as f32;
@@ -134,17 +388,18 @@ impl Osc {
                         sq += 0.25*Osc::pseudo_sine(phase)/ot;
                     }
                     sq
-                };
+                }
             }
             WvType::Pulse => {
-                return |x, y| {
-                    let mut pls: f32 = 0.1;
-                    let mut oti = y;
-                    if oti > 32 {oti = 32;}
+                |x, y, w| {
+                    //  coefficient of partial j: 2sin(PI*j*w)/(PI*j)
+                    let mut pls: f32 = PLS_OFFSET;
+                    let oti = y.min(PLS_MAX_OVERTONE);
                     for j in 1..oti {
                         let ot:f32 = j as f32;
                         let phase:f32 = x * ot;
-                        pls += 0.5*msgf_gen::PULSE0_1[j]*Osc::pseudo_sine(phase);
+                        let coef = 2.0*Osc::pseudo_sine(0.5*ot*w)/(msgf_if::PI*ot);
+                        pls += 0.5*coef*Osc::pseudo_sine(phase);
                     }
                     pls
                 }
@@ -152,18 +407,188 @@ impl Osc {
         }
     }
 }
-impl Engine for Osc {
-    fn process_ac(&mut self, abuf: &mut msgf_afrm::AudioFrame, lbuf: &mut msgf_cfrm::CtrlFrame) {
-        let delta_phase = self.base_pitch*self.cnt_ratio/msgf_if::SAMPLING_FREQ;
+impl Osc {
+    fn pulse_width(&self, lfo: f32, eg: f32) -> f32 {
+        let prms = &self.prms_variable;
+        (prms.pulse_width + prms.pwm_lfo*lfo + prms.pwm_eg*eg).clamp(PW_MIN, 1.0 - PW_MIN)
+    }
+    fn naive_wave(&self, phase: f32, pw: f32) -> f32 {
+        //  the same level as the band-limited waves, to know the step of Hard sync
+        match self.prms_variable.wv_type {
+            WvType::Sine => Osc::pseudo_sine(phase),
+            WvType::Saw => -SAW_LEVEL*(2.0*phase - 1.0),
+            WvType::Square => if phase < 0.5 {SQU_LEVEL} else {-SQU_LEVEL},
+            WvType::Pulse => PLS_OFFSET + PLS_LEVEL*(if phase < pw {1.0 - pw} else {-pw}),
+        }
+    }
+    fn poly_blep(t: f32, dt: f32) -> f32 {
+        //  residual of a band-limited step of +2 at t=0
+        if t < dt {
+            let t = t/dt;
+            2.0*t - t*t - 1.0
+        } else if t > 1.0 - dt {
+            let t = (t - 1.0)/dt;
+            t*t + 2.0*t + 1.0
+        } else {0.0}
+    }
+    fn poly_blep_wave(&self, phase: f32, dt: f32, pw: f32) -> f32 {
+        let dt = dt.abs().min(0.5);
+        match self.prms_variable.wv_type {
+            WvType::Sine => Osc::pseudo_sine(phase),
+            WvType::Saw => -SAW_LEVEL*(2.0*phase - 1.0 - Osc::poly_blep(phase, dt)),
+            WvType::Square => {
+                let naive = if phase < 0.5 {1.0} else {-1.0};
+                let fall = (phase + 0.5).fract();
+                SQU_LEVEL*(naive + Osc::poly_blep(phase, dt) - Osc::poly_blep(fall, dt))
+            }
+            WvType::Pulse => {
+                let naive = if phase < pw {1.0 - pw} else {-pw};
+                let fall = (phase + 1.0 - pw).fract();
+                PLS_OFFSET + PLS_LEVEL*(naive + 0.5*(Osc::poly_blep(phase, dt) - Osc::poly_blep(fall, dt)))
+            }
+        }
+    }
+    fn dirichlet(phase: f32, m: f32) -> f32 {
+        //  band-limited impulse of m partials (odd), 1.0 at phase 0
+        let den = (msgf_if::PI*phase).sin();
+        if den.abs() < 1.0e-5 {return 1.0;}
+        (msgf_if::PI*m*phase).sin()/(m*den)
+    }
+    fn blit_start(wv_type: WvType, pw: f32) -> f32 {
+        //  steady state just before the step at phase 0, so the note starts without DC
+        match wv_type {
+            WvType::Pulse => -pw.clamp(PW_MIN, 1.0 - PW_MIN),
+            _ => -0.5,
+        }
+    }
+    fn blit_level(&self) -> (f32, f32) {
+        //  (level, offset) of the integrator output
+        match self.prms_variable.wv_type {
+            WvType::Pulse => (PLS_LEVEL, PLS_OFFSET),
+            WvType::Square => (2.0*SQU_LEVEL, 0.0),
+            _ => (2.0*SAW_LEVEL, 0.0),
+        }
+    }
+    fn blit_wave(&mut self, phase: f32, dt: f32, pw: f32) -> f32 {
+        //  impulses of area 1 per period are integrated to steps of 1
+        //  dt is negative while running backwards by Soft sync
+        let m = 2.0*(0.5/dt.abs()).floor().max(0.0) + 1.0;
+        let gain = m*dt;
+        let step = match self.prms_variable.wv_type {
+            WvType::Sine => return Osc::pseudo_sine(phase),
+            WvType::Saw => Osc::dirichlet(phase, m) - 1.0/m,
+            WvType::Square => Osc::dirichlet(phase, m) - Osc::dirichlet(phase - 0.5, m),
+            WvType::Pulse => Osc::dirichlet(phase, m) - Osc::dirichlet(phase - pw, m),
+        };
+        //  the leak removes DC, so every wave is centered at 0
+        self.blit_integ = self.blit_integ*BLIT_LEAK + step*gain;
+        let (level, offset) = self.blit_level();
+        offset + level*self.blit_integ
+    }
+    fn wavetable_wave(&self, tables: &[[f32; WT_SIZE+1]], phase: f32, dt: f32, pw: f32) -> f32 {
+        let wv = match self.prms_variable.wv_type {
+            WvType::Sine => return Osc::pseudo_sine(phase),
+            WvType::Square => 1,
+            _ => 0,
+        };
+        //  the most partials under the Nyquist frequency
+        let partials = (0.5/dt.abs()) as usize;
+        let mut level = 0;
+        while level < WT_LEVELS-1 && (WT_MAX_PARTIAL >> level) > partials {level += 1;}
+        let tbl = &tables[wv*WT_LEVELS + level];
+        let lookup = |phase: f32| {
+            let pos = phase*(WT_SIZE as f32);
+            let idx = (pos as usize).min(WT_SIZE-1);
+            let frac = pos - idx as f32;
+            tbl[idx] + (tbl[idx+1] - tbl[idx])*frac
+        };
+        if self.prms_variable.wv_type == WvType::Pulse {
+            //  Saw(phase) - Saw(phase - pw) is 2*SAW_LEVEL*(1-pw) / -2*SAW_LEVEL*pw
+            let diff = lookup(phase) - lookup((phase - pw).rem_euclid(1.0));
+            PLS_OFFSET + diff*PLS_LEVEL/(2.0*SAW_LEVEL)
+        } else {
+            lookup(phase)
+        }
+    }
+    fn hard_sync(&mut self, phase: f32, dt: f32, pos: f32, pw: f32) -> (f32, f32) {
+        //  pos: ratio of the sample interval after the end of the master cycle
+        //  returns the new phase and the correction of this sample
+        let reset = pos*dt;
+        let end = (phase + (1.0 - pos)*dt).rem_euclid(1.0);
+        let jump = self.naive_wave(reset, pw) - self.naive_wave(end, pw);
+        if self.prms_variable.algorithm == OscAlgorithm::Blit {
+            //  the impulse at phase 0 is about a sample wide, and adds the rest of the step
+            let (level, offset) = self.blit_level();
+            self.blit_integ = (self.naive_wave(reset, pw) - offset)/level - (1.0 - pos);
+        }
+        //  PolyBLEP: the step is smoothed over this and the next sample
+        self.sync_blep = -0.5*jump*(1.0 - pos)*(1.0 - pos);
+        (reset, 0.5*jump*pos*pos)
+    }
+    pub fn process_sync_src(&mut self, lbuf: &mut msgf_cfrm::CtrlFrame, sbuf: &mut msgf_afrm::AudioFrame) {
+        //  Master of sync: no sound, only the end of each cycle is written in sbuf
+        let delta_phase = self.base_pitch*self.cnt_ratio/self.fs;
         let mut phase = self.next_phase;
-        let max_overtone: usize = (msgf_gen::ABORT_FREQUENCY/self.base_pitch) as usize;
+        for i in 0..sbuf.sample_number {
+            let magnitude = lbuf.ctrl_for_audio(i)*self.pmd + self.next_prtm_cent()/1200.0;
+            let dt = delta_phase*(2.0_f32.powf(magnitude));
+            phase += dt;
+            if phase >= 1.0 {
+                phase -= 1.0;
+                sbuf.set_val(i, (phase/dt).min(1.0));
+            } else {
+                sbuf.set_val(i, NO_SYNC);
+            }
+        }
+        self.next_phase = phase;
+    }
+    pub fn process_mod(&mut self, abuf: &mut msgf_afrm::AudioFrame, lbuf: &mut msgf_cfrm::CtrlFrame,
+      ebuf: Option<&msgf_cfrm::CtrlFrame>, sbuf: Option<&msgf_afrm::AudioFrame>) {
+        //  ebuf: envelope for PWM, sbuf: cycle ends of the master (see process_sync_src())
+        let mut delta_phase = self.base_pitch*self.cnt_ratio/self.fs;
+        if sbuf.is_some() {delta_phase *= Osc::calc_cnt_pitch(self.prms_variable.sync_pitch);}
+        let mut phase = self.next_phase;
+        let algorithm = self.prms_variable.algorithm;
+        let additive = algorithm == OscAlgorithm::Additive || self.prms_variable.wv_type == WvType::Sine;
+        let max_overtone: usize = (msgf_gen::ABORT_FREQUENCY/(delta_phase*self.fs)) as usize;
         let wave_func: WvFn = self.get_wave_func();
+        let tables = WAVE_TABLES.get_or_init(Osc::make_tables);
         for i in 0..abuf.sample_number {
-            abuf.set_val(i, wave_func(phase, max_overtone));
-            let magnitude = lbuf.ctrl_for_audio(i)*self.pmd;
-            phase += delta_phase*(2.0_f32.powf(magnitude));
-            while phase > 1.0 { phase -= 1.0 }
+            let lfo = lbuf.ctrl_for_audio(i);
+            let magnitude = lfo*self.pmd + self.next_prtm_cent()/1200.0;
+            //  band-limited: the phase increment of each sample decides the partials
+            let dt = delta_phase*(2.0_f32.powf(magnitude))*self.sync_dir;
+            let pw = self.pulse_width(lfo, ebuf.map_or(0.0, |eb| eb.ctrl_for_audio(i)));
+            let mut val = if additive {wave_func(phase, max_overtone, pw)} else {
+                match algorithm {
+                    OscAlgorithm::PolyBlep => self.poly_blep_wave(phase, dt, pw),
+                    OscAlgorithm::Blit => self.blit_wave(phase, dt, pw),
+                    _ => self.wavetable_wave(tables, phase, dt, pw),
+                }
+            };
+            val += self.sync_blep;
+            self.sync_blep = 0.0;
+            let pos = sbuf.and_then(|sb| sb.get_from_abuf(i)).unwrap_or(NO_SYNC);
+            if pos == NO_SYNC {
+                phase += dt;
+            } else if self.prms_variable.sync == SyncType::Hard {
+                let (reset, blep) = self.hard_sync(phase, dt.abs(), pos, pw);
+                val += blep;
+                phase = reset;
+            } else {
+                //  Soft: turns back at the end of the master cycle
+                phase += (1.0 - pos)*dt - pos*dt;
+                self.sync_dir = -self.sync_dir;
+            }
+            abuf.set_val(i, val);
+            while phase >= 1.0 { phase -= 1.0 }
+            while phase < 0.0 { phase += 1.0 }
         }
         self.next_phase = phase;
     }
 }
+impl Engine for Osc {
+    fn process_ac(&mut self, abuf: &mut msgf_afrm::AudioFrame, lbuf: &mut msgf_cfrm::CtrlFrame) {
+        self.process_mod(abuf, lbuf, None, None);
+    }
+}
@@ -29,35 +29,38 @@ struct SingleBuf {
 }
 pub struct SdDelay {
     att_ratio: f32,
+    buf_size: usize,
     dbuf: [SingleBuf; 2],
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl SingleBuf {
-    fn init(wr: usize) -> Self {
+    fn init(wr: usize, buf_size: usize) -> Self {
         Self { 
-            delay_buffer: msgf_afrm::AudioFrame::new(44100,44100),
-            rd_ptr: 0, wr_ptr: wr,
+            delay_buffer: msgf_afrm::AudioFrame::new(buf_size,buf_size),
+            rd_ptr: 0, wr_ptr: wr.min(buf_size-1),
         }
     }
 }
 impl SdDelay {
-    pub fn new(ref_prms: &msgf_delay::DelayParameter) -> Self {
-        let wrl: usize = (ref_prms.l_time*44100.0) as usize;
-        let wrr: usize = (ref_prms.r_time*44100.0) as usize;
+    pub fn new(ref_prms: &msgf_delay::DelayParameter, fs: f32) -> Self {
+        let buf_size = fs as usize;    // 1[sec]
+        let wrl: usize = (ref_prms.l_time*fs) as usize;
+        let wrr: usize = (ref_prms.r_time*fs) as usize;
         SdDelay {
             att_ratio: ref_prms.att_ratio,
-            dbuf: [SingleBuf::init(wrl), SingleBuf::init(wrr)],
+            buf_size,
+            dbuf: [SingleBuf::init(wrl, buf_size), SingleBuf::init(wrr, buf_size)],
         }
     }
     fn inc_ptr(&mut self, str: usize) {
         self.dbuf[str].rd_ptr += 1;
         self.dbuf[str].wr_ptr += 1;
-        if self.dbuf[str].rd_ptr >= 44100 {
+        if self.dbuf[str].rd_ptr >= self.buf_size {
             self.dbuf[str].rd_ptr = 0;
         }
-        if self.dbuf[str].wr_ptr >= 44100 {
+        if self.dbuf[str].wr_ptr >= self.buf_size {
             self.dbuf[str].wr_ptr = 0;
         }
     }
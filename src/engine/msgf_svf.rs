@@ -0,0 +1,120 @@
+//
+//  msgf_svf.rs
+//	Musical Sound Generator Framework
+//      State Variable Filter Class
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+use crate::msgf_if;
+use crate::core::*;
+use crate::engine::msgf_gen::*;
+//---------------------------------------------------------
+//		Synth. Parameter
+//---------------------------------------------------------
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SvfType {
+    Lpf,
+    Hpf,
+    Bpf,
+    Notch,
+}
+#[derive(Copy, Clone)]
+pub struct SvfParameter {
+    pub filter_type: SvfType,
+    pub cutoff: f32,        //  [Hz]
+    pub resonance: f32,     //  Q
+}
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+//  Zero Delay Feedback (trapezoidal integrators):
+//  stable even if the cutoff changes every sample
+pub struct Svf {
+    prms: SvfParameter,
+    k: f32,             //  1/Q
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    ic1eq: f32,         //  states of two integrators
+    ic2eq: f32,
+    crnt_oct: f32,      //  log2 of the cutoff, smoothed every sample
+    fs: f32,            //  sampling freq.
+}
+const MIN_CUTOFF: f32 = 20.0;
+const MAX_CUTOFF_RATIO: f32 = 0.45;     //  of the sampling freq.
+const MIN_RESONANCE: f32 = 0.5;
+//  control frames are smoothed out in about one frame
+const SMOOTH_COEF: f32 = 1.0/(msgf_if::AUDIO_FRAME_PER_CONTROL as f32);
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Svf {
+    pub fn new(prms: &SvfParameter, fs: f32) -> Svf {
+        let mut svf = Svf {
+            prms: *prms,
+            k: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+            crnt_oct: prms.cutoff.max(MIN_CUTOFF).log2(),
+            fs,
+        };
+        svf.set_cutoff(prms.cutoff);
+        svf
+    }
+    pub fn update_prm(&mut self, prms: &SvfParameter) {
+        //  the states are kept, so the sound continues
+        self.prms = *prms;
+        self.set_cutoff(prms.cutoff);
+    }
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        //  cheap enough to be called every sample
+        let fc = cutoff.clamp(MIN_CUTOFF, self.fs*MAX_CUTOFF_RATIO);
+        let g = (msgf_if::PI*fc/self.fs).tan();
+        self.k = 1.0/self.prms.resonance.max(MIN_RESONANCE);
+        self.a1 = 1.0/(1.0 + g*(g + self.k));
+        self.a2 = g*self.a1;
+        self.a3 = g*self.a2;
+    }
+    pub fn filter(&mut self, input: f32) -> f32 {
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1*self.ic1eq + self.a2*v3;   //  band pass
+        let v2 = self.ic2eq + self.a2*self.ic1eq + self.a3*v3;  //  low pass
+        self.ic1eq = 2.0*v1 - self.ic1eq;
+        self.ic2eq = 2.0*v2 - self.ic2eq;
+        match self.prms.filter_type {
+            SvfType::Lpf => v2,
+            SvfType::Hpf => input - self.k*v1 - v2,
+            SvfType::Bpf => v1,
+            SvfType::Notch => input - self.k*v1,
+        }
+    }
+}
+impl Engine for Svf {
+    fn process_a(&mut self, abuf: &mut msgf_afrm::AudioFrame) {
+        for i in 0..abuf.sample_number {
+            if let Some(x) = abuf.get_from_abuf(i) {
+                let y = self.filter(x);
+                abuf.set_val(i, y);
+            }
+        }
+    }
+    fn process_ac(&mut self, abuf: &mut msgf_afrm::AudioFrame, cbuf: &mut msgf_cfrm::CtrlFrame) {
+        //  cbuf: cutoff modulation [oct]
+        let base_oct = self.prms.cutoff.max(MIN_CUTOFF).log2();
+        for i in 0..abuf.sample_number {
+            let tgt = base_oct + cbuf.ctrl_for_audio(i);
+            self.crnt_oct += (tgt - self.crnt_oct)*SMOOTH_COEF;
+            self.set_cutoff(2.0_f32.powf(self.crnt_oct));
+            if let Some(x) = abuf.get_from_abuf(i) {
+                let y = self.filter(x);
+                abuf.set_val(i, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//
+//  msgf_vcf.rs
+//	Musical Sound Generator Framework
+//      VCF Class
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+use crate::msgf_if;
+use crate::core::*;
+use crate::core::msgf_param::{ParamUnit, Smoothing};
+use crate::engine::msgf_biquad;
+use crate::engine::msgf_svf;
+use crate::engine::msgf_ladder;
+use crate::engine::msgf_gen::*;
+//---------------------------------------------------------
+//		Synth. Parameter
+//---------------------------------------------------------
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum VcfType {
+    Thru,
+    Lpf,
+    Hpf,
+    Bpf,
+    Notch,
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum VcfSlope {
+    Db12,
+    Db24,       //  two stages
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum VcfModel {
+    Biquad,
+    Svf,        //  Zero Delay Feedback State Variable Filter
+    Ladder,     //  Moog-style, with taps for the other types
+}
+impl VcfType {
+    pub fn from_u8(value: u8) -> VcfType {
+        match value {
+            0 => VcfType::Thru,
+            1 => VcfType::Lpf,
+            2 => VcfType::Hpf,
+            3 => VcfType::Bpf,
+            _ => VcfType::Notch,
+        }
+    }
+}
+impl VcfModel {
+    pub fn from_u8(value: u8) -> VcfModel {
+        match value {
+            0 => VcfModel::Biquad,
+            1 => VcfModel::Svf,
+            _ => VcfModel::Ladder,
+        }
+    }
+}
+impl VcfSlope {
+    pub fn from_u8(value: u8) -> VcfSlope {
+        match value {
+            0 => VcfSlope::Db12,
+            _ => VcfSlope::Db24,
+        }
+    }
+}
+#[derive(Copy, Clone)]
+pub struct VcfParameter {
+    pub filter_type: VcfType,
+    pub slope: VcfSlope,
+    pub cutoff: f32,        //  [Hz]
+    pub resonance: f32,     //  Q
+    pub eg_depth: f32,      //  [oct] at the peak of the envelope
+    pub key_track: f32,     //  1.0: cutoff follows the note, centered at C4
+    pub vel_sens: f32,      //  [oct] at velocity 127, centered at 64
+    pub model: VcfModel,
+    pub drive: f32,         //  input gain of Ladder, 1.0: clean
+}
+impl msgf_patch::PatchPrm for VcfParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "filter_type" => self.filter_type = match value {
+                "Thru" => VcfType::Thru,
+                "Lpf" => VcfType::Lpf,
+                "Hpf" => VcfType::Hpf,
+                "Bpf" => VcfType::Bpf,
+                "Notch" => VcfType::Notch,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "slope" => self.slope = match value {
+                "Db12" => VcfSlope::Db12,
+                "Db24" => VcfSlope::Db24,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "cutoff" => self.cutoff = msgf_patch::parse_value(value)?,
+            "resonance" => self.resonance = msgf_patch::parse_value(value)?,
+            "eg_depth" => self.eg_depth = msgf_patch::parse_value(value)?,
+            "key_track" => self.key_track = msgf_patch::parse_value(value)?,
+            "vel_sens" => self.vel_sens = msgf_patch::parse_value(value)?,
+            "model" => self.model = match value {
+                "Biquad" => VcfModel::Biquad,
+                "Svf" => VcfModel::Svf,
+                "Ladder" => VcfModel::Ladder,
+                _ => return Err(msgf_patch::invalid_value(value)),
+            },
+            "drive" => self.drive = msgf_patch::parse_value(value)?,
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "filter_type", self.filter_type);
+        msgf_patch::write_item(out, prefix, "slope", self.slope);
+        msgf_patch::write_item(out, prefix, "cutoff", self.cutoff);
+        msgf_patch::write_item(out, prefix, "resonance", self.resonance);
+        msgf_patch::write_item(out, prefix, "eg_depth", self.eg_depth);
+        msgf_patch::write_item(out, prefix, "key_track", self.key_track);
+        msgf_patch::write_item(out, prefix, "vel_sens", self.vel_sens);
+        msgf_patch::write_item(out, prefix, "model", self.model);
+        msgf_patch::write_item(out, prefix, "drive", self.drive);
+    }
+}
+pub const VCF_PARAMS: [msgf_param::ParamDef; 9] = [
+    msgf_param::ParamDef {name: "filter_type", min: 0.0, max: 4.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "slope", min: 0.0, max: 1.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "cutoff", min: 20.0, max: 20000.0, unit: ParamUnit::Hz, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "resonance", min: 0.5, max: 20.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "eg_depth", min: -8.0, max: 8.0, unit: ParamUnit::Octave, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "key_track", min: 0.0, max: 1.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "vel_sens", min: 0.0, max: 4.0, unit: ParamUnit::Octave, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "model", min: 0.0, max: 2.0, unit: ParamUnit::Index, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "drive", min: 1.0, max: 10.0, unit: ParamUnit::None, smoothing: Smoothing::Control},
+];
+impl msgf_param::ParamPrm for VcfParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.filter_type as u8 as f32,
+            1 => self.slope as u8 as f32,
+            2 => self.cutoff,
+            3 => self.resonance,
+            4 => self.eg_depth,
+            5 => self.key_track,
+            6 => self.vel_sens,
+            7 => self.model as u8 as f32,
+            _ => self.drive,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.filter_type = VcfType::from_u8(value as u8),
+            1 => self.slope = VcfSlope::from_u8(value as u8),
+            2 => self.cutoff = value,
+            3 => self.resonance = value,
+            4 => self.eg_depth = value,
+            5 => self.key_track = value,
+            6 => self.vel_sens = value,
+            7 => self.model = VcfModel::from_u8(value as u8),
+            _ => self.drive = value,
+        }
+    }
+}
+//---------------------------------------------------------
+//		Definition
+//---------------------------------------------------------
+pub struct Vcf {
+    prms: VcfParameter,
+    stage: [msgf_biquad::Biquad; 2],
+    svf: [msgf_svf::Svf; 2],
+    ladder: msgf_ladder::Ladder,
+    note: u8,
+    vel: f32,       //  0.0-1.0
+    base_oct: f32,      //  log2 of the cutoff by key and velocity
+    crnt_oct: f32,      //  smoothed every sample
+    coef_oct: f32,      //  cutoff of the current coefficients
+    fs: f32,            //  sampling freq.
+}
+const KEY_CENTER: f32 = 60.0;
+const MIN_CUTOFF: f32 = 20.0;
+const MAX_CUTOFF_RATIO: f32 = 0.45;     //  of the sampling freq.
+const STAGE1_Q: f32 = 0.707;            //  24dB: resonance is given to the second stage
+const LADDER_RESO: f32 = 1.2/19.5;     //  resonance 0.5-20.0 -> 0.0-1.2 of Ladder
+const COEF_THRESHOLD: f32 = 0.0001;     //  [oct] change to recalculate the coefficients
+//  control frames are smoothed out in about one frame
+const SMOOTH_COEF: f32 = 1.0/(msgf_if::AUDIO_FRAME_PER_CONTROL as f32);
+//---------------------------------------------------------
+//		Implements
+//---------------------------------------------------------
+impl Vcf {
+    pub fn new(prms: &VcfParameter, note: u8, vel: f32, fs: f32) -> Vcf {
+        let base_oct = Vcf::calc_base_oct(prms, note, vel);
+        let svf_prm = msgf_svf::SvfParameter {filter_type: msgf_svf::SvfType::Lpf, cutoff: prms.cutoff, resonance: STAGE1_Q};
+        let ladder_prm = msgf_ladder::LadderParameter {filter_type: msgf_ladder::LadderType::Lpf,
+            four_pole: true, cutoff: prms.cutoff, resonance: 0.0, drive: 1.0};
+        let mut vcf = Vcf {
+            prms: *prms,
+            stage: [msgf_biquad::Biquad::new(fs), msgf_biquad::Biquad::new(fs)],
+            svf: [msgf_svf::Svf::new(&svf_prm, fs), msgf_svf::Svf::new(&svf_prm, fs)],
+            ladder: msgf_ladder::Ladder::new(&ladder_prm, fs),
+            note,
+            vel,
+            base_oct,
+            crnt_oct: base_oct,
+            coef_oct: base_oct,
+            fs,
+        };
+        vcf.set_coef(base_oct);
+        vcf
+    }
+    pub fn update_prm(&mut self, prms: &VcfParameter) {
+        //  Parameter change while sounding: the cutoff moves smoothly
+        self.prms = *prms;
+        self.base_oct = Vcf::calc_base_oct(prms, self.note, self.vel);
+        self.set_coef(self.coef_oct);
+    }
+    pub fn change_note(&mut self, note: u8) {
+        self.note = note;
+        self.base_oct = Vcf::calc_base_oct(&self.prms, note, self.vel);
+    }
+    pub fn is_thru(&self) -> bool {self.prms.filter_type == VcfType::Thru}
+    fn calc_base_oct(prms: &VcfParameter, note: u8, vel: f32) -> f32 {
+        let key = prms.key_track*((note as f32) - KEY_CENTER)/12.0;
+        let vel = prms.vel_sens*(vel*127.0 - 64.0)/63.0;
+        prms.cutoff.max(MIN_CUTOFF).log2() + key + vel
+    }
+    fn set_coef(&mut self, oct: f32) {
+        let cutoff = 2.0_f32.powf(oct).clamp(MIN_CUTOFF, self.fs*MAX_CUTOFF_RATIO);
+        let reso = self.prms.resonance.max(0.5);
+        let (q0, q1) = match self.prms.slope {
+            VcfSlope::Db12 => (reso, reso),
+            VcfSlope::Db24 => (STAGE1_Q, reso),
+        };
+        match self.prms.model {
+            VcfModel::Biquad => {
+                for (stg, q) in self.stage.iter_mut().zip([q0, q1]) {
+                    match self.prms.filter_type {
+                        VcfType::Thru => stg.set_thru(),
+                        VcfType::Lpf => stg.set_lpf(cutoff, q),
+                        VcfType::Hpf => stg.set_hpf(cutoff, q),
+                        VcfType::Bpf => stg.set_bpf(cutoff, q),
+                        VcfType::Notch => stg.set_notch(cutoff, q),
+                    }
+                }
+            }
+            VcfModel::Svf => {
+                let filter_type = match self.prms.filter_type {
+                    VcfType::Thru => None,      //  filter() passes the input
+                    VcfType::Lpf => Some(msgf_svf::SvfType::Lpf),
+                    VcfType::Hpf => Some(msgf_svf::SvfType::Hpf),
+                    VcfType::Bpf => Some(msgf_svf::SvfType::Bpf),
+                    VcfType::Notch => Some(msgf_svf::SvfType::Notch),
+                };
+                if let Some(filter_type) = filter_type {
+                    for (stg, q) in self.svf.iter_mut().zip([q0, q1]) {
+                        stg.update_prm(&msgf_svf::SvfParameter {filter_type, cutoff, resonance: q});
+                    }
+                }
+            }
+            VcfModel::Ladder => {
+                let filter_type = match self.prms.filter_type {
+                    VcfType::Thru => None,      //  filter() passes the input
+                    VcfType::Lpf => Some(msgf_ladder::LadderType::Lpf),
+                    VcfType::Hpf => Some(msgf_ladder::LadderType::Hpf),
+                    VcfType::Bpf => Some(msgf_ladder::LadderType::Bpf),
+                    VcfType::Notch => Some(msgf_ladder::LadderType::Notch),
+                };
+                if let Some(filter_type) = filter_type {
+                    self.ladder.update_prm(&msgf_ladder::LadderParameter {
+                        filter_type,
+                        four_pole: self.prms.slope == VcfSlope::Db24,
+                        cutoff,
+                        resonance: (reso - 0.5)*LADDER_RESO,
+                        drive: self.prms.drive,
+                    });
+                }
+            }
+        }
+        self.coef_oct = oct;
+    }
+    fn filter(&mut self, x: f32, two_stage: bool) -> f32 {
+        if self.is_thru() {return x;}
+        match self.prms.model {
+            VcfModel::Biquad => {
+                let y = self.stage[0].filter(x);
+                if two_stage {self.stage[1].filter(y)} else {y}
+            }
+            VcfModel::Svf => {
+                let y = self.svf[0].filter(x);
+                if two_stage {self.svf[1].filter(y)} else {y}
+            }
+            VcfModel::Ladder => self.ladder.filter(x),
+        }
+    }
+}
+impl Engine for Vcf {
+    fn process_ac(&mut self, abuf: &mut msgf_afrm::AudioFrame, egbuf: &mut msgf_cfrm::CtrlFrame) {
+        //  egbuf: filter envelope 0.0-1.0
+        let two_stage = self.prms.slope == VcfSlope::Db24;
+        for i in 0..abuf.sample_number {
+            let tgt = self.base_oct + self.prms.eg_depth*egbuf.ctrl_for_audio(i);
+            self.crnt_oct += (tgt - self.crnt_oct)*SMOOTH_COEF;
+            if (self.crnt_oct - self.coef_oct).abs() > COEF_THRESHOLD {
+                self.set_coef(self.crnt_oct);
+            }
+            if let Some(x) = abuf.get_from_abuf(i) {
+                let y = self.filter(x, two_stage);
+                abuf.set_val(i, y);
+            }
+        }
+    }
+}
@@ -9,8 +9,8 @@
 //  Released under the MIT license
 //  https://opensource.org/licenses/mit-license.php
 //
-use crate::msgf_if;
 use crate::core::*;
+use crate::core::msgf_param::{ParamUnit, Smoothing};
 use crate::engine::msgf_gen;
 use crate::engine::msgf_gen::*;
 //---------------------------------------------------------
@@ -22,6 +22,43 @@ pub struct VocalParameter {
     pub fine_tune: f32,     //  [cent]
     pub lfo_depth: f32,     //  1.0 means +-1oct.
 }
+impl msgf_patch::PatchPrm for VocalParameter {
+    fn set_patch(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "coarse_tune" => self.coarse_tune = msgf_patch::parse_value(value)?,
+            "fine_tune" => self.fine_tune = msgf_patch::parse_value(value)?,
+            "lfo_depth" => self.lfo_depth = msgf_patch::parse_value(value)?,
+            _ => return Err(msgf_patch::unknown_key()),
+        }
+        Ok(())
+    }
+    fn write_patch(&self, prefix: &str, out: &mut String) {
+        msgf_patch::write_item(out, prefix, "coarse_tune", self.coarse_tune);
+        msgf_patch::write_item(out, prefix, "fine_tune", self.fine_tune);
+        msgf_patch::write_item(out, prefix, "lfo_depth", self.lfo_depth);
+    }
+}
+pub const VOCAL_PARAMS: [msgf_param::ParamDef; 3] = [
+    msgf_param::ParamDef {name: "coarse_tune", min: -24.0, max: 24.0, unit: ParamUnit::Semitone, smoothing: Smoothing::None},
+    msgf_param::ParamDef {name: "fine_tune", min: -100.0, max: 100.0, unit: ParamUnit::Cent, smoothing: Smoothing::Control},
+    msgf_param::ParamDef {name: "lfo_depth", min: 0.0, max: 1.0, unit: ParamUnit::Octave, smoothing: Smoothing::Control},
+];
+impl msgf_param::ParamPrm for VocalParameter {
+    fn param(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.coarse_tune as f32,
+            1 => self.fine_tune,
+            _ => self.lfo_depth,
+        }
+    }
+    fn set_param(&mut self, idx: usize, value: f32) {
+        match idx {
+            0 => self.coarse_tune = value as i32,
+            1 => self.fine_tune = value,
+            _ => self.lfo_depth = value,
+        }
+    }
+}
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
@@ -31,20 +68,27 @@ pub struct Vocal {
     base_pitch: f32,    //  [Hz]
     cnt_ratio: f32,     //  ratio of Hz
     next_phase: f32,    //  0.0 - 1.0
+    fs: f32,            //  sampling freq.
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl Vocal {
-    pub fn new(prms:&VocalParameter, note:u8, pmd:f32, cnt_pitch:f32) -> Vocal {
+    pub fn new(prms:&VocalParameter, note:u8, pmd:f32, cnt_pitch:f32, fs:f32) -> Vocal {
         Vocal {
             prms_variable: *prms,
             pmd,
             base_pitch: Vocal::calc_base_pitch(prms.coarse_tune, prms.fine_tune, note),
             cnt_ratio: Vocal::calc_cnt_pitch(cnt_pitch),
             next_phase: 0.0,
+            fs,
         }
     }
+    pub fn update_prm(&mut self, prms: &VocalParameter, note: u8) {
+        //  Parameter change while sounding
+        self.prms_variable = *prms;
+        self.change_note(note);
+    }
     pub fn change_pmd(&mut self, value:f32) {self.pmd = value;}
     pub fn change_note(&mut self, note:u8) {
         self.base_pitch = Vocal::calc_base_pitch(self.prms_variable.coarse_tune,
@@ -81,10 +125,8 @@ impl Vocal {
         //            -(x0*x1*x3*SIN_TABLE[phase_locate+3]/2.0) + (x0*x1*x2*SIN_TABLE[phase_locate+4]/6.0);
         //assert!(phase_locate < 258, "{},{},{},{}:{}->{}", x0,x1,x2,x3,phase_locate,y);
         let y0 = msgf_gen::VOCAL_TABLE[phase_locate+2];                    //  linear interpolation
-        let mut y = (msgf_gen::VOCAL_TABLE[phase_locate+3] - y0)*x1 + y0;  //
-        if y > 1.0 { y = 1.0 }
-        else if y < -1.0 { y = -1.0 }
-        y
+        let y = (msgf_gen::VOCAL_TABLE[phase_locate+3] - y0)*x1 + y0;  //
+        y.clamp(-1.0, 1.0)
     }
     pub fn calc_cnt_pitch(pitch: f32) -> f32 {    //  pitch : [cent]
         let mut pt: f32 = 1.0;
@@ -99,7 +141,7 @@ impl Vocal {
 }
 impl Engine for Vocal {
     fn process_ac(&mut self, abuf: &mut msgf_afrm::AudioFrame, lbuf: &mut msgf_cfrm::CtrlFrame) {
-        let delta_phase = self.base_pitch*self.cnt_ratio/msgf_if::SAMPLING_FREQ;
+        let delta_phase = self.base_pitch*self.cnt_ratio/self.fs;
         let mut phase = self.next_phase;
         for i in 0..abuf.sample_number {
             abuf.set_val(i, Vocal::gen_wave(phase));
@@ -11,30 +11,288 @@
 //  How to generate msgf.h
 //      cbindgen --config cbindgen.toml --crate msgf --output msgf.h --lang=c -q
 //      (-q: Report errors only )
-mod msgf_if;
+pub mod msgf_if;
 mod core;
 mod engine;
 mod app;
 
 #[no_mangle]
-pub extern "C" fn rust_msgf_new() -> *mut msgf_if::Msgf {
-    let mut ptr = Box::new(msgf_if::Msgf::new());
+pub extern "C" fn rust_msgf_new(sample_rate: f32, max_block_size: u32) -> *mut msgf_if::Msgf {
+    let mut ptr = Box::new(msgf_if::Msgf::new(sample_rate, max_block_size as usize));
     ptr.init();
     Box::into_raw(ptr)
 }
 #[no_mangle]
+pub extern "C" fn rust_set_sample_rate(rust_msgf: &mut msgf_if::Msgf, sample_rate: f32) {
+    rust_msgf.set_sample_rate(sample_rate);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_part_channel(rust_msgf: &mut msgf_if::Msgf, part_num: u32, rcv_ch: u8) {
+    rust_msgf.set_part_channel(part_num as usize, rcv_ch);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_part_key_range(rust_msgf: &mut msgf_if::Msgf, part_num: u32, low: u8, high: u8) {
+    rust_msgf.set_part_key_range(part_num as usize, low, high);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_part_vel_range(rust_msgf: &mut msgf_if::Msgf, part_num: u32, low: u8, high: u8) {
+    rust_msgf.set_part_vel_range(part_num as usize, low, high);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_part_zone_fade(rust_msgf: &mut msgf_if::Msgf, part_num: u32, key_width: u8, vel_width: u8) {
+    rust_msgf.set_part_zone_fade(part_num as usize, key_width, vel_width);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_part_transpose(rust_msgf: &mut msgf_if::Msgf, part_num: u32, transpose: i8) {
+    rust_msgf.set_part_transpose(part_num as usize, transpose);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_part_polyphony(rust_msgf: &mut msgf_if::Msgf, part_num: u32, max_voice: u32, steal: u8) {
+    rust_msgf.set_part_polyphony(part_num as usize, max_voice as usize, steal);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_part_mono_config(rust_msgf: &mut msgf_if::Msgf, part_num: u32, priority: u8, legato: bool) {
+    rust_msgf.set_part_mono_config(part_num as usize, priority, legato);
+}
+/// # Safety
+/// `text` must point to `size` bytes of a UTF-8 patch bank text.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_load_patch_bank(rust_msgf: &mut msgf_if::Msgf, text: *const u8, size: usize) -> bool {
+    let text = std::slice::from_raw_parts(text, size);
+    match std::str::from_utf8(text) {
+        Ok(text) => match rust_msgf.load_patch_bank(text) {
+            Ok(()) => true,
+            Err(_) => false,    //  the error is logged
+        },
+        Err(_) => false,
+    }
+}
+/// # Safety
+/// `buf` must point to `size` bytes, or be null when `size` is 0.
+/// Returns the length of the whole text; the text is written only if it fits in `buf`.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_save_patch_bank(rust_msgf: &msgf_if::Msgf, buf: *mut u8, size: usize) -> usize {
+    let text = rust_msgf.save_patch_bank();
+    if text.len() <= size {
+        std::ptr::copy_nonoverlapping(text.as_ptr(), buf, text.len());
+    }
+    text.len()
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_program_count(rust_msgf: &msgf_if::Msgf) -> u32 {
+    rust_msgf.program_count() as u32
+}
+/// # Safety
+/// `bank_msb`, `bank_lsb` and `program` must point to writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_program_info(rust_msgf: &msgf_if::Msgf, index: u32,
+  bank_msb: *mut u8, bank_lsb: *mut u8, program: *mut u8) -> bool {
+    match rust_msgf.program_info(index as usize) {
+        Some(info) => {
+            *bank_msb = info.bank_msb;
+            *bank_lsb = info.bank_lsb;
+            *program = info.program;
+            true
+        }
+        None => false,
+    }
+}
+/// # Safety
+/// `buf` must point to `size` bytes, or be null when `size` is 0.
+/// The name is truncated to `size`-1 bytes and terminated by NUL.
+/// Returns the length of the whole name (0: no such program).
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_program_name(rust_msgf: &msgf_if::Msgf, index: u32, buf: *mut u8, size: usize) -> usize {
+    match rust_msgf.program_info(index as usize) {
+        Some(info) => copy_str(info.name, buf, size),
+        None => 0,
+    }
+}
+unsafe fn copy_str(text: &str, buf: *mut u8, size: usize) -> usize {
+    if size > 0 {
+        let len = text.len().min(size-1);
+        std::ptr::copy_nonoverlapping(text.as_ptr(), buf, len);
+        *buf.add(len) = 0;
+    }
+    text.len()
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_param_count(rust_msgf: &msgf_if::Msgf, part_num: u32) -> u32 {
+    rust_msgf.param_count(part_num as usize) as u32
+}
+/// # Safety
+/// `min`, `max`, `default_value`, `unit` and `smoothing` must point to writable values.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_param_info(rust_msgf: &msgf_if::Msgf, part_num: u32, id: u32,
+  min: *mut f32, max: *mut f32, default_value: *mut f32, unit: *mut u8, smoothing: *mut u8) -> bool {
+    match rust_msgf.param_info(part_num as usize, id as usize) {
+        Some(info) => {
+            *min = info.min;
+            *max = info.max;
+            *default_value = info.default;
+            *unit = info.unit as u8;
+            *smoothing = info.smoothing as u8;
+            true
+        }
+        None => false,
+    }
+}
+/// # Safety
+/// `buf` must point to `size` bytes, or be null when `size` is 0.
+/// The path ("inst.engine.name") is truncated to `size`-1 bytes and terminated by NUL.
+/// Returns the length of the whole path (0: no such parameter).
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_param_path(rust_msgf: &msgf_if::Msgf, part_num: u32, id: u32, buf: *mut u8, size: usize) -> usize {
+    match rust_msgf.param_info(part_num as usize, id as usize) {
+        Some(info) => copy_str(&info.path(), buf, size),
+        None => 0,
+    }
+}
+/// # Safety
+/// `path` must be a NUL terminated string.
+/// Returns the parameter ID, or -1 when not found.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_find_param(rust_msgf: &msgf_if::Msgf, part_num: u32, path: *const std::os::raw::c_char) -> i32 {
+    let path = match std::ffi::CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => return -1,
+    };
+    rust_msgf.find_param(part_num as usize, path).map_or(-1, |id| id as i32)
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_param(rust_msgf: &mut msgf_if::Msgf, part_num: u32, id: u32, value: f32) -> bool {
+    rust_msgf.set_param(part_num as usize, id as usize, value)
+}
+/// # Safety
+/// `value` must point to a writable f32.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_get_param(rust_msgf: &msgf_if::Msgf, part_num: u32, id: u32, value: *mut f32) -> bool {
+    match rust_msgf.get_param(part_num as usize, id as usize) {
+        Some(vl) => {*value = vl; true}
+        None => false,
+    }
+}
+#[no_mangle]
 pub extern "C" fn rust_recieve_midi_message(rust_msgf: &mut msgf_if::Msgf, dt1: u8, dt2: u8, dt3: u8) {
     rust_msgf.recieve_midi_message(dt1, dt2, dt3);
 }
 #[no_mangle]
-pub extern "C" fn rust_process(rust_msgf: &mut msgf_if::Msgf, abuf_l: &mut [f32; msgf_if::MAX_BUFFER_SIZE], abuf_r: &mut [f32; msgf_if::MAX_BUFFER_SIZE],in_number_frames: u32) {
+pub extern "C" fn rust_recieve_midi_message_at(rust_msgf: &mut msgf_if::Msgf, dt1: u8, dt2: u8, dt3: u8, frame: u32) {
+    rust_msgf.recieve_midi_message_at(dt1, dt2, dt3, frame);
+}
+/// # Safety
+/// `bytes` must point to `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rust_recieve_midi_bytes(rust_msgf: &mut msgf_if::Msgf, bytes: *const u8, size: usize) {
+    rust_msgf.recieve_midi_bytes(std::slice::from_raw_parts(bytes, size));
+}
+/// # Safety
+/// `bytes` must point to `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rust_recieve_midi_bytes_at(rust_msgf: &mut msgf_if::Msgf, bytes: *const u8, size: usize, frame: u32) {
+    rust_msgf.recieve_midi_bytes_at(std::slice::from_raw_parts(bytes, size), frame);
+}
+/// # Safety
+/// `words` must point to `count` 32bit words.
+#[no_mangle]
+pub unsafe extern "C" fn rust_recieve_ump(rust_msgf: &mut msgf_if::Msgf, words: *const u32, count: usize) {
+    rust_msgf.recieve_ump(std::slice::from_raw_parts(words, count));
+}
+/// # Safety
+/// `words` must point to `count` 32bit words.
+#[no_mangle]
+pub unsafe extern "C" fn rust_recieve_ump_at(rust_msgf: &mut msgf_if::Msgf, words: *const u32, count: usize, frame: u32) {
+    rust_msgf.recieve_ump_at(std::slice::from_raw_parts(words, count), frame);
+}
+/// # Safety
+/// `abuf_l` and `abuf_r` must point to buffers of `in_number_frames` samples.
+#[no_mangle]
+pub unsafe extern "C" fn rust_process(rust_msgf: &mut msgf_if::Msgf, abuf_l: *mut f32, abuf_r: *mut f32, in_number_frames: u32) {
+    let abuf_l = std::slice::from_raw_parts_mut(abuf_l, in_number_frames as usize);
+    let abuf_r = std::slice::from_raw_parts_mut(abuf_r, in_number_frames as usize);
     rust_msgf.process(abuf_l, abuf_r, in_number_frames);
 }
+/// # Safety
+/// `data` must point to `size` bytes of a Standard MIDI File.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_load_smf(rust_msgf: &mut msgf_if::Msgf, data: *const u8, size: usize) -> bool {
+    let smf = std::slice::from_raw_parts(data, size);
+    rust_msgf.load_smf(smf).is_ok()
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_play(rust_msgf: &mut msgf_if::Msgf) {
+    rust_msgf.play();
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_stop(rust_msgf: &mut msgf_if::Msgf) {
+    rust_msgf.stop();
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_locate(rust_msgf: &mut msgf_if::Msgf, tick: u32) {
+    rust_msgf.locate(tick);
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_is_playing(rust_msgf: &msgf_if::Msgf) -> bool {
+    rust_msgf.is_playing()
+}
+/// # Safety
+/// `buf` must point to `size` bytes, or be null when `size` is 0.
+/// The message is truncated to `size`-1 bytes and terminated by NUL.
+/// Returns the length of the whole message (0: no message).
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_pop_log(buf: *mut u8, size: usize) -> usize {
+    let msg = match msgf_if::pop_log() {
+        Some(msg) => msg.text,
+        None => return 0,
+    };
+    if size > 0 {
+        let len = msg.len().min(size-1);
+        std::ptr::copy_nonoverlapping(msg.as_ptr(), buf, len);
+        *buf.add(len) = 0;
+    }
+    msg.len()
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_log_level(level: u8) {
+    msgf_if::set_log_level(msgf_if::LogLevel::from_u8(level));
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_set_log_categories(mask: u8) {
+    msgf_if::set_log_categories(mask);
+}
+//  level, category, NUL terminated message, user data
+pub type MsgfLogCallback = extern "C" fn(u8, u8, *const std::os::raw::c_char, *mut std::os::raw::c_void);
+struct UserData(*mut std::os::raw::c_void);
+//  the host is responsible for the user data in the thread calling rust_msgf_flush_log()
+unsafe impl Send for UserData {}
+/// # Safety
+/// `user` is passed to `callback` as is, and must be valid until the callback is replaced.
+/// `callback` is called from rust_msgf_flush_log(), not from the audio thread.
+#[no_mangle]
+pub unsafe extern "C" fn rust_msgf_set_log_callback(callback: Option<MsgfLogCallback>, user: *mut std::os::raw::c_void) {
+    let callback = match callback {
+        Some(cb) => cb,
+        None => {msgf_if::set_log_sink(None); return}
+    };
+    let user = UserData(user);
+    msgf_if::set_log_sink(Some(Box::new(move |level, category, msg: &str| {
+        //  interior NUL ends the message
+        let mut text: Vec<u8> = msg.bytes().take_while(|b| *b != 0).collect();
+        text.push(0);
+        callback(level as u8, category as u8, text.as_ptr() as *const std::os::raw::c_char, user.0);
+    })));
+}
+#[no_mangle]
+pub extern "C" fn rust_msgf_flush_log() -> usize {
+    msgf_if::flush_log()
+}
 #[no_mangle]
 pub extern "C" fn say_hello() {
     println!("Hello, World!");
 }
+/// # Safety
+/// `rust_msgf` must be a pointer returned by `rust_msgf_new()`.
 #[no_mangle]
-pub extern "C" fn rust_msgf_destroy(rust_msgf: *mut msgf_if::Msgf) {
-    unsafe { Box::from_raw(rust_msgf) };
+pub unsafe extern "C" fn rust_msgf_destroy(rust_msgf: *mut msgf_if::Msgf) {
+    drop(Box::from_raw(rust_msgf));
 }
\ No newline at end of file
@@ -10,27 +10,135 @@
 //
 use crate::core::*;
 use crate::engine::*;
+use crate::app;
 use crate::engine::msgf_gen::*;
 use crate::core::msgf_disp::MsgfDisplay;
+pub use crate::core::msgf_log::{LogLevel, LogCategory, LogSink, LOG_CATEGORY_ALL};
+pub use crate::core::msgf_param::{ParamInfo, ParamUnit, Smoothing};
 //---------------------------------------------------------
 //		Constants
 //---------------------------------------------------------
 //  configuration
 pub const REV_NUM: &str = "rev.0-0-9";
-pub const MAX_PART_NUM: usize = 10;
-pub const MAX_BUFFER_SIZE: usize = 1024;
-pub const SAMPLING_FREQ: f32 = 44100.0;
+pub const MAX_PART_NUM: usize = 16;
+pub const RCV_CH_OMNI: u8 = 0x10;       //  receive channel of a part: 0x00-0x0f, omni or off
+pub const RCV_CH_OFF: u8 = 0x7f;
+pub const VOICE_STEAL_OLDEST: u8 = 0;   //  voice steal policy of a part
+pub const VOICE_STEAL_QUIETEST: u8 = 1;
+pub const VOICE_STEAL_SAME_NOTE: u8 = 2;
+pub const VOICE_STEAL_RELEASE_FIRST: u8 = 3;
+pub const NOTE_PRIORITY_LAST: u8 = 0;   //  note priority of mono mode
+pub const NOTE_PRIORITY_LOW: u8 = 1;
+pub const NOTE_PRIORITY_HIGH: u8 = 2;
+pub const LOG_LEVEL_OFF: u8 = LogLevel::Off as u8;    //  log level (less is quieter)
+pub const LOG_LEVEL_ERROR: u8 = LogLevel::Error as u8;
+pub const LOG_LEVEL_WARN: u8 = LogLevel::Warn as u8;
+pub const LOG_LEVEL_INFO: u8 = LogLevel::Info as u8;
+pub const LOG_LEVEL_DEBUG: u8 = LogLevel::Debug as u8;
+pub const LOG_CATEGORY_SYSTEM: u8 = LogCategory::System as u8;    //  log category (bit mask)
+pub const LOG_CATEGORY_MIDI: u8 = LogCategory::Midi as u8;
+pub const LOG_CATEGORY_VOICE: u8 = LogCategory::Voice as u8;
+pub const LOG_CATEGORY_PARAM: u8 = LogCategory::Param as u8;
+pub const PARAM_UNIT_NONE: u8 = ParamUnit::None as u8;    //  unit of a parameter
+pub const PARAM_UNIT_SEMITONE: u8 = ParamUnit::Semitone as u8;
+pub const PARAM_UNIT_CENT: u8 = ParamUnit::Cent as u8;
+pub const PARAM_UNIT_HZ: u8 = ParamUnit::Hz as u8;
+pub const PARAM_UNIT_SEC: u8 = ParamUnit::Sec as u8;
+pub const PARAM_UNIT_RATE: u8 = ParamUnit::Rate as u8;
+pub const PARAM_UNIT_LEVEL: u8 = ParamUnit::Level as u8;
+pub const PARAM_UNIT_OCTAVE: u8 = ParamUnit::Octave as u8;
+pub const PARAM_UNIT_CONTROL_FRAME: u8 = ParamUnit::ControlFrame as u8;
+pub const PARAM_UNIT_INDEX: u8 = ParamUnit::Index as u8;
+pub const PARAM_SMOOTHING_NONE: u8 = Smoothing::None as u8;  //  smoothing of a parameter
+pub const PARAM_SMOOTHING_CONTROL: u8 = Smoothing::Control as u8;
+pub const PARAM_SMOOTHING_AUDIO: u8 = Smoothing::Audio as u8;
+pub const MAX_BUFFER_SIZE: usize = 1024;     //  default of max block size
+pub const DEFAULT_SAMPLING_FREQ: f32 = 44100.0; //  Synth. Parameters are tuned for this
 pub const PI: f32 = std::f32::consts::PI;
 pub const AUDIO_FRAME_PER_CONTROL: usize = 128;
 pub const DAMP_LIMIT_DEPTH: f32 = 0.0001;
 pub const TOTAL_EFF_DLY_TIME_L: f32 = 0.25;
 pub const TOTAL_EFF_DLY_TIME_R: f32 = 0.27;
 pub const TOTAL_EFF_ATT_RATE: f32 = 0.3;
+//  internal status of MidiEvent
+const STATUS_GM_SYSTEM_ON: u8 = 0xf0;
+const MSG_BUF_CAPACITY: usize = 1024;   //  pending MIDI events without reallocation
+//---------------------------------------------------------
+//		Runtime Configuration
+//---------------------------------------------------------
+//  Set by Msgf::new()/set_sample_rate() for each instance,
+//  and passed down when parts, instruments, voices and engines are built.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AudioConfig {
+    pub sampling_freq: f32,
+    pub max_block_size: usize,
+}
+impl AudioConfig {
+    pub fn new(sample_rate: f32, max_block_size: usize) -> Self {
+        Self {
+            sampling_freq: if sample_rate > 0.0 {sample_rate} else {DEFAULT_SAMPLING_FREQ},
+            max_block_size: if max_block_size > 0 {max_block_size} else {MAX_BUFFER_SIZE},
+        }
+    }
+    pub fn sampling_ratio(&self) -> f32 {  //  sampling freq. / DEFAULT_SAMPLING_FREQ
+        self.sampling_freq/DEFAULT_SAMPLING_FREQ
+    }
+}
+impl Default for AudioConfig {
+    fn default() -> Self {Self::new(DEFAULT_SAMPLING_FREQ, MAX_BUFFER_SIZE)}
+}
+//---------------------------------------------------------
+//		Log
+//---------------------------------------------------------
+//  Silent by default in release builds. Shared by all instances in the process.
+pub fn set_log_level(level: LogLevel) {msgf_log::set_level(level)}
+pub fn log_level() -> LogLevel {msgf_log::level()}
+pub fn set_log_categories(mask: u8) {msgf_log::set_category_mask(mask)}  //  OR of LogCategory
+pub fn set_log_sink(sink: Option<LogSink>) {msgf_log::set_sink(sink)}
+//  Pass queued messages to the sink (call from a non real-time thread)
+pub fn flush_log() -> usize {msgf_log::flush()}
+//  Log messages queued by the audio thread (call from a non real-time thread)
+pub fn pop_log() -> Option<LogMessage> {
+    let dropped = msgf_log::take_dropped();
+    if dropped > 0 {
+        return Some(LogMessage {
+            level: LogLevel::Warn,
+            category: LogCategory::System,
+            text: format!("({} log messages dropped)", dropped),
+        });
+    }
+    msgf_log::pop().map(|line| LogMessage {
+        level: line.level,
+        category: line.category,
+        text: String::from(line.as_str()),
+    })
+}
 //---------------------------------------------------------
 //		Definition
 //---------------------------------------------------------
+#[derive(Copy, Clone)]
+struct MidiEvent {
+    frame: u32,     //  frame offset from the top of the next audio buffer
+    status: u8,
+    ch: u8,         //  MIDI channel
+    dt2: u8,
+    dt3: u8,
+    data: u32,      //  MIDI 2.0: 32bit data
+    midi2: bool,    //  status is an opcode of MIDI 2.0 Channel Voice
+}
+pub struct LogMessage {
+    pub level: LogLevel,
+    pub category: LogCategory,
+    pub text: String,
+}
+pub struct ProgramInfo {
+    pub bank_msb: u8,   //  CC#0
+    pub bank_lsb: u8,   //  CC#32
+    pub program: u8,
+    pub name: &'static str,
+}
 pub struct Msgf {
-    msg_buf: Vec<(u8,usize,u8,u8)>,
+    msg_buf: Vec<MidiEvent>,    //  sorted by frame
     part: Vec<msgf_part::Part>,
     audio_buffer_l: msgf_afrm::AudioFrame,
     audio_buffer_r: msgf_afrm::AudioFrame,
@@ -39,84 +147,371 @@ pub struct Msgf {
     audio_buffer_total_effect_l: msgf_afrm::AudioFrame,
     audio_buffer_total_effect_r: msgf_afrm::AudioFrame,
     delay: msgf_sd_delay::SdDelay,
+    seq: msgf_seq::Sequencer,
+    parser: msgf_midi::MidiParser,
+    ump_parser: msgf_ump::UmpParser,
     in_number_frames: u32,
+    cfg: AudioConfig,
+    bank: std::sync::Arc<app::PatchBank>,  //  snapshot of the patch bank shared by all instances
+    bank_generation: usize,
 }
 //---------------------------------------------------------
 //		Implements
 //---------------------------------------------------------
 impl msgf_disp::MsgfDisplay for Msgf {}
 impl Msgf {
-    pub fn new() -> Self {
-        let dprm = msgf_delay::DelayParameter {
-            l_time: TOTAL_EFF_DLY_TIME_L,   //  0.0 - 1.0 [sec]
-            r_time: TOTAL_EFF_DLY_TIME_R,   //  0.0 - 1.0 [sec]
-            att_ratio: TOTAL_EFF_ATT_RATE,
-        };        
+    pub fn new(sample_rate: f32, max_block_size: usize) -> Self {
+        let cfg = AudioConfig::new(sample_rate, max_block_size);
+        msgf_osc::Osc::init_tables();
+        let max_block_size = cfg.max_block_size;
         Self {
-            msg_buf: Vec::new(),
+            msg_buf: Vec::with_capacity(MSG_BUF_CAPACITY),
             part: Vec::new(),
-            audio_buffer_l: msgf_afrm::AudioFrame::new(0,MAX_BUFFER_SIZE),
-            audio_buffer_r: msgf_afrm::AudioFrame::new(0,MAX_BUFFER_SIZE),
-            audio_buffer_send_effect_l: msgf_afrm::AudioFrame::new(0,MAX_BUFFER_SIZE),
-            audio_buffer_send_effect_r: msgf_afrm::AudioFrame::new(0,MAX_BUFFER_SIZE),
-            audio_buffer_total_effect_l: msgf_afrm::AudioFrame::new(0,MAX_BUFFER_SIZE),
-            audio_buffer_total_effect_r: msgf_afrm::AudioFrame::new(0,MAX_BUFFER_SIZE),
-            delay: msgf_sd_delay::SdDelay::new(&dprm),
+            audio_buffer_l: msgf_afrm::AudioFrame::new(0,max_block_size),
+            audio_buffer_r: msgf_afrm::AudioFrame::new(0,max_block_size),
+            audio_buffer_send_effect_l: msgf_afrm::AudioFrame::new(0,max_block_size),
+            audio_buffer_send_effect_r: msgf_afrm::AudioFrame::new(0,max_block_size),
+            audio_buffer_total_effect_l: msgf_afrm::AudioFrame::new(0,max_block_size),
+            audio_buffer_total_effect_r: msgf_afrm::AudioFrame::new(0,max_block_size),
+            delay: Self::new_total_effect(&cfg),
+            seq: msgf_seq::Sequencer::new(cfg.sampling_freq),
+            parser: msgf_midi::MidiParser::new(),
+            ump_parser: msgf_ump::UmpParser::new(),
             in_number_frames: 0,
+            cfg,
+            bank_generation: app::patch_bank_generation(),
+            bank: app::patch_bank(),
         }
     }
+    pub fn config(&self) -> AudioConfig {self.cfg}
+    fn new_total_effect(cfg: &AudioConfig) -> msgf_sd_delay::SdDelay {
+        let dprm = msgf_delay::DelayParameter {
+            l_time: TOTAL_EFF_DLY_TIME_L,   //  0.0 - 1.0 [sec]
+            r_time: TOTAL_EFF_DLY_TIME_R,   //  0.0 - 1.0 [sec]
+            att_ratio: TOTAL_EFF_ATT_RATE,
+        };
+        msgf_sd_delay::SdDelay::new(&dprm, cfg.sampling_freq)
+    }
     pub fn init(&mut self) {    // call this fn just after new()
-        for _ in 0..MAX_PART_NUM {
-            self.part.push(msgf_part::Part::new());
+        for i in 0..MAX_PART_NUM {
+            //  Part n receives MIDI ch.n by default (parts over 16 are off)
+            let rcv_ch = if i < 16 {i as u8} else {RCV_CH_OFF};
+            self.part.push(msgf_part::Part::new(rcv_ch, &self.bank, &self.cfg));
         };
-        self.print_str(REV_NUM);
+        self.print_str(msgf_log::LogCategory::System, REV_NUM);
+    }
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        //  Don't call this during process().
+        //  All sounding notes are stopped, and every engine is rebuilt.
+        self.cfg = AudioConfig::new(sample_rate, self.cfg.max_block_size);
+        self.delay = Self::new_total_effect(&self.cfg);
+        self.seq.set_sampling_freq(self.cfg.sampling_freq);
+        let (bank, cfg) = (&self.bank, self.cfg);
+        self.part.iter_mut().for_each(|pt| pt.reinit(bank, &cfg));
+        self.print_prm(msgf_log::LogCategory::System, "Sampling Frequency: ", self.cfg.sampling_freq);
+    }
+    pub fn set_part_mute(&mut self, part_num: usize, mute: bool) {
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_mute(mute);
+        }
+    }
+    pub fn set_part_channel(&mut self, part_num: usize, rcv_ch: u8) {
+        //  rcv_ch: 0x00-0x0f, RCV_CH_OMNI or RCV_CH_OFF
+        //  Several parts on one channel are layered
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_rcv_ch(rcv_ch);
+        }
+    }
+    pub fn set_part_key_range(&mut self, part_num: usize, low: u8, high: u8) {
+        //  note on out of the range is ignored by the part (for keyboard split)
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_key_range(low, high);
+        }
+    }
+    pub fn set_part_vel_range(&mut self, part_num: usize, low: u8, high: u8) {
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_vel_range(low, high);
+        }
+    }
+    pub fn set_part_zone_fade(&mut self, part_num: usize, key_width: u8, vel_width: u8) {
+        //  velocity of note on is lowered linearly in the width inside
+        //  the key/velocity range edges, to crossfade two split parts
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_zone_fade(key_width, vel_width);
+        }
+    }
+    pub fn set_part_transpose(&mut self, part_num: usize, transpose: i8) {
+        //  transpose: [semitone], notes are transposed after the range check
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_transpose(transpose);
+        }
+    }
+    pub fn set_part_polyphony(&mut self, part_num: usize, max_voice: usize, steal: u8) {
+        //  max_voice: 1-32, steal: VOICE_STEAL_*
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_polyphony(max_voice, msgf_voice::StealPolicy::from_u8(steal));
+        }
+    }
+    pub fn set_part_mono_config(&mut self, part_num: usize, priority: u8, legato: bool) {
+        //  priority: NOTE_PRIORITY_*, legato: false means envelope retrigger
+        //  Mono mode itself is switched by CC#126/127
+        if let Some(pt) = self.part.get_mut(part_num) {
+            pt.set_mono_config(msgf_note_stack::NotePriority::from_u8(priority), legato);
+        }
+    }
+    //  Patch Bank: shared by all Msgf instances in the process
+    pub fn load_patch_bank(&mut self, text: &str) -> Result<(), String> {
+        if let Err(e) = app::load_patch_bank(text) {
+            self.log(msgf_log::LogLevel::Error, msgf_log::LogCategory::Param,
+                format_args!("Patch Bank: {}", e));
+            return Err(e);
+        }
+        self.print_str(msgf_log::LogCategory::Param, "Patch Bank loaded");
+        //  current programs are rebuilt by the new tones
+        self.bank_generation = app::patch_bank_generation();
+        self.bank = app::patch_bank();
+        let (bank, cfg) = (&self.bank, self.cfg);
+        self.part.iter_mut().for_each(|pt| pt.reinit(bank, &cfg));
+        Ok(())
+    }
+    pub fn save_patch_bank(&self) -> String {
+        app::save_patch_bank()
+    }
+    //  Registered programs for host UIs, in order of bank and program number
+    //  (user tone banks change by loading a patch bank)
+    pub fn program_count(&self) -> usize {
+        app::patch_bank().registry().programs().len()
+    }
+    pub fn program_info(&self, index: usize) -> Option<ProgramInfo> {
+        app::patch_bank().registry().programs().get(index).map(|prg| ProgramInfo {
+            bank_msb: prg.bank_msb(), bank_lsb: prg.bank_lsb(), program: prg.number, name: prg.name})
+    }
+    //  Parameter Tree: part -> instrument -> engine -> parameter
+    //  IDs belong to the instrument of the part, and change by Program Change.
+    pub fn param_count(&self, part_num: usize) -> usize {
+        self.part.get(part_num).map_or(0, |pt| pt.param_count())
+    }
+    pub fn param_info(&self, part_num: usize, id: usize) -> Option<ParamInfo> {
+        self.part.get(part_num)?.param_info(id)
+    }
+    pub fn find_param(&self, part_num: usize, path: &str) -> Option<usize> {
+        //  path: "engine.name" e.g. "lfo.freq", or ParamInfo::path() "va.lfo.freq"
+        self.part.get(part_num)?.find_param(path)
+    }
+    pub fn get_param(&self, part_num: usize, id: usize) -> Option<f32> {
+        self.part.get(part_num)?.get_param(id)
+    }
+    pub fn set_param(&mut self, part_num: usize, id: usize, value: f32) -> bool {
+        //  value is clamped to the range, and smoothed in the next process()
+        self.part.get_mut(part_num).is_some_and(|pt| pt.set_param(id, value))
     }
     pub fn recieve_midi_message(&mut self, dt1: u8, dt2: u8, dt3: u8) {
-        let ch: usize = (dt1 & 0x0f).into();
+        self.recieve_midi_message_at(dt1, dt2, dt3, 0);
+    }
+    pub fn recieve_midi_message_at(&mut self, dt1: u8, dt2: u8, dt3: u8, frame: u32) {
+        //  frame: offset in the next audio buffer the message takes effect at.
+        //  It may exceed the buffer size, then it is carried to later buffers.
+        Self::push_msg(&mut self.msg_buf, dt1, dt2, dt3, frame);
+    }
+    pub fn recieve_midi_bytes(&mut self, bytes: &[u8]) {
+        self.recieve_midi_bytes_at(bytes, 0);
+    }
+    pub fn recieve_midi_bytes_at(&mut self, bytes: &[u8], frame: u32) {
+        //  bytes: any part of a MIDI 1.0 byte stream (serial/USB-MIDI)
+        //  Complete messages in it take effect at frame.
+        let msg_buf = &mut self.msg_buf;
+        self.parser.parse(bytes, |msg| match msg {
+            msgf_midi::MidiMessage::Channel(dt1, dt2, dt3) =>
+                Self::push_msg(msg_buf, dt1, dt2, dt3, frame),
+            msgf_midi::MidiMessage::SysEx(sysex) => {
+                //  Universal Non-Real Time: GM/GM2 System On
+                if let [0xf0, 0x7e, _, 0x09, 0x01 | 0x03, 0xf7] = sysex {
+                    Self::insert_msg(msg_buf, MidiEvent {
+                        frame, status: STATUS_GM_SYSTEM_ON, ch: 0, dt2: 0, dt3: 0, data: 0, midi2: false});
+                }
+            }
+            msgf_midi::MidiMessage::RealTime(0xff) =>   //  System Reset
+                Self::insert_msg(msg_buf, MidiEvent {
+                    frame, status: STATUS_GM_SYSTEM_ON, ch: 0, dt2: 0, dt3: 0, data: 0, midi2: false}),
+            msgf_midi::MidiMessage::RealTime(_) => {}
+        });
+    }
+    pub fn recieve_ump(&mut self, words: &[u32]) {
+        self.recieve_ump_at(words, 0);
+    }
+    pub fn recieve_ump_at(&mut self, words: &[u32], frame: u32) {
+        //  words: any part of Universal MIDI Packets (MIDI 1.0/2.0 Channel Voice)
+        //  Complete packets in it take effect at frame.
+        let msg_buf = &mut self.msg_buf;
+        self.ump_parser.parse(words, |msg| match msg {
+            msgf_ump::UmpMessage::Midi1(dt1, dt2, dt3) =>
+                Self::push_msg(msg_buf, dt1, dt2, dt3, frame),
+            msgf_ump::UmpMessage::Midi2 {opcode, ch, index, extra, data} =>
+                Self::insert_msg(msg_buf, MidiEvent {
+                    frame, status: opcode, ch, dt2: index, dt3: extra, data, midi2: true}),
+            msgf_ump::UmpMessage::SysEx(sysex) => {
+                //  Universal Non-Real Time: GM/GM2 System On
+                if let [0x7e, _, 0x09, 0x01 | 0x03] = sysex {
+                    Self::insert_msg(msg_buf, MidiEvent {
+                        frame, status: STATUS_GM_SYSTEM_ON, ch: 0, dt2: 0, dt3: 0, data: 0, midi2: false});
+                }
+            }
+            msgf_ump::UmpMessage::System(0xff) =>   //  System Reset
+                Self::insert_msg(msg_buf, MidiEvent {
+                    frame, status: STATUS_GM_SYSTEM_ON, ch: 0, dt2: 0, dt3: 0, data: 0, midi2: false}),
+            msgf_ump::UmpMessage::System(_) => {}
+        });
+    }
+    fn push_msg(msg_buf: &mut Vec<MidiEvent>, dt1: u8, dt2: u8, dt3: u8, frame: u32) {
+        let ch = dt1 & 0x0f;
         let status = dt1 & 0xf0;
 
-        if ch >= MAX_PART_NUM {
+        if !(0x80..0xf0).contains(&dt1) {
             return;
         };
 
-        let msg: (u8,usize,u8,u8) = (status, ch, dt2, dt3);
-        self.msg_buf.push(msg);
-    }
-    fn parse_msg(&mut self) {
-        if self.msg_buf.len() == 0 { return }
-        let (status, ch, dt2, dt3) = self.msg_buf.remove(0);
-        let pt = &mut self.part[ch];
-        match status {
-            0x80 => pt.note_off(dt2, dt3),
-            0x90 => if dt3 == 0 { pt.note_off(dt2, dt3);} else { pt.note_on(dt2, dt3);},
-            0xa0 => pt.per_note_after(dt2, dt3),
-            0xb0 => pt.control_change(dt2, dt3),
-            0xc0 => pt.program_change(dt2),
-            0xe0 => {
-                let mut bend: i16 = dt2.into();
-                bend += dt3 as i16*128;
-                bend -= 8192;
-                pt.pitch_bend(bend);
-            }
-            _ => {}
-        };
+        Self::insert_msg(msg_buf, MidiEvent {frame, status, ch, dt2, dt3, data: 0, midi2: false});
+    }
+    fn insert_msg(msg_buf: &mut Vec<MidiEvent>, msg: MidiEvent) {
+        let frame = msg.frame;
+        //  keep the order of arrival among messages of the same frame
+        let idx = msg_buf.partition_point(|ev| ev.frame <= frame);
+        msg_buf.insert(idx, msg);
+    }
+    //  SMF Player
+    pub fn load_smf(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        let smf = msgf_smf::Smf::parse(data)?;
+        let msg_buf = &mut self.msg_buf;
+        self.seq.load(smf, |dt1, dt2, dt3| Self::push_msg(msg_buf, dt1, dt2, dt3, 0));
+        Ok(())
+    }
+    pub fn play(&mut self) {self.seq.play();}
+    pub fn stop(&mut self) {
+        let msg_buf = &mut self.msg_buf;
+        self.seq.stop(|dt1, dt2, dt3| Self::push_msg(msg_buf, dt1, dt2, dt3, 0));
+    }
+    pub fn locate(&mut self, tick: u32) {
+        let msg_buf = &mut self.msg_buf;
+        self.seq.locate(tick, |dt1, dt2, dt3| Self::push_msg(msg_buf, dt1, dt2, dt3, 0));
+    }
+    pub fn is_playing(&self) -> bool {self.seq.is_playing()}
+    fn parse_msg(&mut self, msg: MidiEvent) {
+        let (status, ch, dt2, dt3) = (msg.status, msg.ch, msg.dt2, msg.dt3);
+        if status == STATUS_GM_SYSTEM_ON {
+            let bank = &self.bank;
+            self.part.iter_mut().for_each(|pt| pt.reset(bank));
+            return;
+        }
+        if msg.midi2 {
+            self.parse_msg2(msg);
+            return;
+        }
+        for pt in self.part.iter_mut().filter(|pt| pt.receives(ch)) {
+            match status {
+                0x80 => pt.note_off(dt2, dt3),
+                0x90 => if dt3 == 0 { pt.note_off(dt2, dt3);} else { pt.note_on(dt2, dt3);},
+                0xa0 => pt.per_note_after(dt2, dt3),
+                0xb0 => pt.control_change(dt2, dt3),
+                0xc0 => pt.program_change(&self.bank, dt2),
+                0xd0 => pt.channel_after(dt2),
+                0xe0 => {
+                    let mut bend: i16 = dt2.into();
+                    bend += dt3 as i16*128;
+                    bend -= 8192;
+                    pt.pitch_bend(bend);
+                }
+                _ => {}
+            };
+        }
+    }
+    fn parse_msg2(&mut self, msg: MidiEvent) {
+        //  MIDI 2.0 Channel Voice
+        use msgf_ump::*;
+        let (ch, dt2, dt3, data) = (msg.ch, msg.dt2, msg.dt3, msg.data);
+        for pt in self.part.iter_mut().filter(|pt| pt.receives(ch)) {
+            match msg.status {
+                OP_NOTE_OFF => pt.note_off(dt2, (data >> 25) as u8),
+                OP_NOTE_ON => pt.note_on_hr(dt2, (data >> 16) as u16, dt3, data as u16),
+                OP_POLY_PRESSURE => pt.per_note_after_hr(dt2, data),
+                OP_CONTROL_CHANGE => pt.control_change_hr(dt2, data),
+                OP_PROGRAM_CHANGE => {
+                    if dt3 & PROGRAM_BANK_VALID != 0 {
+                        pt.control_change(0, ((data >> 8) & 0x7f) as u8);
+                        pt.control_change(32, (data & 0x7f) as u8);
+                    }
+                    pt.program_change(&self.bank, ((data >> 24) & 0x7f) as u8);
+                }
+                OP_CHANNEL_PRESSURE => pt.channel_after_hr(data),
+                OP_PITCH_BEND => pt.pitch_bend_hr(data),
+                OP_PER_NOTE_PB => pt.per_note_pitch_bend(dt2, data),
+                OP_PER_NOTE_RCC => pt.per_note_rcc(dt2, dt3, data),
+                OP_PER_NOTE_MANAGE => pt.per_note_manage(dt2, dt3),
+                OP_RPN => pt.rpn_hr(dt2, dt3 & 0x7f, data),
+                OP_NRPN => pt.nrpn_hr(dt2, dt3 & 0x7f, data, false),
+                OP_RELATIVE_NRPN => pt.nrpn_hr(dt2, dt3 & 0x7f, data, true),
+                _ => {}     //  Assignable Per-Note Controller, Relative RPN
+            };
+        }
     }
     pub fn process(&mut self,
-      abuf_l: &mut [f32; MAX_BUFFER_SIZE],
-      abuf_r: &mut [f32; MAX_BUFFER_SIZE],
+      abuf_l: &mut [f32],
+      abuf_r: &mut [f32],
       in_number_frames: u32) {
-        self.parse_msg();   // MIDI message
         if self.in_number_frames != in_number_frames {
-            self.print_prm("Audio Buffer: ", in_number_frames);
+            self.print_prm(msgf_log::LogCategory::System, "Audio Buffer: ", in_number_frames);
             self.in_number_frames = in_number_frames;
         }
-        self.audio_buffer_l.set_sample_number(in_number_frames as usize);
-        self.audio_buffer_r.set_sample_number(in_number_frames as usize);
+        //  a patch bank loaded by another instance, taken without lock
+        let generation = app::patch_bank_generation();
+        if self.bank_generation != generation {
+            self.bank_generation = generation;
+            self.bank = app::patch_bank();
+        }
+        //  SMF Player
+        let msg_buf = &mut self.msg_buf;
+        self.seq.process(in_number_frames,
+            |dt1, dt2, dt3, frame| Self::push_msg(msg_buf, dt1, dt2, dt3, frame));
+
+        //  Split the buffer at the frame of each MIDI message,
+        //  and into blocks not longer than the max block size
+        //  (AUDIO_FRAME_PER_CONTROL while parameters are in smoothing)
+        let total_frames = in_number_frames as usize;
+        let mut start: usize = 0;
+        let mut msg_cnt: usize = 0;
+        while start < total_frames {
+            while msg_cnt < self.msg_buf.len() && (self.msg_buf[msg_cnt].frame as usize) <= start {
+                let msg = self.msg_buf[msg_cnt];
+                self.parse_msg(msg);
+                msg_cnt += 1;
+            }
+            let mut end = total_frames.min(start + self.cfg.max_block_size);
+            if let Some(next_msg) = self.msg_buf.get(msg_cnt) {
+                end = end.min(next_msg.frame as usize);
+            }
+            if self.part.iter().any(|pt| pt.param_ramping()) {
+                //  smoothed parameters are updated every control frame
+                end = end.min(start + AUDIO_FRAME_PER_CONTROL);
+            }
+            self.render(abuf_l, abuf_r, start, end-start);
+            start = end;
+        }
+        //  Messages for later buffers
+        self.msg_buf.drain(..msg_cnt);
+        self.msg_buf.iter_mut().for_each(|msg| msg.frame -= in_number_frames);
+    }
+    fn render(&mut self,
+      abuf_l: &mut [f32],
+      abuf_r: &mut [f32],
+      ofs: usize,
+      in_number_frames: usize) {
+        //  render in_number_frames samples into abuf_l/r from ofs
+        self.audio_buffer_l.set_sample_number(in_number_frames);
+        self.audio_buffer_r.set_sample_number(in_number_frames);
         // init effect buffer
-        self.audio_buffer_send_effect_l.set_sample_number(in_number_frames as usize);
-        self.audio_buffer_send_effect_r.set_sample_number(in_number_frames as usize);
-        self.audio_buffer_total_effect_l.set_sample_number(in_number_frames as usize);
-        self.audio_buffer_total_effect_r.set_sample_number(in_number_frames as usize);
+        self.audio_buffer_send_effect_l.set_sample_number(in_number_frames);
+        self.audio_buffer_send_effect_r.set_sample_number(in_number_frames);
+        self.audio_buffer_total_effect_l.set_sample_number(in_number_frames);
+        self.audio_buffer_total_effect_r.set_sample_number(in_number_frames);
         if MAX_PART_NUM >= 1 {
             //  Dry Sound:      Part 1 は copy
             //  Total Effect:   total_effect 用のバッファに直接書き込み
@@ -125,9 +520,9 @@ impl Msgf {
                 &mut self.audio_buffer_r,
                 &mut self.audio_buffer_total_effect_l,
                 &mut self.audio_buffer_total_effect_r,
-                in_number_frames as usize);
-            self.audio_buffer_l.copy_to_sysbuf(abuf_l);  // L
-            self.audio_buffer_r.copy_to_sysbuf(abuf_r);  // R
+                in_number_frames);
+            self.audio_buffer_l.copy_to_sysbuf(abuf_l, ofs);  // L
+            self.audio_buffer_r.copy_to_sysbuf(abuf_r, ofs);  // R
         }
         for i in 1..MAX_PART_NUM {
             //  Dry Sound:      Part 2 以降は add, 
@@ -137,19 +532,19 @@ impl Msgf {
                 &mut self.audio_buffer_r,
                 &mut self.audio_buffer_send_effect_l,
                 &mut self.audio_buffer_send_effect_r,
-                in_number_frames as usize);
+                in_number_frames);
             //  Dry Sound を Sysbuf に足し込む
-            self.audio_buffer_l.add_to_sysbuf(abuf_l);  // L
-            self.audio_buffer_r.add_to_sysbuf(abuf_r);  // R
+            self.audio_buffer_l.add_to_sysbuf(abuf_l, ofs);  // L
+            self.audio_buffer_r.add_to_sysbuf(abuf_r, ofs);  // R
             //  Send を足し合わせる  in:send_effect -> out:total_effect
-            self.audio_buffer_total_effect_l.mix_and_check_no_sound(&mut self.audio_buffer_send_effect_l);  // L
-            self.audio_buffer_total_effect_r.mix_and_check_no_sound(&mut self.audio_buffer_send_effect_r);  // R
+            self.audio_buffer_total_effect_l.mix_and_check_no_sound(&self.audio_buffer_send_effect_l);  // L
+            self.audio_buffer_total_effect_r.mix_and_check_no_sound(&self.audio_buffer_send_effect_r);  // R
         };
         //  Total Effect をかける in:total_effect -> out:send_effect
         self.delay.process_as2([&mut self.audio_buffer_total_effect_l, &mut self.audio_buffer_total_effect_r],
                                [&mut self.audio_buffer_send_effect_l, &mut self.audio_buffer_send_effect_r]);
         //  Total Effect を sysbuf に足す
-        self.audio_buffer_send_effect_l.add_to_sysbuf(abuf_l);  // L
-        self.audio_buffer_send_effect_r.add_to_sysbuf(abuf_r);  // R
+        self.audio_buffer_send_effect_l.add_to_sysbuf(abuf_l, ofs);  // L
+        self.audio_buffer_send_effect_r.add_to_sysbuf(abuf_r, ofs);  // R
     }
 }
@@ -0,0 +1,51 @@
+//
+//  block_size.rs
+//	Musical Sound Generator Framework
+//      Block Size Independence Test
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Fails if the envelopes depend on how the audio buffer is split.
+//
+use msgf::msgf_if;
+
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+const TOTAL_FRAMES: usize = 44100;
+const NOTE_ON_FRAME: u32 = 1000;
+const NOTE_OFF_FRAME: u32 = 20000;
+fn render(program: u8, block_size: usize) -> Vec<f32> {
+    let mut msgf = msgf_if::Msgf::new(44100.0, 1024);
+    msgf.init();
+    msgf.recieve_midi_message(0xc0, program, 0);
+    msgf.recieve_midi_message_at(0x90, 60, 100, NOTE_ON_FRAME);
+    msgf.recieve_midi_message_at(0x80, 60, 64, NOTE_OFF_FRAME);
+    let mut out = Vec::with_capacity(TOTAL_FRAMES);
+    let mut abuf_l = vec![0.0_f32; block_size];
+    let mut abuf_r = vec![0.0_f32; block_size];
+    while out.len() < TOTAL_FRAMES {
+        msgf.process(&mut abuf_l, &mut abuf_r, block_size as u32);
+        out.extend_from_slice(&abuf_l);
+    }
+    out.truncate(TOTAL_FRAMES);
+    out
+}
+fn last_sound(wave: &[f32]) -> usize {
+    wave.iter().rposition(|v| v.abs() > 0.0001).unwrap_or(0)
+}
+#[test]
+fn envelopes_do_not_depend_on_block_size() {
+    for program in [0, 3, 7, 8, 9] {
+        let base = render(program, 64);
+        for block_size in [100, 256] {
+            let wave = render(program, block_size);
+            let diff = base.iter().zip(wave.iter()).fold(0.0_f32, |m, (a, b)| m.max((a - b).abs()));
+            assert!(diff < 0.0001, "program {}: block size {} differs by {}", program, block_size, diff);
+            assert_eq!(last_sound(&base), last_sound(&wave), "program {}: release of block size {}", program, block_size);
+        }
+    }
+}
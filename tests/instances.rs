@@ -0,0 +1,57 @@
+//
+//  instances.rs
+//	Musical Sound Generator Framework
+//      Instance Independence Test
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Fails if the sampling freq. or the block size of an instance
+//  changes the sound of another one.
+//
+use msgf::msgf_if;
+
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+const TOTAL_FRAMES: usize = 22050;
+const BLOCK_SIZE: usize = 256;
+fn start(program: u8) -> msgf_if::Msgf {
+    let mut msgf = msgf_if::Msgf::new(44100.0, 1024);
+    msgf.init();
+    msgf.recieve_midi_message(0xc0, program, 0);
+    msgf.recieve_midi_message(0x90, 60, 100);
+    msgf
+}
+fn render(program: u8, other: bool) -> Vec<f32> {
+    let mut msgf = start(program);
+    let mut out = Vec::with_capacity(TOTAL_FRAMES);
+    let mut abuf_l = vec![0.0_f32; BLOCK_SIZE];
+    let mut abuf_r = vec![0.0_f32; BLOCK_SIZE];
+    let mut another = None;
+    while out.len() < TOTAL_FRAMES {
+        msgf.process(&mut abuf_l, &mut abuf_r, BLOCK_SIZE as u32);
+        out.extend_from_slice(&abuf_l);
+        if other && another.is_none() {
+            //  another instance with a different config starts while sounding
+            let mut sub = msgf_if::Msgf::new(22050.0, 64);
+            sub.init();
+            sub.recieve_midi_message(0x90, 72, 100);
+            another = Some(sub);
+        }
+        if let Some(sub) = another.as_mut() {
+            let mut sub_l = [0.0_f32; 64];
+            let mut sub_r = [0.0_f32; 64];
+            sub.process(&mut sub_l, &mut sub_r, 64);
+        }
+    }
+    out
+}
+#[test]
+fn config_is_kept_by_each_instance() {
+    for program in [0, 7, 8] {
+        assert_eq!(render(program, false), render(program, true), "program {}", program);
+    }
+}
@@ -0,0 +1,139 @@
+//
+//  rt_alloc.rs
+//	Musical Sound Generator Framework
+//      Real-time Safety Test
+//
+//  Created on 2026/10/17.
+//  Copyright (c) 2026 Musical Sound Generator Framework contributors.
+//  Released under the MIT license
+//  https://opensource.org/licenses/mit-license.php
+//
+//  Fails if the global allocator is called inside Msgf::process().
+//
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use msgf::msgf_if;
+
+//---------------------------------------------------------
+//		Counting Allocator
+//---------------------------------------------------------
+struct CountingAlloc;
+thread_local! {
+    //  only the thread calling process() is counted
+    static COUNTING: Cell<bool> = const {Cell::new(false)};
+}
+static ALLOC_COUNT: AtomicUsize = AtomicUsize::new(0);
+fn counting() -> bool {COUNTING.try_with(Cell::get).unwrap_or(false)}
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if counting() {ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);}
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if counting() {ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);}
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if counting() {ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);}
+        System.realloc(ptr, layout, new_size)
+    }
+}
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+//---------------------------------------------------------
+//		Test
+//---------------------------------------------------------
+const BLOCK_SIZE: usize = 256;
+fn process_counted(msgf: &mut msgf_if::Msgf, abuf_l: &mut [f32], abuf_r: &mut [f32]) -> usize {
+    let before = ALLOC_COUNT.load(Ordering::Relaxed);
+    COUNTING.with(|c| c.set(true));
+    msgf.process(abuf_l, abuf_r, abuf_l.len() as u32);
+    COUNTING.with(|c| c.set(false));
+    ALLOC_COUNT.load(Ordering::Relaxed) - before
+}
+fn play_program(msgf: &mut msgf_if::Msgf, program: u8) {
+    let mut abuf_l = vec![0.0_f32; BLOCK_SIZE];
+    let mut abuf_r = vec![0.0_f32; BLOCK_SIZE];
+    //  GM System On and Program Change reuse the instruments of the part
+    msgf.recieve_midi_bytes_at(&[0xf0, 0x7e, 0x7f, 0x09, 0x01, 0xf7], 0);
+    let cnt = process_counted(msgf, &mut abuf_l, &mut abuf_r);
+    assert_eq!(cnt, 0, "program {}: allocated in process() by GM System On", program);
+    msgf.recieve_midi_message(0xc0, program, 0);
+    let cnt = process_counted(msgf, &mut abuf_l, &mut abuf_r);
+    assert_eq!(cnt, 0, "program {}: allocated in process() by Program Change", program);
+    let events: [&[u8]; 24] = [
+        &[0x90, 60, 100], &[0x90, 64, 90], &[0x90, 67, 80], &[0x90, 72, 70],
+        &[0x90, 76, 60], &[0x90, 79, 50],                       //  over the polyphony
+        &[0xb0, 1, 64], &[0xb0, 7, 90], &[0xb0, 10, 30], &[0xb0, 11, 100],
+        &[0xe0, 0x00, 0x50], &[0xd0, 80, 0], &[0xa0, 60, 70],
+        &[0xb0, 64, 100], &[0x80, 60, 64], &[0xb0, 64, 127], &[0x80, 64, 64],
+        &[0xb0, 66, 127], &[0xb0, 64, 0], &[0xb0, 66, 0],
+        &[0xb0, 5, 40], &[0xb0, 65, 127], &[0xb0, 84, 48], &[0xb0, 16, 80],
+    ];
+    let mono: [&[u8]; 8] = [
+        &[0xb0, 126, 1], &[0x90, 48, 100], &[0x90, 52, 100], &[0x80, 52, 64],
+        &[0x80, 48, 64], &[0xb0, 127, 0], &[0xb0, 120, 0], &[0xb0, 65, 0],
+    ];
+    for (i, ev) in events.iter().chain(mono.iter()).enumerate() {
+        msgf.recieve_midi_bytes_at(ev, ((i*37) % BLOCK_SIZE) as u32);
+        let cnt = process_counted(msgf, &mut abuf_l, &mut abuf_r);
+        assert_eq!(cnt, 0, "program {}: allocated in process() after {:02x?}", program, ev);
+    }
+    //  all notes off, and voices end in process()
+    for note in [67, 72, 76, 79] {
+        msgf.recieve_midi_message(0x80, note, 64);
+    }
+    for _ in 0..400 {
+        let cnt = process_counted(msgf, &mut abuf_l, &mut abuf_r[..BLOCK_SIZE]);
+        assert_eq!(cnt, 0, "program {}: allocated in process() while releasing", program);
+    }
+    //  short and split buffers
+    msgf.recieve_midi_message_at(0x90, 62, 100, 10);
+    msgf.recieve_midi_message_at(0x80, 62, 64, 50);
+    let cnt = process_counted(msgf, &mut abuf_l[..100], &mut abuf_r[..100]);
+    assert_eq!(cnt, 0, "program {}: allocated in a split buffer", program);
+}
+#[test]
+fn process_does_not_allocate() {
+    let mut msgf = msgf_if::Msgf::new(44100.0, BLOCK_SIZE);
+    msgf.init();
+    msgf.set_part_polyphony(0, 4, msgf_if::VOICE_STEAL_OLDEST);
+    for program in [0, 1, 3, 6, 7, 8, 9] {
+        play_program(&mut msgf, program);
+    }
+}
+#[test]
+fn program_change_while_loading_patch_bank() {
+    //  the patch bank is replaced by another instance in another thread
+    let mut msgf = msgf_if::Msgf::new(44100.0, BLOCK_SIZE);
+    msgf.init();
+    let text = msgf.save_patch_bank();
+    let va_count = text.lines().filter(|line| line.starts_with("[va.")).count();
+    let added = format!("{}\n[va.{}]\nosc.coarse_tune = 12\n", text, va_count);
+    let loader = thread::spawn(move || {
+        let mut host = msgf_if::Msgf::new(44100.0, BLOCK_SIZE);
+        for i in 0..200 {
+            //  the built-in tones are back at last
+            let text = if i % 2 == 0 {&added} else {&text};
+            host.load_patch_bank(text).expect("patch bank");
+        }
+    });
+    let mut abuf_l = vec![0.0_f32; BLOCK_SIZE];
+    let mut abuf_r = vec![0.0_f32; BLOCK_SIZE];
+    let mut program = 0;
+    while !loader.is_finished() || program < 100 {
+        //  Bank 2: user tones of va, the last one exists only in the added bank
+        msgf.recieve_midi_message(0xb0, 0, 2);
+        msgf.recieve_midi_message(0xb0, 32, 0);
+        msgf.recieve_midi_message(0xc0, (program % (va_count+1)) as u8, 0);
+        msgf.recieve_midi_message_at(0x90, 60, 100, 10);
+        msgf.recieve_midi_message_at(0x80, 60, 64, 100);
+        let cnt = process_counted(&mut msgf, &mut abuf_l, &mut abuf_r);
+        assert_eq!(cnt, 0, "program {}: allocated in process() while loading a patch bank", program);
+        program += 1;
+    }
+    loader.join().expect("loader thread");
+}